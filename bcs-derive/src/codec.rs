@@ -0,0 +1,278 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    check_no_bcs_attributes, check_serde_attributes, has_pinned_tags, tagged, variant_tags,
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt, parse_quote, Attribute, Data, DeriveInput, Fields, GenericParam, Index,
+    LifetimeParam, LitStr, Variant,
+};
+
+pub(crate) fn derive_serialize(input: &DeriveInput) -> syn::Result<TokenStream> {
+    check_serde_attributes(&input.attrs, "BcsSerialize")?;
+    check_no_bcs_attributes(&input.attrs)?;
+    let (name, _) = serde_names(&input.ident, &input.attrs)?;
+    let ident = &input.ident;
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::bcs::BcsSerialize));
+    }
+    // Serde implementations for enums with pinned tags.
+    let mut serde_impl = TokenStream::new();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            check_fields_attributes(&data.fields, "BcsSerialize")?;
+            let accessors = data
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match &field.ident {
+                    Some(ident) => quote!(self.#ident),
+                    None => {
+                        let index = Index::from(i);
+                        quote!(self.#index)
+                    }
+                });
+            quote! {
+                encoder.enter_container(#name)?;
+                #(::bcs::BcsSerialize::bcs_serialize(&#accessors, encoder)?;)*
+                encoder.leave_container();
+            }
+        }
+        Data::Enum(data) => {
+            let tags = variant_tags(data)?;
+            if has_pinned_tags(&tags) {
+                serde_impl = tagged::serialize(input, data, &name, &tags)?;
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .push(parse_quote!(Self: ::bcs::__private::DeserializesPinnedTags));
+            }
+            let arms = data
+                .variants
+                .iter()
+                .zip(tags)
+                .map(|(variant, tag)| {
+                    check_serde_attributes(&variant.attrs, "BcsSerialize")?;
+                    check_fields_attributes(&variant.fields, "BcsSerialize")?;
+                    let ident = &variant.ident;
+                    let (pattern, bindings) = variant_bindings(variant);
+                    Ok(quote! {
+                        Self::#ident #pattern => {
+                            encoder.write_variant_index(#tag);
+                            #(::bcs::BcsSerialize::bcs_serialize(#bindings, encoder)?;)*
+                        }
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                encoder.enter_container(#name)?;
+                match self {
+                    #(#arms)*
+                }
+                encoder.leave_container();
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "`#[derive(BcsSerialize)]` does not support unions",
+            ))
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #serde_impl
+
+        impl #impl_generics ::bcs::BcsSerialize for #ident #ty_generics #where_clause {
+            fn bcs_serialize(
+                &self,
+                encoder: &mut ::bcs::codec::Encoder<'_>,
+            ) -> ::bcs::Result<()> {
+                #body
+                ::std::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+pub(crate) fn derive_deserialize(input: &DeriveInput) -> syn::Result<TokenStream> {
+    check_serde_attributes(&input.attrs, "BcsDeserialize")?;
+    check_no_bcs_attributes(&input.attrs)?;
+    let (_, name) = serde_names(&input.ident, &input.attrs)?;
+    let ident = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    // Borrowed fields may live as long as the input.
+    let mut generics = input.generics.clone();
+    let de_lifetime: LifetimeParam = {
+        let lifetimes = input.generics.lifetimes().map(|param| &param.lifetime);
+        parse_quote!('__de: #(#lifetimes)+*)
+    };
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::bcs::BcsDeserialize<'__de>));
+    }
+    generics
+        .params
+        .insert(0, GenericParam::Lifetime(de_lifetime));
+    let mut serde_impl = TokenStream::new();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            check_fields_attributes(&data.fields, "BcsDeserialize")?;
+            let construct = construct(quote!(Self), &data.fields);
+            quote! {
+                decoder.enter_container(#name)?;
+                let value = #construct;
+                decoder.leave_container();
+            }
+        }
+        Data::Enum(data) => {
+            let tags = variant_tags(data)?;
+            if has_pinned_tags(&tags) {
+                serde_impl = tagged::deserialize(input, data, &name, &tags)?;
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .push(parse_quote!(Self: ::bcs::__private::SerializesPinnedTags));
+            }
+            let arms = data
+                .variants
+                .iter()
+                .zip(&tags)
+                .map(|(variant, tag)| {
+                    check_serde_attributes(&variant.attrs, "BcsDeserialize")?;
+                    check_fields_attributes(&variant.fields, "BcsDeserialize")?;
+                    let ident = &variant.ident;
+                    let construct = construct(quote!(Self::#ident), &variant.fields);
+                    Ok(quote!(#tag => #construct,))
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            // Without pinned tags, fail as `#[derive(Deserialize)]` does.
+            let unknown_variant = if !has_pinned_tags(&tags) {
+                let count = tags.len();
+                quote!(::bcs::codec::Decoder::unknown_variant(index, #count))
+            } else {
+                quote!(::bcs::codec::Decoder::unknown_variant_tag(index, &[#(#tags),*]))
+            };
+            quote! {
+                decoder.enter_container(#name)?;
+                let value = match decoder.read_variant_index()? {
+                    #(#arms)*
+                    index => {
+                        return ::std::result::Result::Err(#unknown_variant)
+                    }
+                };
+                decoder.leave_container();
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "`#[derive(BcsDeserialize)]` does not support unions",
+            ))
+        }
+    };
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #serde_impl
+
+        impl #impl_generics ::bcs::BcsDeserialize<'__de> for #ident #ty_generics #where_clause {
+            fn bcs_deserialize(
+                decoder: &mut ::bcs::codec::Decoder<'__de>,
+            ) -> ::bcs::Result<Self> {
+                #body
+                ::std::result::Result::Ok(value)
+            }
+        }
+    })
+}
+
+fn check_fields_attributes(fields: &Fields, derive: &str) -> syn::Result<()> {
+    fields.iter().try_for_each(|field| {
+        check_serde_attributes(&field.attrs, derive)?;
+        check_no_bcs_attributes(&field.attrs)
+    })
+}
+
+/// The pattern binding the fields of a variant, and the bindings in order.
+fn variant_bindings(variant: &Variant) -> (TokenStream, Vec<syn::Ident>) {
+    let bindings: Vec<_> = (0..variant.fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect();
+    let pattern = match &variant.fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|f| &f.ident);
+            quote!({ #(#names: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+        Fields::Unit => quote!(),
+    };
+    (pattern, bindings)
+}
+
+/// An expression decoding the fields in order and building `path` from them.
+fn construct(path: TokenStream, fields: &Fields) -> TokenStream {
+    let decode = quote!(::bcs::BcsDeserialize::bcs_deserialize(decoder)?);
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|f| &f.ident);
+            quote!(#path { #(#names: #decode),* })
+        }
+        Fields::Unnamed(fields) => {
+            let decodes = fields.unnamed.iter().map(|_| &decode);
+            quote!(#path ( #(#decodes),* ))
+        }
+        Fields::Unit => path,
+    }
+}
+
+/// The names under which serde serializes and deserializes a container, a variant or a
+/// field. Container names show in container depth errors.
+pub(crate) fn serde_names(
+    ident: &syn::Ident,
+    attrs: &[Attribute],
+) -> syn::Result<(String, String)> {
+    let name = ident.unraw().to_string();
+    let (mut serialize, mut deserialize) = (name.clone(), name);
+    for attr in serde_attributes(attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                if meta.input.peek(syn::Token![=]) {
+                    let name = meta.value()?.parse::<LitStr>()?.value();
+                    serialize = name.clone();
+                    deserialize = name;
+                } else {
+                    meta.parse_nested_meta(|meta| {
+                        let name = meta.value()?.parse::<LitStr>()?.value();
+                        if meta.path.is_ident("serialize") {
+                            serialize = name;
+                        } else if meta.path.is_ident("deserialize") {
+                            deserialize = name;
+                        }
+                        Ok(())
+                    })?;
+                }
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let _content;
+                syn::parenthesized!(_content in meta.input);
+            }
+            Ok(())
+        })?;
+    }
+    Ok((serialize, deserialize))
+}
+
+fn serde_attributes(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("serde"))
+}
@@ -0,0 +1,180 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Derive macros for the `bcs` crate. Use them through `bcs` with the feature `derive`
+//! rather than depending on this crate directly.
+
+extern crate proc_macro;
+
+mod codec;
+mod marker;
+mod size;
+mod tagged;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, Attribute, DataEnum, DeriveInput, LitInt};
+
+/// Derive `bcs::BcsSize` for a struct or an enum.
+///
+/// The computed sizes follow the layout produced by `#[derive(Serialize)]`. Serde
+/// attributes that change this layout (e.g. `skip` or `with`) are rejected. Variant tags
+/// pinned with `#[bcs(tag = N)]` are taken into account, as in `BcsSerialize`, which must
+/// then be derived as well.
+#[proc_macro_derive(BcsSize, attributes(bcs))]
+pub fn derive_bcs_size(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    size::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `bcs::BcsCompatible` for a struct or an enum whose fields are all
+/// `BcsCompatible`, failing to compile otherwise. Enums with pinned tags must also derive
+/// `BcsSerialize` and `BcsDeserialize`.
+#[proc_macro_derive(BcsCompatible, attributes(bcs))]
+pub fn derive_bcs_compatible(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    marker::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `bcs::BcsSerialize` for a struct or an enum.
+///
+/// The encoding is the same as that of `#[derive(Serialize)]` with `bcs::to_bytes`. Serde
+/// attributes that change this layout (e.g. `skip` or `with`) are rejected.
+///
+/// Enum variants are written with their index, unless a tag is pinned with
+/// `#[bcs(tag = N)]`. Variants without an explicit tag take the tag of the previous
+/// variant plus one, so that variants can be removed or reordered without changing the
+/// tags of the others. Serde's derives would ignore these tags, so on enums with pinned
+/// tags this derive also implements `Serialize`, with the tags as variant indices.
+/// `BcsDeserialize` must then be derived as well, and serde's derives cannot be.
+#[proc_macro_derive(BcsSerialize, attributes(bcs, serde))]
+pub fn derive_bcs_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    codec::derive_serialize(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `bcs::BcsDeserialize` for a struct or an enum.
+///
+/// The decoding, errors included, is the same as that of `#[derive(Deserialize)]` with
+/// `bcs::from_bytes`. Serde attributes that change this layout (e.g. `other` or `default`)
+/// are rejected. Variant tags pinned with `#[bcs(tag = N)]` are mapped back to their
+/// variants, see `BcsSerialize`.
+/// On enums with pinned tags, this derive also implements `Deserialize`, which accepts
+/// the tags or the variant names as identifiers.
+#[proc_macro_derive(BcsDeserialize, attributes(bcs, serde))]
+pub fn derive_bcs_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    codec::derive_deserialize(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Serde attributes under which the layout of a type no longer follows its definition.
+const LAYOUT_CHANGING_SERDE_ATTRIBUTES: &[&str] = &[
+    "default",
+    "deserialize_with",
+    "flatten",
+    "from",
+    "into",
+    "other",
+    "remote",
+    "serialize_with",
+    "skip",
+    "skip_deserializing",
+    "skip_serializing",
+    "skip_serializing_if",
+    "tag",
+    "transparent",
+    "try_from",
+    "untagged",
+    "with",
+];
+
+/// Fail on serde attributes that our derives cannot follow.
+fn check_serde_attributes(attrs: &[Attribute], derive: &str) -> syn::Result<()> {
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if let Some(name) = LAYOUT_CHANGING_SERDE_ATTRIBUTES
+                .iter()
+                .find(|name| meta.path.is_ident(name))
+            {
+                return Err(meta.error(format!(
+                    "`#[serde({})]` is not supported by `#[derive({})]`",
+                    name, derive
+                )));
+            }
+            // Skip the value of attributes such as `rename = "..."`.
+            if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let _content;
+                syn::parenthesized!(_content in meta.input);
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// Fail on `#[bcs(...)]` attributes, which are only supported on enum variants.
+fn check_no_bcs_attributes(attrs: &[Attribute]) -> syn::Result<()> {
+    match attrs.iter().find(|attr| attr.path().is_ident("bcs")) {
+        Some(attr) => Err(syn::Error::new_spanned(
+            attr,
+            "`#[bcs(...)]` attributes are only supported on enum variants",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The tags of the variants of an enum. Variants without `#[bcs(tag = N)]` take the tag of
+/// the previous variant plus one, starting from 0.
+fn variant_tags(data: &DataEnum) -> syn::Result<Vec<u32>> {
+    let mut tags = Vec::with_capacity(data.variants.len());
+    let mut next_tag = Some(0u32);
+    for variant in &data.variants {
+        let tag = match pinned_tag(&variant.attrs)? {
+            Some(tag) => tag,
+            None => next_tag.ok_or_else(|| {
+                syn::Error::new_spanned(&variant.ident, "variant tag overflows u32")
+            })?,
+        };
+        if tags.contains(&tag) {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                format!("duplicate variant tag {}", tag),
+            ));
+        }
+        tags.push(tag);
+        next_tag = tag.checked_add(1);
+    }
+    Ok(tags)
+}
+
+fn pinned_tag(attrs: &[Attribute]) -> syn::Result<Option<u32>> {
+    let mut tag = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("bcs")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") {
+                tag = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u32>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `bcs` attribute, expected `tag = N`"))
+            }
+        })?;
+    }
+    Ok(tag)
+}
+
+/// Whether the tags differ from the declaration indices of the variants.
+fn has_pinned_tags(tags: &[u32]) -> bool {
+    !tags.iter().copied().eq(0..tags.len() as u32)
+}
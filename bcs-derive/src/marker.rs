@@ -0,0 +1,67 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{check_no_bcs_attributes, check_serde_attributes, has_pinned_tags, variant_tags};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{parse_quote, spanned::Spanned, Data, DeriveInput, Fields};
+
+pub(crate) fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    check_serde_attributes(&input.attrs, "BcsCompatible")?;
+    check_no_bcs_attributes(&input.attrs)?;
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::bcs::BcsCompatible));
+    }
+    let fields: Vec<&Fields> = match &input.data {
+        Data::Struct(data) => vec![&data.fields],
+        Data::Enum(data) => {
+            // Tags do not change compatibility, as long as serde writes them too.
+            if has_pinned_tags(&variant_tags(data)?) {
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .extend::<[syn::WherePredicate; 2]>([
+                        parse_quote!(Self: ::bcs::__private::SerializesPinnedTags),
+                        parse_quote!(Self: ::bcs::__private::DeserializesPinnedTags),
+                    ]);
+            }
+            data.variants
+                .iter()
+                .map(|variant| {
+                    check_serde_attributes(&variant.attrs, "BcsCompatible")?;
+                    Ok(&variant.fields)
+                })
+                .collect::<syn::Result<_>>()?
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "`#[derive(BcsCompatible)]` does not support unions",
+            ))
+        }
+    };
+    // Report unsupported types on the fields that use them.
+    let assertions = fields
+        .into_iter()
+        .flatten()
+        .map(|field| {
+            check_serde_attributes(&field.attrs, "BcsCompatible")?;
+            check_no_bcs_attributes(&field.attrs)?;
+            let ty = &field.ty;
+            Ok(quote_spanned! {ty.span()=>
+                ::bcs::__private::assert_bcs_compatible::<#ty>();
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bcs::BcsCompatible for #name #ty_generics #where_clause {
+            fn __assert_fields_are_bcs_compatible() {
+                #(#assertions)*
+            }
+        }
+    })
+}
@@ -0,0 +1,120 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{check_no_bcs_attributes, check_serde_attributes, has_pinned_tags, variant_tags};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Data, DeriveInput, Fields, Index};
+
+pub(crate) fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    check_serde_attributes(&input.attrs, "BcsSize")?;
+    check_no_bcs_attributes(&input.attrs)?;
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::bcs::BcsSize));
+    }
+    let body = match &input.data {
+        Data::Struct(data) => {
+            check_fields_attributes(&data.fields)?;
+            let fixed_size = fixed_size(&data.fields);
+            let accessors = data
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match &field.ident {
+                    Some(ident) => quote!(self.#ident),
+                    None => {
+                        let index = Index::from(i);
+                        quote!(self.#index)
+                    }
+                });
+            quote! {
+                const FIXED_SIZE: ::std::option::Option<usize> = #fixed_size;
+
+                fn bcs_size(&self) -> usize {
+                    if let ::std::option::Option::Some(size) = Self::FIXED_SIZE {
+                        return size;
+                    }
+                    0 #(+ ::bcs::BcsSize::bcs_size(&#accessors))*
+                }
+            }
+        }
+        Data::Enum(data) => {
+            let tags = variant_tags(data)?;
+            // The sizes must agree with the serde encoding.
+            if has_pinned_tags(&tags) {
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .push(parse_quote!(Self: ::bcs::__private::SerializesPinnedTags));
+            }
+            let arms = data
+                .variants
+                .iter()
+                .zip(tags)
+                .map(|(variant, tag)| {
+                    check_serde_attributes(&variant.attrs, "BcsSize")?;
+                    check_fields_attributes(&variant.fields)?;
+                    let ident = &variant.ident;
+                    let bindings: Vec<_> = (0..variant.fields.len())
+                        .map(|i| format_ident!("__field{}", i))
+                        .collect();
+                    let pattern = match &variant.fields {
+                        Fields::Named(fields) => {
+                            let names = fields.named.iter().map(|f| &f.ident);
+                            quote!({ #(#names: #bindings),* })
+                        }
+                        Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+                        Fields::Unit => quote!(),
+                    };
+                    Ok(quote! {
+                        Self::#ident #pattern => {
+                            ::bcs::__private::uleb128_size(#tag)
+                                #(+ ::bcs::BcsSize::bcs_size(#bindings))*
+                        }
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                fn bcs_size(&self) -> usize {
+                    match self {
+                        #(#arms)*
+                    }
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "`#[derive(BcsSize)]` does not support unions",
+            ))
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bcs::BcsSize for #name #ty_generics #where_clause {
+            #body
+        }
+    })
+}
+
+fn check_fields_attributes(fields: &Fields) -> syn::Result<()> {
+    fields.iter().try_for_each(|field| {
+        check_serde_attributes(&field.attrs, "BcsSize")?;
+        check_no_bcs_attributes(&field.attrs)
+    })
+}
+
+/// The sum of the fixed sizes of the fields, if they all have one.
+fn fixed_size(fields: &Fields) -> TokenStream {
+    fields
+        .iter()
+        .fold(quote!(::std::option::Option::Some(0)), |size, field| {
+            let ty = &field.ty;
+            quote! {
+                ::bcs::__private::add_fixed_sizes(#size, <#ty as ::bcs::BcsSize>::FIXED_SIZE)
+            }
+        })
+}
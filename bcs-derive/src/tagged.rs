@@ -0,0 +1,418 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Serde implementations for enums with pinned variant tags.
+//!
+//! `#[derive(Serialize, Deserialize)]` would write the declaration indices of the variants,
+//! so `BcsSerialize` and `BcsDeserialize` implement serde's traits themselves for these
+//! enums, with the tags as variant indices. Serializers then agree with `bcs::codec`.
+
+use crate::codec::serde_names;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, DataEnum, DeriveInput, Fields, FieldsNamed, GenericParam, LifetimeParam};
+
+/// Implement `Serialize` with the variant tags `tags`.
+pub(crate) fn serialize(
+    input: &DeriveInput,
+    data: &DataEnum,
+    name: &str,
+    tags: &[u32],
+) -> syn::Result<TokenStream> {
+    check_no_rename_all(input)?;
+    let ident = &input.ident;
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::bcs::__private::serde::Serialize));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (marker_generics, _, _) = input.generics.split_for_impl();
+
+    let mut arms = Vec::new();
+    for (variant, tag) in data.variants.iter().zip(tags) {
+        let variant_ident = &variant.ident;
+        let (variant_name, _) = serde_names(variant_ident, &variant.attrs)?;
+        let bindings = bindings(&variant.fields);
+        arms.push(match &variant.fields {
+            Fields::Unit => quote! {
+                Self::#variant_ident => ::bcs::__private::serde::Serializer::serialize_unit_variant(
+                    serializer, #name, #tag, #variant_name,
+                ),
+            },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote! {
+                Self::#variant_ident(__field0) => {
+                    ::bcs::__private::serde::Serializer::serialize_newtype_variant(
+                        serializer, #name, #tag, #variant_name, __field0,
+                    )
+                }
+            },
+            Fields::Unnamed(_) => {
+                let len = bindings.len();
+                quote! {
+                    Self::#variant_ident(#(#bindings),*) => {
+                        use ::bcs::__private::serde::ser::SerializeTupleVariant as _;
+                        let mut state = ::bcs::__private::serde::Serializer::serialize_tuple_variant(
+                            serializer, #name, #tag, #variant_name, #len,
+                        )?;
+                        #(state.serialize_field(#bindings)?;)*
+                        state.end()
+                    }
+                }
+            }
+            Fields::Named(fields) => {
+                let len = bindings.len();
+                let names: Vec<_> = fields.named.iter().map(|f| &f.ident).collect();
+                let keys = field_names(fields)?.into_iter().map(|(key, _)| key);
+                quote! {
+                    Self::#variant_ident { #(#names: #bindings),* } => {
+                        use ::bcs::__private::serde::ser::SerializeStructVariant as _;
+                        let mut state = ::bcs::__private::serde::Serializer::serialize_struct_variant(
+                            serializer, #name, #tag, #variant_name, #len,
+                        )?;
+                        #(state.serialize_field(#keys, #bindings)?;)*
+                        state.end()
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::bcs::__private::serde::Serialize for #ident #ty_generics #where_clause {
+            fn serialize<__S>(
+                &self,
+                serializer: __S,
+            ) -> ::std::result::Result<__S::Ok, __S::Error>
+            where
+                __S: ::bcs::__private::serde::Serializer,
+            {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+
+        impl #marker_generics ::bcs::__private::SerializesPinnedTags for #ident #ty_generics {}
+    })
+}
+
+/// Implement `Deserialize` mapping the variant tags `tags` back to their variants.
+///
+/// Variants can also be identified by their names, as in self-describing formats. Tuple
+/// variants are read from sequences; struct variants from sequences or maps.
+pub(crate) fn deserialize(
+    input: &DeriveInput,
+    data: &DataEnum,
+    name: &str,
+    tags: &[u32],
+) -> syn::Result<TokenStream> {
+    check_no_rename_all(input)?;
+    let ident = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let (marker_generics, _, _) = input.generics.split_for_impl();
+
+    // Borrowed fields may live as long as the input.
+    let mut generics = input.generics.clone();
+    let de_lifetime: LifetimeParam = {
+        let lifetimes = input.generics.lifetimes().map(|param| &param.lifetime);
+        parse_quote!('__de: #(#lifetimes)+*)
+    };
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::bcs::__private::serde::Deserialize<'__de>));
+    }
+    generics
+        .params
+        .insert(0, GenericParam::Lifetime(de_lifetime));
+    let (impl_generics, visitor_generics, where_clause) = generics.split_for_impl();
+    let variant_names = data
+        .variants
+        .iter()
+        .map(|variant| Ok(serde_names(&variant.ident, &variant.attrs)?.1))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let expected_tags = format!(
+        "one of the variant tags {}",
+        tags.iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let mut enum_arms = Vec::new();
+    let mut seq_arms = Vec::new();
+    let mut map_arms = Vec::new();
+    for (variant, tag) in data.variants.iter().zip(tags) {
+        let variant_ident = &variant.ident;
+        let bindings = bindings(&variant.fields);
+        let len = bindings.len();
+        let construct = match &variant.fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|f| &f.ident);
+                quote!(#ident::#variant_ident { #(#names: #bindings),* })
+            }
+            Fields::Unnamed(_) => quote!(#ident::#variant_ident(#(#bindings),*)),
+            Fields::Unit => quote!(#ident::#variant_ident),
+        };
+        let expecting = format!("variant {}::{} with {} fields", ident, variant_ident, len);
+        enum_arms.push(match &variant.fields {
+            Fields::Unit => quote! {
+                #tag => {
+                    ::bcs::__private::serde::de::VariantAccess::unit_variant(variant)?;
+                    ::std::result::Result::Ok(#construct)
+                }
+            },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote! {
+                #tag => ::bcs::__private::serde::de::VariantAccess::newtype_variant(variant)
+                    .map(#ident::#variant_ident),
+            },
+            Fields::Unnamed(_) => quote! {
+                #tag => ::bcs::__private::serde::de::VariantAccess::tuple_variant(
+                    variant,
+                    #len,
+                    __Visitor { tag: #tag, marker: ::std::marker::PhantomData },
+                ),
+            },
+            Fields::Named(fields) => {
+                let keys = field_names(fields)?.into_iter().map(|(_, key)| key);
+                quote! {
+                    #tag => ::bcs::__private::serde::de::VariantAccess::struct_variant(
+                        variant,
+                        &[#(#keys),*],
+                        __Visitor { tag: #tag, marker: ::std::marker::PhantomData },
+                    ),
+                }
+            }
+        });
+        if len == 0 {
+            continue;
+        }
+        let elements = bindings.iter().enumerate().map(|(i, binding)| {
+            quote! {
+                let #binding = match seq.next_element()? {
+                    ::std::option::Option::Some(value) => value,
+                    ::std::option::Option::None => {
+                        return ::std::result::Result::Err(
+                            ::bcs::__private::serde::de::Error::invalid_length(#i, &#expecting),
+                        )
+                    }
+                };
+            }
+        });
+        seq_arms.push(quote! {
+            #tag => {
+                #(#elements)*
+                ::std::result::Result::Ok(#construct)
+            }
+        });
+        if let Fields::Named(fields) = &variant.fields {
+            let keys: Vec<_> = field_names(fields)?
+                .into_iter()
+                .map(|(_, key)| key)
+                .collect();
+            map_arms.push(quote! {
+                #tag => {
+                    #(let mut #bindings = ::std::option::Option::None;)*
+                    while let ::std::option::Option::Some(key) =
+                        map.next_key::<::std::string::String>()?
+                    {
+                        match key.as_str() {
+                            #(#keys => {
+                                if #bindings.is_some() {
+                                    return ::std::result::Result::Err(
+                                        ::bcs::__private::serde::de::Error::duplicate_field(#keys),
+                                    );
+                                }
+                                #bindings = ::std::option::Option::Some(map.next_value()?);
+                            })*
+                            _ => {
+                                map.next_value::<::bcs::__private::serde::de::IgnoredAny>()?;
+                            }
+                        }
+                    }
+                    #(let #bindings = #bindings.ok_or_else(|| {
+                        ::bcs::__private::serde::de::Error::missing_field(#keys)
+                    })?;)*
+                    ::std::result::Result::Ok(#construct)
+                }
+            });
+        }
+    }
+    let name_arms = variant_names.iter().zip(tags).map(
+        |(variant_name, tag)| quote!(#variant_name => ::std::result::Result::Ok(__Tag(#tag)),),
+    );
+
+    Ok(quote! {
+        impl #impl_generics ::bcs::__private::serde::Deserialize<'__de> for #ident #ty_generics #where_clause {
+            fn deserialize<__D>(deserializer: __D) -> ::std::result::Result<Self, __D::Error>
+            where
+                __D: ::bcs::__private::serde::Deserializer<'__de>,
+            {
+                const VARIANTS: &[&str] = &[#(#variant_names),*];
+                const TAGS: &[u32] = &[#(#tags),*];
+
+                struct __Tag(u32);
+
+                impl<'de> ::bcs::__private::serde::Deserialize<'de> for __Tag {
+                    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+                    where
+                        D: ::bcs::__private::serde::Deserializer<'de>,
+                    {
+                        deserializer.deserialize_identifier(__TagVisitor)
+                    }
+                }
+
+                struct __TagVisitor;
+
+                impl<'de> ::bcs::__private::serde::de::Visitor<'de> for __TagVisitor {
+                    type Value = __Tag;
+
+                    fn expecting(
+                        &self,
+                        formatter: &mut ::std::fmt::Formatter<'_>,
+                    ) -> ::std::fmt::Result {
+                        formatter.write_str("variant identifier")
+                    }
+
+                    fn visit_u64<E>(self, value: u64) -> ::std::result::Result<__Tag, E>
+                    where
+                        E: ::bcs::__private::serde::de::Error,
+                    {
+                        ::bcs::__private::variant_tag(#name, TAGS, value)
+                            .map(__Tag)
+                            .ok_or_else(|| {
+                                E::invalid_value(
+                                    ::bcs::__private::serde::de::Unexpected::Unsigned(value),
+                                    &#expected_tags,
+                                )
+                            })
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> ::std::result::Result<__Tag, E>
+                    where
+                        E: ::bcs::__private::serde::de::Error,
+                    {
+                        match value {
+                            #(#name_arms)*
+                            _ => ::std::result::Result::Err(E::unknown_variant(value, VARIANTS)),
+                        }
+                    }
+                }
+
+                struct __Visitor #impl_generics #where_clause {
+                    tag: u32,
+                    marker: ::std::marker::PhantomData<(
+                        &'__de (),
+                        fn() -> #ident #ty_generics,
+                    )>,
+                }
+
+                impl #impl_generics ::bcs::__private::serde::de::Visitor<'__de>
+                    for __Visitor #visitor_generics #where_clause
+                {
+                    type Value = #ident #ty_generics;
+
+                    fn expecting(
+                        &self,
+                        formatter: &mut ::std::fmt::Formatter<'_>,
+                    ) -> ::std::fmt::Result {
+                        formatter.write_str(concat!("enum ", stringify!(#ident)))
+                    }
+
+                    fn visit_enum<A>(self, data: A) -> ::std::result::Result<Self::Value, A::Error>
+                    where
+                        A: ::bcs::__private::serde::de::EnumAccess<'__de>,
+                    {
+                        let (__Tag(tag), variant) = data.variant()?;
+                        match tag {
+                            #(#enum_arms)*
+                            _ => unreachable!("tags are checked when deserialized"),
+                        }
+                    }
+
+                    #[allow(unused_mut)]
+                    fn visit_seq<A>(self, mut seq: A) -> ::std::result::Result<Self::Value, A::Error>
+                    where
+                        A: ::bcs::__private::serde::de::SeqAccess<'__de>,
+                    {
+                        match self.tag {
+                            #(#seq_arms)*
+                            _ => ::std::result::Result::Err(
+                                ::bcs::__private::serde::de::Error::invalid_type(
+                                    ::bcs::__private::serde::de::Unexpected::Seq,
+                                    &self,
+                                ),
+                            ),
+                        }
+                    }
+
+                    #[allow(unused_mut)]
+                    fn visit_map<A>(self, mut map: A) -> ::std::result::Result<Self::Value, A::Error>
+                    where
+                        A: ::bcs::__private::serde::de::MapAccess<'__de>,
+                    {
+                        match self.tag {
+                            #(#map_arms)*
+                            _ => ::std::result::Result::Err(
+                                ::bcs::__private::serde::de::Error::invalid_type(
+                                    ::bcs::__private::serde::de::Unexpected::Map,
+                                    &self,
+                                ),
+                            ),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_enum(
+                    #name,
+                    VARIANTS,
+                    __Visitor { tag: 0, marker: ::std::marker::PhantomData },
+                )
+            }
+        }
+
+        impl #marker_generics ::bcs::__private::DeserializesPinnedTags for #ident #ty_generics {}
+    })
+}
+
+/// The serialization and deserialization names of the fields.
+fn field_names(fields: &FieldsNamed) -> syn::Result<Vec<(String, String)>> {
+    fields
+        .named
+        .iter()
+        .map(|field| serde_names(field.ident.as_ref().unwrap(), &field.attrs))
+        .collect()
+}
+
+/// Fail on `#[serde(rename_all = "...")]`, which the generated implementations do not follow.
+fn check_no_rename_all(input: &DeriveInput) -> syn::Result<()> {
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                return Err(
+                    meta.error("`#[serde(rename_all)]` is not supported on enums with pinned tags")
+                );
+            }
+            if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let _content;
+                syn::parenthesized!(_content in meta.input);
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+fn bindings(fields: &Fields) -> Vec<syn::Ident> {
+    (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect()
+}
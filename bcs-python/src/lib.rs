@@ -0,0 +1,643 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Python bindings, built with `pyo3`, to encode and decode BCS against a format registry.
+//!
+//! The Python module `bcs_python` defines a class `Schema`, created from a registry
+//! serialized in JSON, as produced by `serde_json::to_string(&registry)`, and the name of one
+//! of its containers. Values are native Python objects:
+//!
+//! | Format                                      | Python                                 |
+//! |---------------------------------------------|----------------------------------------|
+//! | `Unit`, unit structs                        | `None`                                 |
+//! | `Bool`                                      | `bool`                                 |
+//! | integers                                    | `int`                                  |
+//! | `Str`                                       | `str`                                  |
+//! | `Bytes`, sequences and arrays of `U8`       | `bytes` (`bytearray` is also accepted) |
+//! | `Option`                                    | `None` or the value                    |
+//! | `Seq`, `TupleArray`                         | `list` (`tuple` is also accepted)      |
+//! | `Tuple`, tuple structs                      | `tuple` (`list` is also accepted)      |
+//! | `Map`                                       | `dict`, see below for its keys         |
+//! | newtype structs                             | the inner value                        |
+//! | structs                                     | `dict` with exactly the fields         |
+//! | enums                                       | `{"Variant": payload}`                 |
+//!
+//! Map keys must be hashable, so keys that would be a `list` or a `dict` are decoded as a
+//! `tuple`, recursively: a `tuple` of the items for lists, and a `tuple` of the `(key,
+//! value)` pairs for dicts, such as those of structs and enums. These tuples are accepted
+//! wherever a `list` or a `dict` is.
+//!
+//! Values are transcoded through [`bcs::json`], so that bytes are checked by the same code
+//! as [`bcs::from_bytes`]: Python and Rust agree on which inputs are valid. Failures raise
+//! `bcs_python.BcsError`, a subclass of `ValueError` whose attribute `code` is the stable
+//! numeric value of the [`bcs::ErrorCode`] of the error.
+//!
+//! ```python
+//! from bcs_python import Schema
+//!
+//! schema = Schema(registry_json, "Transfer")
+//! data = schema.encode({"Coins": {"amount": 100, "to": b"\xab\xcd"}})
+//! assert schema.decode(data) == {"Coins": {"amount": 100, "to": b"\xab\xcd"}}
+//! ```
+//!
+//! The extension module is built with `maturin build --release` from this directory, which
+//! enables the `extension-module` feature of pyo3.
+
+use bcs::{
+    __private::hex,
+    json::{bcs_to_json, json_to_bcs},
+    Error, Result,
+};
+use pyo3::{
+    create_exception,
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBool, PyByteArray, PyBytes, PyDict, PyInt, PyList, PyString, PyTuple},
+    BoundObject,
+};
+use serde_json::{Map, Value};
+use serde_reflection::{ContainerFormat, Format, Named, Registry, VariantFormat};
+use std::{collections::BTreeMap, str::FromStr};
+
+create_exception!(
+    bcs_python,
+    BcsError,
+    PyValueError,
+    "An error raised while encoding or decoding BCS. The attribute `code` is the numeric \
+     value of the error code."
+);
+
+/// A format registry together with the container to encode and decode.
+// Formats are not `Send`, so a schema can only be used by the thread that created it.
+#[pyclass(frozen, unsendable, module = "bcs_python")]
+pub struct Schema {
+    format: Format,
+    registry: Registry,
+}
+
+#[pymethods]
+impl Schema {
+    /// Create a schema from a registry serialized in JSON and the name of one of its
+    /// containers.
+    #[new]
+    fn new(py: Python<'_>, registry: &str, type_name: &str) -> PyResult<Self> {
+        let registry: Registry = serde_json::from_str(registry)
+            .map_err(|e| error(py, Error::Custom(format!("invalid registry: {}", e))))?;
+        if !registry.contains_key(type_name) {
+            return Err(error(
+                py,
+                Error::Custom(format!("unknown container {}", type_name)),
+            ));
+        }
+        Ok(Schema {
+            format: Format::TypeName(type_name.to_string()),
+            registry,
+        })
+    }
+
+    /// Encode a Python value in BCS.
+    fn encode<'py>(&self, value: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyBytes>> {
+        let py = value.py();
+        let bytes = self.to_bytes(value).map_err(|e| error(py, e))?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    /// Decode BCS bytes to a Python value. The bytes must be canonical and consumed
+    /// entirely.
+    fn decode(&self, py: Python<'_>, data: &[u8]) -> PyResult<PyObject> {
+        bcs_to_json(data, &self.format, &self.registry)
+            .and_then(|value| {
+                let decoder = Decoder {
+                    py,
+                    registry: &self.registry,
+                };
+                decoder.decode(&value, &self.format)
+            })
+            .map_err(|e| error(py, e))
+    }
+
+    /// The number of bytes of the BCS encoding of a Python value.
+    fn serialized_size(&self, value: &Bound<'_, PyAny>) -> PyResult<usize> {
+        let bytes = self.to_bytes(value).map_err(|e| error(value.py(), e))?;
+        Ok(bytes.len())
+    }
+}
+
+impl Schema {
+    fn to_bytes(&self, value: &Bound<'_, PyAny>) -> Result<Vec<u8>> {
+        let mut encoder = Encoder {
+            registry: &self.registry,
+            max_remaining_depth: bcs::MAX_CONTAINER_DEPTH,
+        };
+        let value = encoder.encode(value, &self.format)?;
+        json_to_bcs(&value, &self.format, &self.registry)
+    }
+}
+
+/// The Python module `bcs_python`.
+#[pymodule]
+pub fn bcs_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Schema>()?;
+    m.add("BcsError", m.py().get_type::<BcsError>())?;
+    Ok(())
+}
+
+fn error(py: Python<'_>, error: Error) -> PyErr {
+    let exception = BcsError::new_err(error.to_string());
+    match exception.value(py).setattr("code", error.code().as_u16()) {
+        Ok(()) => exception,
+        Err(e) => e,
+    }
+}
+
+fn container<'a>(registry: &'a Registry, name: &str) -> Result<&'a ContainerFormat> {
+    registry
+        .get(name)
+        .ok_or_else(|| Error::Custom(format!("unknown container {}", name)))
+}
+
+fn is_bytes(format: &Format) -> bool {
+    match format {
+        Format::Bytes => true,
+        Format::Seq(content) | Format::TupleArray { content, .. } => **content == Format::U8,
+        _ => false,
+    }
+}
+
+fn unsupported(format: &Format) -> Error {
+    match format {
+        Format::F32 => Error::NotSupported("f32"),
+        Format::F64 => Error::NotSupported("f64"),
+        Format::Char => Error::NotSupported("char"),
+        _ => Error::Custom(format!("unsupported format {:?}", format)),
+    }
+}
+
+fn unknown_variant_format(variant: &Named<VariantFormat>) -> Error {
+    Error::Custom(format!("unknown format of variant {}", variant.name))
+}
+
+fn python_error(error: PyErr) -> Error {
+    Error::Custom(error.to_string())
+}
+
+/// Converts Python values to JSON values following the conventions of [`bcs::json`].
+struct Encoder<'a> {
+    registry: &'a Registry,
+    max_remaining_depth: usize,
+}
+
+impl<'a> Encoder<'a> {
+    // Recursive functions only dispatch, to keep their stack frames small.
+    fn encode(&mut self, value: &Bound<'_, PyAny>, format: &Format) -> Result<Value> {
+        match format {
+            Format::TupleArray { content, size } if **content == Format::U8 => {
+                let bytes = bytes(value)?;
+                if bytes.len() != *size {
+                    return Err(invalid(value, &format!("{} bytes", size)));
+                }
+                Ok(Value::String(hex::encode(&bytes)))
+            }
+            format if is_bytes(format) => Ok(Value::String(hex::encode(&bytes(value)?))),
+            Format::TypeName(name) => self.encode_container(value, name),
+            Format::Option(format) => {
+                if value.is_none() {
+                    Ok(Value::Null)
+                } else {
+                    self.encode(value, format)
+                }
+            }
+            Format::Seq(format) => self.encode_seq(value, format, None),
+            Format::TupleArray { content, size } => self.encode_seq(value, content, Some(*size)),
+            Format::Tuple(formats) => self.encode_tuple(value, formats),
+            Format::Map { key, value: format } => self.encode_map(value, key, format),
+            _ => encode_primitive(value, format),
+        }
+    }
+
+    fn encode_seq(
+        &mut self,
+        value: &Bound<'_, PyAny>,
+        format: &Format,
+        size: Option<usize>,
+    ) -> Result<Value> {
+        let expected = || match size {
+            Some(size) => format!("a list of length {}", size),
+            None => "a list".to_string(),
+        };
+        match items(value) {
+            Some(items) if size.map_or(true, |size| items.len() == size) => {
+                items.iter().map(|item| self.encode(item, format)).collect()
+            }
+            _ => Err(invalid(value, &expected())),
+        }
+    }
+
+    fn encode_tuple(&mut self, value: &Bound<'_, PyAny>, formats: &[Format]) -> Result<Value> {
+        match items(value) {
+            Some(items) if items.len() == formats.len() => items
+                .iter()
+                .zip(formats)
+                .map(|(item, format)| self.encode(item, format))
+                .collect(),
+            _ => Err(invalid(
+                value,
+                &format!("a tuple of length {}", formats.len()),
+            )),
+        }
+    }
+
+    fn encode_map(
+        &mut self,
+        value: &Bound<'_, PyAny>,
+        key_format: &Format,
+        format: &Format,
+    ) -> Result<Value> {
+        let dict = dict(value)?;
+        if *key_format == Format::Str {
+            let mut object = Map::new();
+            for (key, value) in dict {
+                object.insert(string(&key)?, self.encode(&value, format)?);
+            }
+            return Ok(Value::Object(object));
+        }
+        dict.iter()
+            .map(|(key, value)| {
+                Ok(Value::Array(vec![
+                    self.encode(&key, key_format)?,
+                    self.encode(&value, format)?,
+                ]))
+            })
+            .collect()
+    }
+
+    fn encode_container(&mut self, value: &Bound<'_, PyAny>, name: &str) -> Result<Value> {
+        let registry = self.registry;
+        if self.max_remaining_depth == 0 {
+            return Err(Error::Custom(format!(
+                "exceeded max container depth while entering: {}",
+                name
+            )));
+        }
+        self.max_remaining_depth -= 1;
+        let value = match container(registry, name)? {
+            ContainerFormat::UnitStruct => encode_primitive(value, &Format::Unit)?,
+            ContainerFormat::NewTypeStruct(format) => self.encode(value, format)?,
+            ContainerFormat::TupleStruct(formats) => self.encode_tuple(value, formats)?,
+            ContainerFormat::Struct(fields) => self.encode_struct(value, fields)?,
+            ContainerFormat::Enum(variants) => self.encode_variant(value, name, variants)?,
+        };
+        self.max_remaining_depth += 1;
+        Ok(value)
+    }
+
+    fn encode_variant(
+        &mut self,
+        value: &Bound<'_, PyAny>,
+        name: &str,
+        variants: &BTreeMap<u32, Named<VariantFormat>>,
+    ) -> Result<Value> {
+        let (variant, payload) = match dict(value) {
+            Ok(dict) if dict.len() == 1 => dict.iter().next().unwrap(),
+            _ => return Err(invalid(value, "a dict with a single variant")),
+        };
+        let variant = string(&variant)?;
+        let format = variants
+            .values()
+            .find(|named| named.name == variant)
+            .ok_or_else(|| Error::Custom(format!("unknown variant `{}` of {}", variant, name)))?;
+        let payload = match &format.value {
+            VariantFormat::Unit => encode_primitive(&payload, &Format::Unit)?,
+            VariantFormat::NewType(format) => self.encode(&payload, format)?,
+            VariantFormat::Tuple(formats) => self.encode_tuple(&payload, formats)?,
+            VariantFormat::Struct(fields) => self.encode_struct(&payload, fields)?,
+            VariantFormat::Variable(_) => return Err(unknown_variant_format(format)),
+        };
+        let mut object = Map::new();
+        object.insert(variant, payload);
+        Ok(Value::Object(object))
+    }
+
+    fn encode_struct(
+        &mut self,
+        value: &Bound<'_, PyAny>,
+        fields: &[Named<Format>],
+    ) -> Result<Value> {
+        let dict = dict(value)?;
+        for key in dict.keys() {
+            let key = string(&key)?;
+            if fields.iter().all(|field| field.name != key) {
+                return Err(Error::Custom(format!("unknown field `{}`", key)));
+            }
+        }
+        let mut object = Map::new();
+        for field in fields {
+            let value = dict
+                .get_item(&field.name)
+                .map_err(python_error)?
+                .ok_or_else(|| Error::Custom(format!("missing field `{}`", field.name)))?;
+            object.insert(field.name.clone(), self.encode(&value, &field.value)?);
+        }
+        Ok(Value::Object(object))
+    }
+}
+
+fn encode_primitive(value: &Bound<'_, PyAny>, format: &Format) -> Result<Value> {
+    Ok(match format {
+        Format::Unit if value.is_none() => Value::Null,
+        Format::Unit => return Err(invalid(value, "None")),
+        Format::Bool => match value.downcast::<PyBool>() {
+            Ok(b) => Value::Bool(b.is_true()),
+            Err(_) => return Err(invalid(value, "a bool")),
+        },
+        Format::I8 => Value::from(integer::<i8>(value)?),
+        Format::I16 => Value::from(integer::<i16>(value)?),
+        Format::I32 => Value::from(integer::<i32>(value)?),
+        Format::U8 => Value::from(integer::<u8>(value)?),
+        Format::U16 => Value::from(integer::<u16>(value)?),
+        Format::U32 => Value::from(integer::<u32>(value)?),
+        Format::I64 => Value::String(integer::<i64>(value)?.to_string()),
+        Format::I128 => Value::String(integer::<i128>(value)?.to_string()),
+        Format::U64 => Value::String(integer::<u64>(value)?.to_string()),
+        Format::U128 => Value::String(integer::<u128>(value)?.to_string()),
+        Format::Str => Value::String(string(value)?),
+        _ => return Err(unsupported(format)),
+    })
+}
+
+/// The items of a list or a tuple.
+fn items<'py>(value: &Bound<'py, PyAny>) -> Option<Vec<Bound<'py, PyAny>>> {
+    if let Ok(list) = value.downcast::<PyList>() {
+        Some(list.iter().collect())
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        Some(tuple.iter().collect())
+    } else {
+        None
+    }
+}
+
+/// A dict, or the tuple of its `(key, value)` pairs to which map keys are decoded.
+fn dict<'py>(value: &Bound<'py, PyAny>) -> Result<Bound<'py, PyDict>> {
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        return Ok(dict.clone());
+    }
+    let pairs = value
+        .downcast::<PyTuple>()
+        .map_err(|_| invalid(value, "a dict"))?;
+    let dict = PyDict::new(value.py());
+    for pair in pairs {
+        match pair.downcast::<PyTuple>() {
+            Ok(pair) if pair.len() == 2 => dict
+                .set_item(pair.get_item(0).unwrap(), pair.get_item(1).unwrap())
+                .map_err(python_error)?,
+            _ => return Err(invalid(value, "a dict")),
+        }
+    }
+    if dict.len() != pairs.len() {
+        return Err(invalid(value, "a dict without duplicate keys"));
+    }
+    Ok(dict)
+}
+
+fn integer<'py, T>(value: &Bound<'py, PyAny>) -> Result<T>
+where
+    T: FromPyObject<'py>,
+{
+    // `bool` is a subclass of `int` in Python.
+    if value.is_instance_of::<PyInt>() && !value.is_instance_of::<PyBool>() {
+        if let Ok(n) = value.extract() {
+            return Ok(n);
+        }
+    }
+    Err(invalid(
+        value,
+        &format!("an integer of type {}", std::any::type_name::<T>()),
+    ))
+}
+
+fn string(value: &Bound<'_, PyAny>) -> Result<String> {
+    let s = value
+        .downcast::<PyString>()
+        .map_err(|_| invalid(value, "a str"))?;
+    s.to_str().map(str::to_string).map_err(|_| Error::Utf8)
+}
+
+fn bytes(value: &Bound<'_, PyAny>) -> Result<Vec<u8>> {
+    if let Ok(bytes) = value.downcast::<PyBytes>() {
+        Ok(bytes.as_bytes().to_vec())
+    } else if let Ok(bytes) = value.downcast::<PyByteArray>() {
+        Ok(bytes.to_vec())
+    } else {
+        Err(invalid(value, "bytes"))
+    }
+}
+
+fn invalid(value: &Bound<'_, PyAny>, expected: &str) -> Error {
+    let repr = value
+        .repr()
+        .map(|repr| repr.to_string())
+        .unwrap_or_else(|_| "<unprintable>".to_string());
+    Error::Custom(format!(
+        "invalid Python value {}, expected {}",
+        repr, expected
+    ))
+}
+
+/// Converts JSON values produced by [`bcs_to_json`] to Python values.
+struct Decoder<'a, 'py> {
+    py: Python<'py>,
+    registry: &'a Registry,
+}
+
+impl<'a, 'py> Decoder<'a, 'py> {
+    // Recursive functions only dispatch, to keep their stack frames small.
+    fn decode(&self, value: &Value, format: &Format) -> Result<PyObject> {
+        match format {
+            format if is_bytes(format) => {
+                let bytes = value
+                    .as_str()
+                    .and_then(hex::decode)
+                    .ok_or_else(|| unexpected(value))?;
+                Ok(PyBytes::new(self.py, &bytes).into_any().unbind())
+            }
+            Format::TypeName(name) => self.decode_container(value, name),
+            Format::Option(format) => {
+                if value.is_null() {
+                    Ok(self.py.None())
+                } else {
+                    self.decode(value, format)
+                }
+            }
+            Format::Seq(format)
+            | Format::TupleArray {
+                content: format, ..
+            } => {
+                let items = self.decode_items(value, std::iter::repeat(&**format))?;
+                let list = PyList::new(self.py, items).map_err(python_error)?;
+                Ok(list.into_any().unbind())
+            }
+            Format::Tuple(formats) => self.decode_tuple(value, formats),
+            Format::Map { key, value: format } => self.decode_map(value, key, format),
+            _ => self.decode_primitive(value, format),
+        }
+    }
+
+    fn decode_primitive(&self, value: &Value, format: &Format) -> Result<PyObject> {
+        let py = self.py;
+        Ok(match (format, value) {
+            (Format::Unit, Value::Null) => py.None(),
+            (Format::Bool, Value::Bool(b)) => object(py, b)?,
+            (Format::I8, _) | (Format::I16, _) | (Format::I32, _) => {
+                object(py, value.as_i64().ok_or_else(|| unexpected(value))?)?
+            }
+            (Format::U8, _) | (Format::U16, _) | (Format::U32, _) => {
+                object(py, value.as_u64().ok_or_else(|| unexpected(value))?)?
+            }
+            (Format::I64, _) => decimal::<i64>(py, value)?,
+            (Format::I128, _) => decimal::<i128>(py, value)?,
+            (Format::U64, _) => decimal::<u64>(py, value)?,
+            (Format::U128, _) => decimal::<u128>(py, value)?,
+            (Format::Str, Value::String(s)) => object(py, s)?,
+            _ => return Err(unexpected(value)),
+        })
+    }
+
+    fn decode_items<'f>(
+        &self,
+        value: &Value,
+        formats: impl Iterator<Item = &'f Format>,
+    ) -> Result<Vec<PyObject>> {
+        let values = value.as_array().ok_or_else(|| unexpected(value))?;
+        values
+            .iter()
+            .zip(formats)
+            .map(|(value, format)| self.decode(value, format))
+            .collect()
+    }
+
+    fn decode_tuple(&self, value: &Value, formats: &[Format]) -> Result<PyObject> {
+        let items = self.decode_items(value, formats.iter())?;
+        let tuple = PyTuple::new(self.py, items).map_err(python_error)?;
+        Ok(tuple.into_any().unbind())
+    }
+
+    fn decode_map(&self, value: &Value, key_format: &Format, format: &Format) -> Result<PyObject> {
+        let dict = PyDict::new(self.py);
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    dict.set_item(key, self.decode(value, format)?)
+                        .map_err(python_error)?;
+                }
+            }
+            Value::Array(entries) => {
+                for entry in entries {
+                    let (key, value) = match entry.as_array().map(Vec::as_slice) {
+                        Some([key, value]) => (key, value),
+                        _ => return Err(unexpected(entry)),
+                    };
+                    let key = hashable(self.decode(key, key_format)?.into_bound(self.py))?;
+                    dict.set_item(key, self.decode(value, format)?)
+                        .map_err(python_error)?;
+                }
+            }
+            _ => return Err(unexpected(value)),
+        }
+        Ok(dict.into_any().unbind())
+    }
+
+    fn decode_container(&self, value: &Value, name: &str) -> Result<PyObject> {
+        match container(self.registry, name)? {
+            ContainerFormat::UnitStruct => self.decode_primitive(value, &Format::Unit),
+            ContainerFormat::NewTypeStruct(format) => self.decode(value, format),
+            ContainerFormat::TupleStruct(formats) => self.decode_tuple(value, formats),
+            ContainerFormat::Struct(fields) => self.decode_struct(value, fields),
+            ContainerFormat::Enum(variants) => self.decode_variant(value, variants),
+        }
+    }
+
+    fn decode_variant(
+        &self,
+        value: &Value,
+        variants: &BTreeMap<u32, Named<VariantFormat>>,
+    ) -> Result<PyObject> {
+        let (name, payload) = match value.as_object() {
+            Some(object) if object.len() == 1 => object.iter().next().unwrap(),
+            _ => return Err(unexpected(value)),
+        };
+        let variant = variants
+            .values()
+            .find(|variant| variant.name == *name)
+            .ok_or_else(|| unexpected(value))?;
+        let payload = match &variant.value {
+            VariantFormat::Unit => self.decode_primitive(payload, &Format::Unit)?,
+            VariantFormat::NewType(format) => self.decode(payload, format)?,
+            VariantFormat::Tuple(formats) => self.decode_tuple(payload, formats)?,
+            VariantFormat::Struct(fields) => self.decode_struct(payload, fields)?,
+            VariantFormat::Variable(_) => return Err(unknown_variant_format(variant)),
+        };
+        let dict = PyDict::new(self.py);
+        dict.set_item(name, payload).map_err(python_error)?;
+        Ok(dict.into_any().unbind())
+    }
+
+    fn decode_struct(&self, value: &Value, fields: &[Named<Format>]) -> Result<PyObject> {
+        let object = value.as_object().ok_or_else(|| unexpected(value))?;
+        let dict = PyDict::new(self.py);
+        for field in fields {
+            let value = object.get(&field.name).ok_or_else(|| unexpected(value))?;
+            dict.set_item(&field.name, self.decode(value, &field.value)?)
+                .map_err(python_error)?;
+        }
+        Ok(dict.into_any().unbind())
+    }
+}
+
+/// Converts a decoded map key to a hashable value: lists and tuples to tuples of hashable
+/// items, and dicts to tuples of their `(key, value)` pairs, with hashable values.
+fn hashable<'py>(value: Bound<'py, PyAny>) -> Result<Bound<'py, PyAny>> {
+    let py = value.py();
+    let items = if let Some(items) = items(&value) {
+        items
+            .into_iter()
+            .map(hashable)
+            .collect::<Result<Vec<_>>>()?
+    } else if let Ok(dict) = value.downcast::<PyDict>() {
+        dict.iter()
+            .map(|(key, value)| {
+                let pair = PyTuple::new(py, [key, hashable(value)?]).map_err(python_error)?;
+                Ok(pair.into_any())
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        return Ok(value);
+    };
+    let tuple = PyTuple::new(py, items).map_err(python_error)?;
+    Ok(tuple.into_any())
+}
+
+fn decimal<T>(py: Python<'_>, value: &Value) -> Result<PyObject>
+where
+    T: FromStr + for<'py> IntoPyObject<'py>,
+{
+    let n = value
+        .as_str()
+        .and_then(|s| s.parse::<T>().ok())
+        .ok_or_else(|| unexpected(value))?;
+    object(py, n)
+}
+
+fn object<'py, T>(py: Python<'py>, value: T) -> Result<PyObject>
+where
+    T: IntoPyObject<'py>,
+{
+    value
+        .into_pyobject(py)
+        .map(|object| object.into_any().unbind())
+        .map_err(|e| python_error(e.into()))
+}
+
+/// A JSON value that does not match its format, which `bcs_to_json` never produces.
+fn unexpected(value: &Value) -> Error {
+    Error::Custom(format!("unexpected JSON value {}", value))
+}
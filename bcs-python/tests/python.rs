@@ -0,0 +1,268 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::ErrorCode;
+use pyo3::{
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
+use serde::{Deserialize, Serialize};
+use serde_reflection::{Tracer, TracerConfig};
+use std::{collections::BTreeMap, ffi::CString};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Amount(u64);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Payload {
+    Transfer { to: [u8; 4], amount: Amount },
+    Burn(i128, u8),
+    Freeze,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Transaction {
+    sender: Vec<u8>,
+    nonce: Option<u16>,
+    tags: BTreeMap<String, i8>,
+    allowed: BTreeMap<Vec<u8>, bool>,
+    payloads: Vec<Payload>,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+struct Point {
+    x: u8,
+    y: i16,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Keys {
+    paths: BTreeMap<Vec<u16>, u8>,
+    points: BTreeMap<Point, Vec<u8>>,
+}
+
+fn keys() -> Keys {
+    Keys {
+        paths: vec![(vec![1, 2], 3), (vec![], 4), (vec![1], 5)]
+            .into_iter()
+            .collect(),
+        points: vec![
+            (Point { x: 1, y: -2 }, vec![6]),
+            (Point { x: 0, y: 7 }, vec![]),
+        ]
+        .into_iter()
+        .collect(),
+    }
+}
+
+fn transaction() -> Transaction {
+    Transaction {
+        sender: vec![0xab, 0xcd],
+        nonce: Some(7),
+        tags: vec![("b".to_string(), -1), ("a".to_string(), 2)]
+            .into_iter()
+            .collect(),
+        allowed: vec![(vec![2], true), (vec![1, 0], false)]
+            .into_iter()
+            .collect(),
+        payloads: vec![
+            Payload::Transfer {
+                to: [1, 2, 3, 4],
+                amount: Amount(u64::MAX),
+            },
+            Payload::Burn(i128::MIN, 3),
+            Payload::Freeze,
+        ],
+    }
+}
+
+const TRANSACTION: &str = r#"{
+    "sender": b"\xab\xcd",
+    "nonce": 7,
+    "tags": {"a": 2, "b": -1},
+    "allowed": {b"\x02": True, b"\x01\x00": False},
+    "payloads": [
+        {"Transfer": {"to": b"\x01\x02\x03\x04", "amount": 2**64 - 1}},
+        {"Burn": (-2**127, 3)},
+        {"Freeze": None},
+    ],
+}"#;
+
+fn registry() -> String {
+    let mut tracer = Tracer::new(TracerConfig::default());
+    tracer.trace_simple_type::<Payload>().unwrap();
+    tracer.trace_simple_type::<Transaction>().unwrap();
+    tracer.trace_simple_type::<Keys>().unwrap();
+    serde_json::to_string(&tracer.registry().unwrap()).unwrap()
+}
+
+/// Run Python code with the module `bcs_python` and the following globals:
+/// `registry`, `data` (the encoding of `transaction()`), `transaction`, `keys` (the encoding
+/// of `keys()`), and `codes`, the numeric values of error codes by name.
+fn run(code: &str) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = pyo3::wrap_pymodule!(bcs_python::bcs_python)(py);
+        py.import("sys")
+            .and_then(|sys| sys.getattr("modules"))
+            .and_then(|modules| modules.set_item("bcs_python", module))
+            .unwrap();
+
+        let globals = PyDict::new(py);
+        let codes = PyDict::new(py);
+        for code in &[
+            ErrorCode::Eof,
+            ErrorCode::ExpectedBoolean,
+            ErrorCode::NonCanonicalMap,
+            ErrorCode::Custom,
+            ErrorCode::RemainingInput,
+            ErrorCode::Utf8,
+            ErrorCode::NonCanonicalUleb128Encoding,
+        ] {
+            codes
+                .set_item(format!("{:?}", code), code.as_u16())
+                .unwrap();
+        }
+        globals.set_item("codes", codes).unwrap();
+        globals.set_item("registry", registry()).unwrap();
+        let data = bcs::to_bytes(&transaction()).unwrap();
+        globals.set_item("data", PyBytes::new(py, &data)).unwrap();
+        let transaction = py
+            .eval(&CString::new(TRANSACTION).unwrap(), None, None)
+            .unwrap();
+        globals.set_item("transaction", transaction).unwrap();
+        let keys = bcs::to_bytes(&keys()).unwrap();
+        globals.set_item("keys", PyBytes::new(py, &keys)).unwrap();
+
+        let code = CString::new(format!("from bcs_python import *\n{}", code)).unwrap();
+        if let Err(e) = py.run(&code, Some(&globals), None) {
+            e.print(py);
+            panic!("{}", e);
+        }
+    });
+}
+
+#[test]
+fn encode_and_decode() {
+    run(r#"
+schema = Schema(registry, "Transaction")
+assert schema.decode(data) == transaction, schema.decode(data)
+assert schema.encode(transaction) == data
+assert schema.serialized_size(transaction) == len(data)
+
+# Equivalent Python values are accepted.
+value = dict(transaction, sender=bytearray(b"\xab\xcd"), payloads=tuple(transaction["payloads"]))
+assert schema.encode(value) == data
+
+payload = Schema(registry, "Payload")
+assert payload.decode(b"\x02") == {"Freeze": None}
+assert payload.encode({"Burn": [1, 2]}) == b"\x01" + (1).to_bytes(16, "little") + b"\x02"
+"#);
+}
+
+#[test]
+fn hashable_map_keys() {
+    run(r#"
+schema = Schema(registry, "Keys")
+value = {
+    "paths": {(): 4, (1,): 5, (1, 2): 3},
+    "points": {(("x", 0), ("y", 7)): b"", (("x", 1), ("y", -2)): b"\x06"},
+}
+assert schema.decode(keys) == value, schema.decode(keys)
+assert schema.encode(value) == keys
+
+# The tuples of map keys are also accepted in place of lists and dicts elsewhere.
+point = Schema(registry, "Point")
+assert point.encode((("y", -2), ("x", 1))) == point.encode({"x": 1, "y": -2})
+assert schema.encode({"paths": (((1,), 5), ((), 4), ((1, 2), 3)), "points": value["points"]}) == keys
+try:
+    point.encode((("x", 1), ("x", 2)))
+    raise AssertionError()
+except BcsError as e:
+    assert str(e).endswith("expected a dict without duplicate keys"), e
+"#);
+}
+
+#[test]
+fn decoding_errors() {
+    run(r#"
+def error(schema, data):
+    try:
+        schema.decode(data)
+    except BcsError as e:
+        assert isinstance(e, ValueError)
+        return e.code, str(e)
+    raise AssertionError("decoding {!r} succeeded".format(data))
+
+schema = Schema(registry, "Transaction")
+assert error(schema, data[:-1]) == (codes["Eof"], "unexpected end of input")
+assert error(schema, data + b"\x00") == (codes["RemainingInput"], "remaining input")
+
+payload = Schema(registry, "Payload")
+assert error(payload, b"\x03") == (
+    codes["Custom"],
+    "invalid value: integer `3`, expected variant index 0 <= i < 3",
+)
+assert error(payload, b"\x80\x00")[0] == codes["NonCanonicalUleb128Encoding"]
+
+# Map keys must be sorted by their encodings, as in Rust.
+prefix = b"\x02\xab\xcd\x00"
+assert error(schema, prefix + b"\x02\x01b\xff\x01a\x02")[0] == codes["NonCanonicalMap"]
+assert error(schema, prefix + b"\x00\x01\x01\x02\x02")[0] == codes["ExpectedBoolean"]
+assert error(schema, prefix + b"\x01\x01\xff\x00")[0] == codes["Utf8"]
+"#);
+}
+
+#[test]
+fn encoding_errors() {
+    run(r#"
+def error(schema, value):
+    try:
+        schema.encode(value)
+    except BcsError as e:
+        return e.code, str(e)
+    raise AssertionError("encoding {!r} succeeded".format(value))
+
+schema = Schema(registry, "Transaction")
+assert error(schema, dict(transaction, nonce=True)) == (
+    codes["Custom"],
+    "invalid Python value True, expected an integer of type u16",
+)
+assert error(schema, dict(transaction, nonce=-1))[1] == (
+    "invalid Python value -1, expected an integer of type u16"
+)
+assert error(schema, dict(transaction, sender="abcd"))[1] == (
+    "invalid Python value 'abcd', expected bytes"
+)
+assert error(schema, dict(transaction, extra=1))[1] == "unknown field `extra`"
+value = dict(transaction)
+del value["tags"]
+assert error(schema, value)[1] == "missing field `tags`"
+
+payload = Schema(registry, "Payload")
+assert error(payload, {"Mint": None})[1] == "unknown variant `Mint` of Payload"
+assert error(payload, {"Burn": (1,)})[1] == (
+    "invalid Python value (1,), expected a tuple of length 2"
+)
+assert error(payload, {"Transfer": {"to": b"\x01", "amount": 1}})[1] == (
+    r"invalid Python value b'\x01', expected 4 bytes"
+)
+"#);
+}
+
+#[test]
+fn invalid_schemas() {
+    run(r#"
+try:
+    Schema(registry, "Missing")
+    raise AssertionError()
+except BcsError as e:
+    assert (e.code, str(e)) == (codes["Custom"], "unknown container Missing")
+
+try:
+    Schema("[]", "Transaction")
+    raise AssertionError()
+except BcsError as e:
+    assert str(e).startswith("invalid registry"), e
+"#);
+}
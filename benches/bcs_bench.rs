@@ -1,8 +1,9 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use bcs::to_bytes;
-use criterion::{criterion_group, criterion_main, Criterion};
+use bcs::{from_bytes, serialized_size, to_bytes};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 
 pub fn bcs_benchmark(c: &mut Criterion) {
@@ -24,5 +25,127 @@ pub fn bcs_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bcs_benchmark);
+#[derive(Serialize, Deserialize)]
+enum Nested {
+    Leaf,
+    Node(Box<Nested>),
+}
+
+impl Nested {
+    fn with_depth(depth: usize) -> Self {
+        (1..depth).fold(Nested::Leaf, |inner, _| Nested::Node(Box::new(inner)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum TransactionArgument {
+    U64(u64),
+    Address([u8; 32]),
+    Bytes(Vec<u8>),
+    Bool(bool),
+}
+
+#[derive(Serialize, Deserialize)]
+enum TransactionPayload {
+    Script {
+        code: Vec<u8>,
+        type_args: Vec<String>,
+        args: Vec<TransactionArgument>,
+    },
+    WriteSet(BTreeMap<Vec<u8>, Option<Vec<u8>>>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignedTransaction {
+    sender: [u8; 32],
+    sequence_number: u64,
+    payload: TransactionPayload,
+    max_gas_amount: u64,
+    gas_unit_price: u64,
+    gas_currency_code: String,
+    expiration_timestamp_secs: u64,
+    chain_id: u8,
+    public_key: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+fn transaction() -> SignedTransaction {
+    SignedTransaction {
+        sender: [0xa5; 32],
+        sequence_number: 42,
+        payload: TransactionPayload::Script {
+            code: (0..2048).map(|i| i as u8).collect(),
+            type_args: vec!["0x1::XUS::XUS".to_string()],
+            args: vec![
+                TransactionArgument::Address([0x5a; 32]),
+                TransactionArgument::U64(1_000_000),
+                TransactionArgument::Bytes(b"metadata".to_vec()),
+                TransactionArgument::Bool(false),
+            ],
+        },
+        max_gas_amount: 1_000_000,
+        gas_unit_price: 0,
+        gas_currency_code: "XUS".to_string(),
+        expiration_timestamp_secs: 1_611_792_876,
+        chain_id: 4,
+        public_key: vec![0x11; 32],
+        signature: vec![0x22; 64],
+    }
+}
+
+/// Benchmark serialization, `from_bytes` and `serialized_size` on the same value.
+fn bench_value<T>(c: &mut Criterion, name: &str, value: &T)
+where
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    let bytes = to_bytes(value).unwrap();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("serialize", |b| {
+        b.iter(|| to_bytes(black_box(value)).unwrap())
+    });
+    group.bench_function("serialize presized", |b| {
+        b.iter(|| bcs::to_bytes_presized(black_box(value)).unwrap())
+    });
+    let mut buffer = Vec::new();
+    group.bench_function("serialize into reused buffer", |b| {
+        b.iter(|| {
+            buffer.clear();
+            bcs::to_bytes_into(&mut buffer, black_box(value)).unwrap()
+        })
+    });
+    group.bench_function("deserialize", |b| {
+        b.iter(|| from_bytes::<T>(black_box(&bytes)).unwrap())
+    });
+    group.bench_function("serialized_size", |b| {
+        b.iter(|| serialized_size(black_box(value)).unwrap())
+    });
+    group.finish();
+}
+
+pub fn payload_benchmark(c: &mut Criterion) {
+    let btree_map: BTreeMap<u32, u32> = (0..2000).map(|i| (i, i)).collect();
+    bench_value(c, "btree map", &btree_map);
+
+    // Keys whose canonical order differs from their numeric order.
+    let string_map: BTreeMap<String, u64> = (0..2000).map(|i| (i.to_string(), i)).collect();
+    bench_value(c, "string map", &string_map);
+
+    let bytes: Vec<u8> = (0..1 << 20).map(|i| i as u8).collect();
+    bench_value(c, "byte vector", &bytes);
+
+    let string = "çå∞≠¢õß∂ƒ∫".repeat(1 << 14);
+    bench_value(c, "string", &string);
+
+    let strings: Vec<String> = (0..2000).map(|i| format!("entry #{}", i)).collect();
+    bench_value(c, "string vector", &strings);
+
+    // Stay clear of the limit since enum variants are containers too.
+    let nested = Nested::with_depth(bcs::MAX_CONTAINER_DEPTH - 10);
+    bench_value(c, "deep enum", &nested);
+
+    bench_value(c, "transaction", &transaction());
+}
+
+criterion_group!(benches, bcs_benchmark, payload_benchmark);
 criterion_main!(benches);
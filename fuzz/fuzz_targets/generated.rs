@@ -0,0 +1,41 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Unlike the other targets, which mostly exercise the early failures of the decoder on
+//! random bytes, this one turns the input into structured BCS bytes (see `bcs::fuzzing`)
+//! so that deeper decoding paths are reached.
+
+#![no_main]
+
+use arbitrary::Unstructured;
+use bcs::fuzzing::{arbitrary_malformed_bytes, arbitrary_valid_bytes};
+use bcs_fuzz::Nested;
+use libfuzzer_sys::fuzz_target;
+use serde_reflection::{Format, Registry, Tracer, TracerConfig};
+
+thread_local! {
+    static FORMATS: (Format, Registry) = {
+        let mut tracer = Tracer::new(TracerConfig::default());
+        let (format, _) = tracer.trace_simple_type::<Nested>().unwrap();
+        (format, tracer.registry().unwrap())
+    };
+}
+
+fuzz_target!(|data: &[u8]| {
+    FORMATS.with(|(format, registry)| {
+        let mut u = Unstructured::new(data);
+        if let Ok(bytes) = arbitrary_valid_bytes(&mut u, format, registry) {
+            bcs::from_bytes::<Nested>(&bytes).unwrap();
+            bcs_fuzz::check::<Nested>(&bytes);
+        }
+        if let Ok(input) = arbitrary_malformed_bytes(&mut u, format, registry) {
+            assert_eq!(
+                bcs::from_bytes::<Nested>(&input.bytes),
+                Err(input.expected_error),
+                "{:?}",
+                input.defect,
+            );
+            bcs_fuzz::check::<Nested>(&input.bytes);
+        }
+    })
+});
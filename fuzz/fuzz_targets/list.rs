@@ -0,0 +1,9 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![no_main]
+
+use bcs_fuzz::List;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| bcs_fuzz::check::<List<Option<u16>>>(data));
@@ -0,0 +1,133 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Types and checks shared by the fuzz targets.
+//!
+//! Every target feeds its input to `bcs::from_bytes` for one type and checks that
+//! * successfully decoded values re-serialize to the exact same bytes, since BCS admits a
+//!   single encoding per value, and
+//! * the memory allocated while decoding stays proportional to the size of the input,
+//!   whatever lengths the input announces.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Bytes that decoding may allocate regardless of the input.
+const BASE_ALLOWANCE: usize = 64 * 1024;
+/// Bytes that decoding may allocate for each byte of input. This covers values larger
+/// in memory than on the wire (e.g. a boxed enum decoded from a single byte) as well as
+/// the capacity preallocated by nested collections.
+const ALLOWANCE_PER_INPUT_BYTE: usize = 1024;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum Nested {
+    Leaf(u8),
+    Pair(Box<Nested>, Box<Nested>),
+    Wrapped(Option<Box<Nested>>),
+    Branch {
+        label: String,
+        children: Vec<Nested>,
+    },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Maps {
+    pub by_name: BTreeMap<String, Vec<u8>>,
+    pub by_id: BTreeMap<u64, Option<bool>>,
+    pub nested: BTreeMap<Vec<u8>, BTreeMap<u16, ()>>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Options {
+    pub flag: Option<bool>,
+    pub name: Option<Option<String>>,
+    pub entries: Vec<Option<(u8, i64)>>,
+    pub fixed: [Option<u32>; 3],
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct List<T> {
+    pub value: T,
+    pub next: Option<Box<List<T>>>,
+}
+
+/// Decode `data` as a `T` and check the properties above.
+pub fn check<T>(data: &[u8])
+where
+    T: Debug + Serialize + DeserializeOwned,
+{
+    let (result, peak) = peak_allocation(|| bcs::from_bytes::<T>(data));
+    let allowance = BASE_ALLOWANCE + ALLOWANCE_PER_INPUT_BYTE * data.len();
+    assert!(
+        peak <= allowance,
+        "decoding {} bytes allocated {} bytes (allowance: {})",
+        data.len(),
+        peak,
+        allowance,
+    );
+    if let Ok(value) = result {
+        assert_eq!(
+            bcs::to_bytes(&value).unwrap(),
+            data,
+            "{:?} was decoded from a non-canonical encoding",
+            value
+        );
+    }
+}
+
+/// Run `f` and return its result with the peak number of bytes allocated meanwhile.
+fn peak_allocation<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let start = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(start, Ordering::SeqCst);
+    let result = f();
+    (result, PEAK.load(Ordering::SeqCst).saturating_sub(start))
+}
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, instrumented to track the peak of allocated memory.
+struct PeakAllocator;
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+impl PeakAllocator {
+    fn add(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::SeqCst) + size;
+        PEAK.fetch_max(allocated, Ordering::SeqCst);
+    }
+
+    fn sub(size: usize) {
+        ALLOCATED.fetch_sub(size, Ordering::SeqCst);
+    }
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::sub(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::sub(layout.size());
+            Self::add(new_size);
+        }
+        new_ptr
+    }
+}
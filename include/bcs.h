@@ -0,0 +1,127 @@
+#ifndef BCS_H
+#define BCS_H
+
+/* Generated with cbindgen from src/ffi.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Status returned on success.
+ */
+#define BCS_OK 0
+
+/**
+ * A format registry together with the container to validate and transcode.
+ */
+typedef struct BcsSchema BcsSchema;
+
+/**
+ * A byte buffer allocated by this library.
+ */
+typedef struct BcsBuffer {
+  /**
+   * The bytes of the buffer, or null if the buffer is empty.
+   */
+  uint8_t *data;
+  /**
+   * The number of bytes of the buffer.
+   */
+  size_t len;
+} BcsBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a schema handle from a registry serialized in JSON and the name of one of its
+ * containers.
+ *
+ * On success, `*schema` is set to a handle to release with `bcs_schema_free`. Otherwise,
+ * it is set to null.
+ *
+ * # Safety
+ *
+ * `registry` must point to `registry_len` readable bytes, `type_name` to a NUL-terminated
+ * string, `schema` to a writable pointer, and `error` must be null or point to a writable
+ * `BcsBuffer`.
+ */
+uint16_t bcs_schema_new(const uint8_t *registry,
+                        size_t registry_len,
+                        const char *type_name,
+                        struct BcsSchema **schema,
+                        struct BcsBuffer *error);
+
+/**
+ * Release a schema handle. Does nothing if `schema` is null.
+ *
+ * # Safety
+ *
+ * `schema` must be null or a handle returned by `bcs_schema_new` that was not released yet.
+ */
+void bcs_schema_free(struct BcsSchema *schema);
+
+/**
+ * Check that `bytes` is the canonical BCS encoding of a value of the schema.
+ *
+ * # Safety
+ *
+ * `schema` must be a live handle, `bytes` must point to `len` readable bytes, and `error`
+ * must be null or point to a writable `BcsBuffer`.
+ */
+uint16_t bcs_validate(const struct BcsSchema *schema,
+                      const uint8_t *bytes,
+                      size_t len,
+                      struct BcsBuffer *error);
+
+/**
+ * Convert the BCS encoding of a value of the schema to JSON, written to `json` as UTF-8.
+ *
+ * # Safety
+ *
+ * `schema` must be a live handle, `bytes` must point to `len` readable bytes, `json` must
+ * point to a writable `BcsBuffer`, and `error` must be null or point to a writable
+ * `BcsBuffer`.
+ */
+uint16_t bcs_decode_to_json(const struct BcsSchema *schema,
+                            const uint8_t *bytes,
+                            size_t len,
+                            struct BcsBuffer *json,
+                            struct BcsBuffer *error);
+
+/**
+ * Convert a JSON value, given in UTF-8, to the BCS encoding of a value of the schema,
+ * written to `bytes`.
+ *
+ * # Safety
+ *
+ * `schema` must be a live handle, `json` must point to `len` readable bytes, `bytes` must
+ * point to a writable `BcsBuffer`, and `error` must be null or point to a writable
+ * `BcsBuffer`.
+ */
+uint16_t bcs_encode_from_json(const struct BcsSchema *schema,
+                              const uint8_t *json,
+                              size_t len,
+                              struct BcsBuffer *bytes,
+                              struct BcsBuffer *error);
+
+/**
+ * Release the bytes of a buffer written by this library and reset it to `{NULL, 0}`.
+ * Does nothing if `buffer` is null or empty.
+ *
+ * # Safety
+ *
+ * `buffer` must be null or point to a buffer written by this library, or reset by this
+ * function, whose bytes were not released yet.
+ */
+void bcs_buffer_free(struct BcsBuffer *buffer);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* BCS_H */
@@ -0,0 +1,77 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::codegen::Language;
+use serde_reflection::Registry;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "bcs",
+    about = "Tools for Binary Canonical Serialization (BCS) formats"
+)]
+enum Command {
+    /// Compare two format registries (YAML files as produced by serde-reflection) and
+    /// report the changes of wire format. Exits with a non-zero status on breaking changes.
+    CheckCompat {
+        /// Registry describing the formats currently deployed.
+        old: PathBuf,
+        /// Registry describing the proposed formats.
+        new: PathBuf,
+    },
+    /// Generate type definitions and BCS serializers and deserializers for another language
+    /// from a registry (YAML file as produced by serde-reflection).
+    Generate {
+        /// Registry describing the formats.
+        registry: PathBuf,
+        /// Target language: `python` or `typescript`.
+        #[structopt(long)]
+        language: Language,
+        /// File to write the generated code to, instead of the standard output.
+        #[structopt(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn read_registry(path: &Path) -> Result<Registry, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    serde_yaml::from_str(&content).map_err(|e| format!("failed to parse {}: {}", path.display(), e))
+}
+
+fn main() {
+    let result = match Command::from_args() {
+        Command::CheckCompat { old, new } => check_compat(&old, &new),
+        Command::Generate {
+            registry,
+            language,
+            output,
+        } => generate(&registry, language, output.as_deref()),
+    };
+    match result {
+        Ok(true) => (),
+        Ok(false) => std::process::exit(1),
+        Err(message) => {
+            eprintln!("error: {}", message);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn check_compat(old: &Path, new: &Path) -> Result<bool, String> {
+    let report = bcs::compat::check_compatibility(&read_registry(old)?, &read_registry(new)?);
+    print!("{}", report);
+    Ok(report.is_compatible())
+}
+
+fn generate(registry: &Path, language: Language, output: Option<&Path>) -> Result<bool, String> {
+    let code = bcs::codegen::generate(language, &read_registry(registry)?)
+        .map_err(|e| format!("failed to generate {} code: {}", language, e))?;
+    match output {
+        Some(path) => std::fs::write(path, code)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))?,
+        None => print!("{}", code),
+    }
+    Ok(true)
+}
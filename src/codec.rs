@@ -0,0 +1,809 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Direct BCS encoding and decoding, without going through serde.
+//!
+//! The traits [`BcsSerialize`] and [`BcsDeserialize`] are usually derived with the
+//! feature `derive`. They produce exactly the same bytes, and fail with exactly the same
+//! errors, as [`to_bytes`](crate::to_bytes) and [`from_bytes`](crate::from_bytes) on the
+//! corresponding `#[derive(Serialize, Deserialize)]`.
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use bcs::{BcsDeserialize, BcsSerialize};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(BcsSerialize, BcsDeserialize, Serialize, Deserialize, Debug, PartialEq)]
+//! enum Message {
+//!     Ping(u64),
+//!     Data { key: String, value: Vec<u8> },
+//! }
+//!
+//! let message = Message::Data { key: "a".to_string(), value: vec![1, 2] };
+//! let bytes = bcs::codec::encode(&message).unwrap();
+//! assert_eq!(bytes, bcs::to_bytes(&message).unwrap());
+//! assert_eq!(bcs::codec::decode::<Message>(&bytes).unwrap(), message);
+//! # }
+//! ```
+//!
+//! To keep the encoding of an enum stable when variants are removed or reordered, the tag
+//! written for a variant can be pinned with `#[bcs(tag = N)]`. The variants that follow
+//! take the next tags, and unknown tags are rejected when decoding. Serde's derives do not
+//! know about these tags, so for such enums `BcsSerialize` and `BcsDeserialize` also
+//! implement `Serialize` and `Deserialize`, with the tags as variant indices. Both must be
+//! derived, without serde's derives, and the serde encoding matches this module's.
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use bcs::{BcsDeserialize, BcsSerialize};
+//!
+//! #[derive(BcsSerialize, BcsDeserialize, Debug, PartialEq)]
+//! enum Command {
+//!     Get(u64),
+//!     // Tag 1 belonged to a variant that was removed.
+//!     #[bcs(tag = 2)]
+//!     Put(u64, Vec<u8>),
+//!     Delete(u64),
+//! }
+//!
+//! assert_eq!(bcs::codec::encode(&Command::Delete(7)).unwrap()[0], 3);
+//! assert_eq!(bcs::to_bytes(&Command::Delete(7)).unwrap()[0], 3);
+//! assert!(bcs::codec::decode::<Command>(&[1, 7, 0, 0, 0, 0, 0, 0, 0]).is_err());
+//! # }
+//! ```
+//!
+//! Types that BCS cannot represent do not implement the traits, so using them is a
+//! compile-time error rather than a `NotSupported` error at runtime:
+//!
+//! ```compile_fail
+//! bcs::codec::encode(&1.5f32);
+//! ```
+//!
+//! ```compile_fail
+//! bcs::codec::decode::<char>(&[0x61]);
+//! ```
+//!
+//! Deriving serde's traits as well would give the enum two encodings:
+//!
+//! ```compile_fail
+//! use bcs::{BcsDeserialize, BcsSerialize};
+//!
+//! #[derive(BcsSerialize, BcsDeserialize, serde::Serialize)]
+//! enum Command {
+//!     Get(u64),
+//!     #[bcs(tag = 2)]
+//!     Put(u64, Vec<u8>),
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use bcs::BcsSerialize;
+//!
+//! // `BcsDeserialize` is missing, so `serde::Deserialize` could be derived separately.
+//! #[derive(BcsSerialize)]
+//! enum Command {
+//!     Get(u64),
+//!     #[bcs(tag = 2)]
+//!     Put(u64, Vec<u8>),
+//! }
+//! ```
+//!
+//! Serde attributes that change how a type is decoded are rejected, since the generated
+//! decoder would not follow them:
+//!
+//! ```compile_fail
+//! use bcs::BcsDeserialize;
+//!
+//! #[derive(BcsDeserialize, serde::Deserialize)]
+//! enum Status {
+//!     Active,
+//!     #[serde(other)]
+//!     Unknown,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use bcs::BcsDeserialize;
+//!
+//! #[derive(BcsDeserialize)]
+//! struct Config {
+//!     #[serde(default)]
+//!     retries: u8,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use bcs::BcsDeserialize;
+//!
+//! #[derive(BcsDeserialize)]
+//! struct Config {
+//!     #[serde(skip_deserializing)]
+//!     cache: Vec<u8>,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use bcs::BcsDeserialize;
+//!
+//! fn parse<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+//!     serde::Deserialize::deserialize(deserializer)
+//! }
+//!
+//! #[derive(BcsDeserialize)]
+//! struct Config {
+//!     #[serde(deserialize_with = "parse")]
+//!     retries: u8,
+//! }
+//! ```
+//!
+//! Pinned tags must be unique:
+//!
+//! ```compile_fail
+//! use bcs::BcsSerialize;
+//!
+//! #[derive(BcsSerialize)]
+//! enum Command {
+//!     Get(u64),
+//!     #[bcs(tag = 0)]
+//!     Put(u64, Vec<u8>),
+//! }
+//! ```
+
+use crate::{
+    de::Deserializer,
+    error::Error,
+    uleb128::{self, ULEB32_NAME, ULEB64_NAME},
+    FixedBytes, Result, Uleb32, Uleb64, U256,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    convert::TryInto,
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    marker::PhantomData,
+};
+
+/// Serialize the given data structure as a BCS byte vector.
+pub fn encode<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + BcsSerialize,
+{
+    let mut output = Vec::new();
+    value.bcs_serialize(&mut Encoder::new(&mut output, crate::MAX_CONTAINER_DEPTH))?;
+    Ok(output)
+}
+
+/// Append the serialization of the given data structure to `output`. On error, `output`
+/// is left as it was.
+pub fn encode_into<T>(output: &mut Vec<u8>, value: &T) -> Result<()>
+where
+    T: ?Sized + BcsSerialize,
+{
+    let len = output.len();
+    let result = value.bcs_serialize(&mut Encoder::new(output, crate::MAX_CONTAINER_DEPTH));
+    if result.is_err() {
+        output.truncate(len);
+    }
+    result
+}
+
+/// Deserialize an instance of type `T` from BCS bytes, which must be entirely consumed.
+pub fn decode<'de, T>(bytes: &'de [u8]) -> Result<T>
+where
+    T: BcsDeserialize<'de>,
+{
+    let mut decoder = Decoder::new(bytes, crate::MAX_CONTAINER_DEPTH);
+    let value = T::bcs_deserialize(&mut decoder)?;
+    decoder.de.end().map(move |_| value)
+}
+
+/// A data structure that can be serialized directly into BCS.
+pub trait BcsSerialize {
+    /// Serialize this value into the given encoder.
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()>;
+
+    /// Serialize the elements of a sequence, so that byte sequences are copied at once.
+    #[doc(hidden)]
+    fn bcs_serialize_slice(values: &[Self], encoder: &mut Encoder<'_>) -> Result<()>
+    where
+        Self: Sized,
+    {
+        values
+            .iter()
+            .try_for_each(|value| value.bcs_serialize(encoder))
+    }
+}
+
+/// A data structure that can be deserialized directly from BCS.
+pub trait BcsDeserialize<'de>: Sized {
+    /// Deserialize a value from the given decoder.
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self>;
+
+    /// Deserialize the `len` elements of a sequence, so that byte sequences are copied at
+    /// once.
+    #[doc(hidden)]
+    fn bcs_deserialize_vec(decoder: &mut Decoder<'de>, len: usize) -> Result<Vec<Self>> {
+        let mut values = Vec::with_capacity(decoder.preallocation::<Self>(len));
+        for _ in 0..len {
+            values.push(Self::bcs_deserialize(decoder)?);
+        }
+        Ok(values)
+    }
+}
+
+/// Writes BCS bytes on behalf of [`BcsSerialize`] implementations.
+pub struct Encoder<'a> {
+    output: &'a mut Vec<u8>,
+    max_remaining_depth: usize,
+}
+
+impl<'a> Encoder<'a> {
+    fn new(output: &'a mut Vec<u8>, max_remaining_depth: usize) -> Self {
+        Self {
+            output,
+            max_remaining_depth,
+        }
+    }
+
+    /// Enter a struct or an enum named `name`, failing if the maximal container depth is
+    /// exceeded. Must be paired with `leave_container`.
+    pub fn enter_container(&mut self, name: &'static str) -> Result<()> {
+        if self.max_remaining_depth == 0 {
+            return Err(Error::ExceededContainerDepthLimit(name));
+        }
+        self.max_remaining_depth -= 1;
+        Ok(())
+    }
+
+    /// Leave the container entered last.
+    pub fn leave_container(&mut self) {
+        self.max_remaining_depth += 1;
+    }
+
+    /// Write the index of an enum variant.
+    pub fn write_variant_index(&mut self, index: u32) {
+        self.write_u32_as_uleb128(index)
+    }
+
+    /// Write the length of a variable-length sequence.
+    pub fn write_len(&mut self, len: usize) -> Result<()> {
+        if len > crate::MAX_SEQUENCE_LENGTH {
+            return Err(Error::ExceededMaxLen(len));
+        }
+        self.write_u32_as_uleb128(len as u32);
+        Ok(())
+    }
+
+    fn write_u32_as_uleb128(&mut self, value: u32) {
+        self.write_uleb128(value.into())
+    }
+
+    fn write_uleb128(&mut self, value: u64) {
+        let mut buffer = [0; uleb128::MAX_LEN];
+        self.write_raw(uleb128::encode(value, &mut buffer));
+    }
+
+    fn write_raw(&mut self, bytes: &[u8]) {
+        self.output.extend_from_slice(bytes);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write_len(bytes.len())?;
+        self.write_raw(bytes);
+        Ok(())
+    }
+
+    fn write_seq<T: BcsSerialize>(&mut self, values: &[T]) -> Result<()> {
+        self.write_len(values.len())?;
+        T::bcs_serialize_slice(values, self)
+    }
+
+    fn write_iter<'b, T, I>(&mut self, values: I) -> Result<()>
+    where
+        T: 'b + BcsSerialize,
+        I: ExactSizeIterator<Item = &'b T>,
+    {
+        self.write_len(values.len())?;
+        values
+            .into_iter()
+            .try_for_each(|value| value.bcs_serialize(self))
+    }
+
+    /// Write map entries sorted by the bytes of their keys, keeping the first entry of
+    /// each key, as `to_bytes` does.
+    fn write_map<'b, K, V, I>(&mut self, entries: I) -> Result<()>
+    where
+        K: 'b + BcsSerialize,
+        V: 'b + BcsSerialize,
+        I: Iterator<Item = (&'b K, &'b V)>,
+    {
+        let mut encoded = Vec::with_capacity(entries.size_hint().0);
+        for (key, value) in entries {
+            let key = self.encode_nested(key)?;
+            let value = self.encode_nested(value)?;
+            encoded.push((key, value));
+        }
+        encoded.sort_by(|e1, e2| e1.0.cmp(&e2.0));
+        encoded.dedup_by(|e1, e2| e1.0.eq(&e2.0));
+
+        self.write_len(encoded.len())?;
+        for (key, value) in &encoded {
+            self.write_raw(key);
+            self.write_raw(value);
+        }
+        Ok(())
+    }
+
+    fn encode_nested<T: ?Sized + BcsSerialize>(&self, value: &T) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        value.bcs_serialize(&mut Encoder::new(&mut output, self.max_remaining_depth))?;
+        Ok(output)
+    }
+}
+
+/// Reads BCS bytes on behalf of [`BcsDeserialize`] implementations.
+pub struct Decoder<'de> {
+    de: Deserializer<'de>,
+}
+
+impl<'de> Decoder<'de> {
+    fn new(input: &'de [u8], max_remaining_depth: usize) -> Self {
+        Self {
+            de: Deserializer::new(input, max_remaining_depth),
+        }
+    }
+
+    /// Enter a struct or an enum named `name`, failing if the maximal container depth is
+    /// exceeded. Must be paired with `leave_container`.
+    pub fn enter_container(&mut self, name: &'static str) -> Result<()> {
+        self.de.enter_named_container(name)
+    }
+
+    /// Leave the container entered last.
+    pub fn leave_container(&mut self) {
+        self.de.leave_named_container()
+    }
+
+    /// Read the index of an enum variant.
+    pub fn read_variant_index(&mut self) -> Result<u32> {
+        self.de.parse_u32_from_uleb128()
+    }
+
+    /// The error for a variant index that is not below the number of variants, as reported
+    /// by `#[derive(Deserialize)]`.
+    pub fn unknown_variant(index: u32, count: usize) -> Error {
+        Error::Custom(format!(
+            "invalid value: integer `{}`, expected variant index 0 <= i < {}",
+            index, count
+        ))
+    }
+
+    /// The error for a variant tag that is not among the tags pinned with `#[bcs(tag = N)]`.
+    pub fn unknown_variant_tag(tag: u32, tags: &[u32]) -> Error {
+        let tags: Vec<_> = tags.iter().map(u32::to_string).collect();
+        Error::Custom(format!(
+            "invalid value: integer `{}`, expected one of the variant tags {}",
+            tag,
+            tags.join(", ")
+        ))
+    }
+
+    /// Read the length of a variable-length sequence.
+    pub fn read_len(&mut self) -> Result<usize> {
+        self.de.parse_length()
+    }
+
+    /// Returns the number of elements of type `T` that a collection announcing `len`
+    /// elements may preallocate: at most 1 MiB and, across the whole input, at most one
+    /// element per input byte.
+    fn preallocation<T>(&mut self, len: usize) -> usize {
+        let max_len = (1 << 20) / std::mem::size_of::<T>().max(1);
+        self.de.preallocation(len.min(max_len))
+    }
+
+    fn read_raw(&mut self, len: usize) -> Result<&'de [u8]> {
+        self.de.parse_fixed_bytes(len)
+    }
+
+    /// Read map entries, which must be strictly sorted by the bytes of their keys.
+    fn read_map<K, V>(&mut self, mut insert: impl FnMut(K, V)) -> Result<()>
+    where
+        K: BcsDeserialize<'de>,
+        V: BcsDeserialize<'de>,
+    {
+        let len = self.read_len()?;
+        let mut previous_key_bytes: Option<&'de [u8]> = None;
+        for _ in 0..len {
+            let previous_input_slice = self.de.input;
+            let key = K::bcs_deserialize(self)?;
+            let key_len = previous_input_slice.len() - self.de.input.len();
+            let key_bytes = &previous_input_slice[..key_len];
+            if let Some(previous_key_bytes) = previous_key_bytes {
+                if previous_key_bytes >= key_bytes {
+                    return Err(Error::NonCanonicalMap);
+                }
+            }
+            previous_key_bytes = Some(key_bytes);
+            let value = V::bcs_deserialize(self)?;
+            insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {$(
+        impl BcsSerialize for $ty {
+            fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+                encoder.write_raw(&self.to_le_bytes());
+                Ok(())
+            }
+        }
+
+        impl<'de> BcsDeserialize<'de> for $ty {
+            fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+                let mut le_bytes = [0; std::mem::size_of::<$ty>()];
+                decoder.de.fill_slice(&mut le_bytes)?;
+                Ok(<$ty>::from_le_bytes(le_bytes))
+            }
+        }
+    )*};
+}
+
+impl_integer!(u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+macro_rules! impl_uleb {
+    ($uleb:ident, $name:expr, $parse:ident) => {
+        impl BcsSerialize for $uleb {
+            fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+                encoder.enter_container($name)?;
+                encoder.write_uleb128(self.0.into());
+                encoder.leave_container();
+                Ok(())
+            }
+        }
+
+        impl<'de> BcsDeserialize<'de> for $uleb {
+            fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+                decoder.enter_container($name)?;
+                let value = decoder.de.$parse()?;
+                decoder.leave_container();
+                Ok($uleb(value))
+            }
+        }
+    };
+}
+
+impl_uleb!(Uleb32, ULEB32_NAME, parse_u32_from_uleb128);
+impl_uleb!(Uleb64, ULEB64_NAME, parse_u64_from_uleb128);
+
+impl<const N: usize> BcsSerialize for FixedBytes<N> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.enter_container(Self::NAME)?;
+        encoder.write_raw(&self.0);
+        encoder.leave_container();
+        Ok(())
+    }
+}
+
+impl<'de, const N: usize> BcsDeserialize<'de> for FixedBytes<N> {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        decoder.enter_container(Self::NAME)?;
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(decoder.read_raw(N)?);
+        decoder.leave_container();
+        Ok(FixedBytes(bytes))
+    }
+}
+
+impl BcsSerialize for U256 {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_raw(&self.to_le_bytes());
+        Ok(())
+    }
+}
+
+impl<'de> BcsDeserialize<'de> for U256 {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        let mut le_bytes = [0; 32];
+        decoder.de.fill_slice(&mut le_bytes)?;
+        Ok(U256::from_le_bytes(le_bytes))
+    }
+}
+
+impl BcsSerialize for u8 {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_raw(&[*self]);
+        Ok(())
+    }
+
+    fn bcs_serialize_slice(values: &[Self], encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_raw(values);
+        Ok(())
+    }
+}
+
+impl<'de> BcsDeserialize<'de> for u8 {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        decoder.de.parse_u8()
+    }
+
+    fn bcs_deserialize_vec(decoder: &mut Decoder<'de>, len: usize) -> Result<Vec<Self>> {
+        Ok(decoder.read_raw(len)?.to_vec())
+    }
+}
+
+impl BcsSerialize for bool {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_raw(&[u8::from(*self)]);
+        Ok(())
+    }
+}
+
+impl<'de> BcsDeserialize<'de> for bool {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        decoder.de.parse_bool()
+    }
+}
+
+impl BcsSerialize for () {
+    fn bcs_serialize(&self, _encoder: &mut Encoder<'_>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'de> BcsDeserialize<'de> for () {
+    fn bcs_deserialize(_decoder: &mut Decoder<'de>) -> Result<Self> {
+        Ok(())
+    }
+}
+
+// Serde treats `PhantomData` as a unit struct, which counts towards the container depth.
+impl<T: ?Sized> BcsSerialize for PhantomData<T> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.enter_container("PhantomData")?;
+        encoder.leave_container();
+        Ok(())
+    }
+}
+
+impl<'de, T: ?Sized> BcsDeserialize<'de> for PhantomData<T> {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        decoder.enter_container("PhantomData")?;
+        decoder.leave_container();
+        Ok(PhantomData)
+    }
+}
+
+impl BcsSerialize for str {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_bytes(self.as_bytes())
+    }
+}
+
+impl BcsSerialize for String {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_bytes(self.as_bytes())
+    }
+}
+
+impl<'de> BcsDeserialize<'de> for String {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        decoder.de.parse_string().map(str::to_owned)
+    }
+}
+
+impl<'de: 'a, 'a> BcsDeserialize<'de> for &'a str {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        decoder.de.parse_string()
+    }
+}
+
+impl<'de: 'a, 'a> BcsDeserialize<'de> for &'a [u8] {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        decoder.de.parse_bytes()
+    }
+}
+
+impl<T: BcsSerialize> BcsSerialize for Option<T> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        match self {
+            None => {
+                encoder.write_raw(&[0]);
+                Ok(())
+            }
+            Some(value) => {
+                encoder.write_raw(&[1]);
+                value.bcs_serialize(encoder)
+            }
+        }
+    }
+}
+
+impl<'de, T: BcsDeserialize<'de>> BcsDeserialize<'de> for Option<T> {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        match decoder.de.next()? {
+            0 => Ok(None),
+            1 => T::bcs_deserialize(decoder).map(Some),
+            _ => Err(Error::ExpectedOption),
+        }
+    }
+}
+
+impl<T: ?Sized + BcsSerialize> BcsSerialize for &T {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        (**self).bcs_serialize(encoder)
+    }
+}
+
+impl<T: ?Sized + BcsSerialize> BcsSerialize for Box<T> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        (**self).bcs_serialize(encoder)
+    }
+}
+
+impl<'de, T: BcsDeserialize<'de>> BcsDeserialize<'de> for Box<T> {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        T::bcs_deserialize(decoder).map(Box::new)
+    }
+}
+
+impl<T: BcsSerialize, const N: usize> BcsSerialize for [T; N] {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        T::bcs_serialize_slice(self, encoder)
+    }
+}
+
+impl<'de, T: BcsDeserialize<'de>, const N: usize> BcsDeserialize<'de> for [T; N] {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        let values = T::bcs_deserialize_vec(decoder, N)?;
+        Ok(values
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("exactly N values were deserialized")))
+    }
+}
+
+impl<T: BcsSerialize> BcsSerialize for [T] {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_seq(self)
+    }
+}
+
+impl<T: BcsSerialize> BcsSerialize for Vec<T> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_seq(self)
+    }
+}
+
+impl<'de, T: BcsDeserialize<'de>> BcsDeserialize<'de> for Vec<T> {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        let len = decoder.read_len()?;
+        T::bcs_deserialize_vec(decoder, len)
+    }
+}
+
+// Like serde, sets are decoded as plain sequences: their order is not checked.
+impl<T: BcsSerialize> BcsSerialize for BTreeSet<T> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_iter(self.iter())
+    }
+}
+
+impl<'de, T: BcsDeserialize<'de> + Ord> BcsDeserialize<'de> for BTreeSet<T> {
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        Vec::bcs_deserialize(decoder)
+            .map(Vec::into_iter)
+            .map(Self::from_iter)
+    }
+}
+
+impl<T: BcsSerialize, S> BcsSerialize for HashSet<T, S> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_iter(self.iter())
+    }
+}
+
+impl<'de, T, S> BcsDeserialize<'de> for HashSet<T, S>
+where
+    T: BcsDeserialize<'de> + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        Vec::bcs_deserialize(decoder)
+            .map(Vec::into_iter)
+            .map(Self::from_iter)
+    }
+}
+
+impl<K: BcsSerialize, V: BcsSerialize> BcsSerialize for BTreeMap<K, V> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_map(self.iter())
+    }
+}
+
+impl<'de, K, V> BcsDeserialize<'de> for BTreeMap<K, V>
+where
+    K: BcsDeserialize<'de> + Ord,
+    V: BcsDeserialize<'de>,
+{
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        let mut map = BTreeMap::new();
+        decoder.read_map(|key, value| {
+            map.insert(key, value);
+        })?;
+        Ok(map)
+    }
+}
+
+impl<K: BcsSerialize, V: BcsSerialize, S> BcsSerialize for HashMap<K, V, S> {
+    fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+        encoder.write_map(self.iter())
+    }
+}
+
+impl<'de, K, V, S> BcsDeserialize<'de> for HashMap<K, V, S>
+where
+    K: BcsDeserialize<'de> + Eq + Hash,
+    V: BcsDeserialize<'de>,
+    S: BuildHasher + Default,
+{
+    fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+        let mut map = HashMap::default();
+        decoder.read_map(|key, value| {
+            map.insert(key, value);
+        })?;
+        Ok(map)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: BcsSerialize),+> BcsSerialize for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn bcs_serialize(&self, encoder: &mut Encoder<'_>) -> Result<()> {
+                let ($($name,)+) = self;
+                $($name.bcs_serialize(encoder)?;)+
+                Ok(())
+            }
+        }
+
+        impl<'de, $($name: BcsDeserialize<'de>),+> BcsDeserialize<'de> for ($($name,)+) {
+            fn bcs_deserialize(decoder: &mut Decoder<'de>) -> Result<Self> {
+                Ok(($($name::bcs_deserialize(decoder)?,)+))
+            }
+        }
+    };
+}
+
+impl_tuple!(T0);
+impl_tuple!(T0 T1);
+impl_tuple!(T0 T1 T2);
+impl_tuple!(T0 T1 T2 T3);
+impl_tuple!(T0 T1 T2 T3 T4);
+impl_tuple!(T0 T1 T2 T3 T4 T5);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7);
+
+/// Implemented by the derive of `BcsSerialize` on enums with pinned tags, along with
+/// `Serialize`. The other derives require it so that the serde encoding cannot differ.
+#[doc(hidden)]
+pub trait SerializesPinnedTags {}
+
+/// Implemented by the derive of `BcsDeserialize` on enums with pinned tags, along with
+/// `Deserialize`.
+#[doc(hidden)]
+pub trait DeserializesPinnedTags {}
+
+/// The tag of the variant identified by `value` when deserializing the enum `name` with
+/// serde, if any.
+#[doc(hidden)]
+#[cfg_attr(not(feature = "reflection"), allow(unused_variables))]
+pub fn variant_tag(name: &'static str, tags: &[u32], value: u64) -> Option<u32> {
+    #[cfg(feature = "reflection")]
+    if let Some(tag) = crate::trace::pinned_tag_while_tracing(name, tags, value) {
+        return tag;
+    }
+    tags.iter().copied().find(|tag| u64::from(*tag) == value)
+}
@@ -0,0 +1,262 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Generation of BCS code for other languages from the formats traced with
+//! `serde-reflection`.
+//!
+//! Given a [`Registry`], the generators emit a self-contained source file with a type
+//! definition for each container, methods to serialize and deserialize it, and a small
+//! runtime following the rules of this crate: little-endian integers, ULEB128-encoded
+//! lengths and variant indices, maps sorted by the bytes of their keys, strict booleans
+//! and UTF-8, and the same limits on sequence lengths and container depth.
+//!
+//! | Format                                | Python                | TypeScript        |
+//! |---------------------------------------|-----------------------|-------------------|
+//! | `Unit`                                | `None`                | `null`            |
+//! | `Bool`                                | `bool`                | `boolean`         |
+//! | `I8` to `I32`, `U8` to `U32`          | `int`                 | `number`          |
+//! | `I64`, `I128`, `U64`, `U128`          | `int`                 | `bigint`          |
+//! | `Str`                                 | `str`                 | `string`          |
+//! | `Bytes`, sequences and arrays of `U8` | `bytes`               | `Uint8Array`      |
+//! | `Option`                              | `Optional[T]`         | `T \| null`       |
+//! | `Seq`, `TupleArray`                   | `List[T]`             | `T[]`             |
+//! | `Tuple`                               | `Tuple[A, B]`         | `[A, B]`          |
+//! | `Map`                                 | `Dict[K, V]`          | `Map<K, V>`       |
+//! | `Uleb32`                              | `int`                 | `number`          |
+//! | `Uleb64`                              | `int`                 | `bigint`          |
+//! | `FixedBytes<N>`                       | `bytes`               | `Uint8Array`      |
+//! | containers                            | classes               | classes           |
+//!
+//! Structs become classes with one attribute per field. Tuple structs and tuple variants
+//! use the attributes `field0`, `field1`, etc., and newtype structs and newtype variants
+//! use `value`. An enum `Foo` becomes a base class `Foo` with one subclass `Foo__Variant`
+//! per variant. Field names that are keywords of the target language get a trailing `_`.
+//!
+//! Floats and `char` are not supported, nor are options of options or of `()`, which
+//! cannot be told apart from `None` in the generated code.
+//!
+//! ```rust
+//! use bcs::codegen::{generate, Language};
+//! use serde::Deserialize;
+//! use serde_reflection::{Tracer, TracerConfig};
+//!
+//! #[derive(Deserialize)]
+//! enum Transfer {
+//!     Coins { amount: u64, to: Vec<u8> },
+//!     Burn(u64),
+//! }
+//!
+//! let mut tracer = Tracer::new(TracerConfig::default());
+//! tracer.trace_simple_type::<Transfer>().unwrap();
+//! let registry = tracer.registry().unwrap();
+//!
+//! let python = generate(Language::Python, &registry).unwrap();
+//! assert!(python.contains("class Transfer__Coins(Transfer):"));
+//! let typescript = generate(Language::TypeScript, &registry).unwrap();
+//! assert!(typescript.contains("export class Transfer__Burn extends Transfer {"));
+//! ```
+
+use crate::{
+    fixed_bytes,
+    uleb128::{ULEB32_NAME, ULEB64_NAME},
+    Error, Result,
+};
+use serde_reflection::{ContainerFormat, Format, Named, Registry, VariantFormat};
+use std::{fmt, str::FromStr};
+
+mod python;
+mod typescript;
+
+pub use python::generate_python;
+pub use typescript::generate_typescript;
+
+/// A target language of the generators.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Language {
+    Python,
+    TypeScript,
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Language::Python => write!(f, "python"),
+            Language::TypeScript => write!(f, "typescript"),
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "python" | "py" => Ok(Language::Python),
+            "typescript" | "ts" => Ok(Language::TypeScript),
+            _ => Err(Error::Custom(format!("unknown language {}", s))),
+        }
+    }
+}
+
+/// Generate the source code for the containers of `registry` in the given language.
+pub fn generate(language: Language, registry: &Registry) -> Result<String> {
+    match language {
+        Language::Python => generate_python(registry),
+        Language::TypeScript => generate_typescript(registry),
+    }
+}
+
+/// The containers that this crate serializes under reserved names. The generated code
+/// encodes them with runtime functions rather than classes.
+#[derive(Clone, Copy)]
+enum Builtin {
+    Uleb32,
+    Uleb64,
+    FixedBytes(usize),
+}
+
+impl Builtin {
+    fn of_name(name: &str) -> Option<Self> {
+        match name {
+            ULEB32_NAME => Some(Builtin::Uleb32),
+            ULEB64_NAME => Some(Builtin::Uleb64),
+            name => fixed_bytes::len(name).map(Builtin::FixedBytes),
+        }
+    }
+}
+
+/// The containers of `registry` that become classes in the generated code.
+fn containers(registry: &Registry) -> impl Iterator<Item = (&str, &ContainerFormat)> {
+    registry
+        .iter()
+        .map(|(name, container)| (name.as_str(), container))
+        .filter(|(name, _)| Builtin::of_name(name).is_none())
+}
+
+/// The shape of the attributes of a struct or of a variant.
+enum Fields<'a> {
+    Unit,
+    Newtype(&'a Format),
+    Tuple(&'a [Format]),
+    Struct(&'a [Named<Format>]),
+}
+
+impl<'a> Fields<'a> {
+    fn of_container(container: &'a ContainerFormat) -> Option<Self> {
+        match container {
+            ContainerFormat::UnitStruct => Some(Fields::Unit),
+            ContainerFormat::NewTypeStruct(format) => Some(Fields::Newtype(format)),
+            ContainerFormat::TupleStruct(formats) => Some(Fields::Tuple(formats)),
+            ContainerFormat::Struct(fields) => Some(Fields::Struct(fields)),
+            ContainerFormat::Enum(_) => None,
+        }
+    }
+
+    fn of_variant(variant: &'a Named<VariantFormat>) -> Result<Self> {
+        match &variant.value {
+            VariantFormat::Unit => Ok(Fields::Unit),
+            VariantFormat::NewType(format) => Ok(Fields::Newtype(format)),
+            VariantFormat::Tuple(formats) => Ok(Fields::Tuple(formats)),
+            VariantFormat::Struct(fields) => Ok(Fields::Struct(fields)),
+            VariantFormat::Variable(_) => Err(Error::Custom(format!(
+                "unknown format of variant {}",
+                variant.name
+            ))),
+        }
+    }
+
+    /// The attributes of the generated class, before escaping, with their formats.
+    fn attributes(&self) -> Vec<(String, &'a Format)> {
+        match self {
+            Fields::Unit => Vec::new(),
+            Fields::Newtype(format) => vec![("value".to_string(), *format)],
+            Fields::Tuple(formats) => formats
+                .iter()
+                .enumerate()
+                .map(|(i, format)| (format!("field{}", i), format))
+                .collect(),
+            Fields::Struct(fields) => fields
+                .iter()
+                .map(|field| (field.name.clone(), &field.value))
+                .collect(),
+        }
+    }
+}
+
+/// Check that `name` can be used as an identifier in the generated code.
+fn check_identifier(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Custom(format!("invalid identifier `{}`", name)))
+    }
+}
+
+/// Append `_` to names that are reserved in the target language.
+fn escape(name: &str, keywords: &[&str]) -> String {
+    if keywords.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Check that the format can be represented in the generated code.
+fn check_format(format: &Format) -> Result<()> {
+    match format {
+        Format::Variable(_) => Err(Error::Custom("unknown format".to_string())),
+        Format::TypeName(name) if Builtin::of_name(name).is_some() => Ok(()),
+        Format::TypeName(name) => check_identifier(name),
+        Format::F32 => Err(Error::NotSupported("f32")),
+        Format::F64 => Err(Error::NotSupported("f64")),
+        Format::Char => Err(Error::NotSupported("char")),
+        Format::Option(content) => match content.as_ref() {
+            Format::Option(_) | Format::Unit => {
+                Err(Error::NotSupported("options of options or of ()"))
+            }
+            content => check_format(content),
+        },
+        Format::Seq(content) | Format::TupleArray { content, .. } => check_format(content),
+        Format::Map { key, value } => {
+            check_format(key)?;
+            check_format(value)
+        }
+        Format::Tuple(formats) => formats.iter().try_for_each(check_format),
+        _ => Ok(()),
+    }
+}
+
+/// Check the names and formats used by a container.
+fn check_container(name: &str, container: &ContainerFormat) -> Result<()> {
+    check_identifier(name)?;
+    let check_fields = |fields: &Fields| {
+        fields
+            .attributes()
+            .into_iter()
+            .try_for_each(|(name, format)| {
+                check_identifier(&name)?;
+                check_format(format)
+            })
+    };
+    match container {
+        ContainerFormat::Enum(variants) => variants.values().try_for_each(|variant| {
+            check_identifier(&variant.name)?;
+            check_fields(&Fields::of_variant(variant)?)
+        }),
+        container => check_fields(&Fields::of_container(container).unwrap()),
+    }
+}
+
+/// Whether values of this format are represented as byte strings.
+fn is_bytes(format: &Format) -> bool {
+    match format {
+        Format::Bytes => true,
+        Format::Seq(content) | Format::TupleArray { content, .. } => **content == Format::U8,
+        _ => false,
+    }
+}
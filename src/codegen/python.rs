@@ -0,0 +1,300 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{check_container, containers, escape, is_bytes, Builtin, Fields};
+use crate::Result;
+use serde_reflection::{ContainerFormat, Format, Named, Registry, VariantFormat};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+const RUNTIME: &str = include_str!("runtime.py");
+
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Generate a Python module defining the containers of `registry` as dataclasses, with
+/// the methods `bcs_serialize` and `bcs_deserialize`.
+///
+/// The module only depends on the standard library of Python 3.7 or later.
+pub fn generate_python(registry: &Registry) -> Result<String> {
+    for (name, container) in containers(registry) {
+        check_container(name, container)?;
+    }
+    let mut output = String::new();
+    write_module(&mut output, registry).expect("writing to a String cannot fail");
+    Ok(output)
+}
+
+fn write_module(out: &mut String, registry: &Registry) -> fmt::Result {
+    writeln!(out, "# Generated by the bcs crate. Do not edit.")?;
+    writeln!(out, "#")?;
+    out.push_str(RUNTIME);
+    for (name, container) in containers(registry) {
+        match container {
+            ContainerFormat::Enum(variants) => write_enum(out, name, variants)?,
+            container => write_struct(out, name, &Fields::of_container(container).unwrap())?,
+        }
+    }
+    Ok(())
+}
+
+fn write_struct(out: &mut String, name: &str, fields: &Fields) -> fmt::Result {
+    writeln!(out, "\n\n@dataclasses.dataclass(frozen=True)")?;
+    writeln!(out, "class {}(BcsObject):", name)?;
+    write_attributes(out, fields)?;
+    writeln!(
+        out,
+        "\n    def bcs_serialize_into(self, serializer: BcsSerializer) -> None:"
+    )?;
+    writeln!(out, "        serializer.enter(\"{}\")", name)?;
+    write_serialize_fields(out, fields)?;
+    writeln!(out, "        serializer.leave()")?;
+    writeln!(out, "\n    @classmethod")?;
+    writeln!(
+        out,
+        "    def bcs_deserialize_from(cls, deserializer: BcsDeserializer) -> {}:",
+        name
+    )?;
+    writeln!(out, "        deserializer.enter(\"{}\")", name)?;
+    write!(out, "        value = ")?;
+    write_constructor(out, fields, "        ")?;
+    writeln!(out, "        deserializer.leave()")?;
+    writeln!(out, "        return value")
+}
+
+fn write_enum(
+    out: &mut String,
+    name: &str,
+    variants: &BTreeMap<u32, Named<VariantFormat>>,
+) -> fmt::Result {
+    writeln!(out, "\n\nclass {}(BcsObject):", name)?;
+    writeln!(out, "    INDEX: typing.ClassVar[int]")?;
+    writeln!(
+        out,
+        "    VARIANTS: typing.ClassVar[typing.Dict[int, typing.Type[{}]]]",
+        name
+    )?;
+    writeln!(
+        out,
+        "\n    def bcs_serialize_into(self, serializer: BcsSerializer) -> None:"
+    )?;
+    writeln!(out, "        serializer.enter(\"{}\")", name)?;
+    writeln!(out, "        serializer.uleb128(self.INDEX)")?;
+    writeln!(out, "        self.bcs_serialize_payload(serializer)")?;
+    writeln!(out, "        serializer.leave()")?;
+    writeln!(
+        out,
+        "\n    def bcs_serialize_payload(self, serializer: BcsSerializer) -> None:"
+    )?;
+    writeln!(out, "        raise NotImplementedError")?;
+    writeln!(out, "\n    @classmethod")?;
+    writeln!(
+        out,
+        "    def bcs_deserialize_from(cls, deserializer: BcsDeserializer) -> {}:",
+        name
+    )?;
+    writeln!(out, "        deserializer.enter(\"{}\")", name)?;
+    writeln!(out, "        index = deserializer.uleb128()")?;
+    writeln!(out, "        if index not in {}.VARIANTS:", name)?;
+    writeln!(out, "            raise BcsError(")?;
+    writeln!(
+        out,
+        "                \"invalid value: integer `%d`, expected variant index 0 <= i < %d\""
+    )?;
+    writeln!(out, "                % (index, len({}.VARIANTS))", name)?;
+    writeln!(out, "            )")?;
+    writeln!(
+        out,
+        "        value = {}.VARIANTS[index].bcs_deserialize_payload(deserializer)",
+        name
+    )?;
+    writeln!(out, "        if not isinstance(value, cls):")?;
+    writeln!(
+        out,
+        "            raise BcsError(\"expected %s, got %r\" % (cls.__name__, value))"
+    )?;
+    writeln!(out, "        deserializer.leave()")?;
+    writeln!(out, "        return value")?;
+
+    for (index, variant) in variants {
+        // Variant formats were checked before writing.
+        let fields = Fields::of_variant(variant).unwrap();
+        let class = format!("{}__{}", name, variant.name);
+        writeln!(out, "\n\n@dataclasses.dataclass(frozen=True)")?;
+        writeln!(out, "class {}({}):", class, name)?;
+        writeln!(out, "    INDEX = {}", index)?;
+        write_attributes(out, &fields)?;
+        writeln!(
+            out,
+            "\n    def bcs_serialize_payload(self, serializer: BcsSerializer) -> None:"
+        )?;
+        if fields.attributes().is_empty() {
+            writeln!(out, "        pass")?;
+        } else {
+            write_serialize_fields(out, &fields)?;
+        }
+        writeln!(out, "\n    @classmethod")?;
+        writeln!(
+            out,
+            "    def bcs_deserialize_payload(cls, deserializer: BcsDeserializer) -> {}:",
+            class
+        )?;
+        write!(out, "        return ")?;
+        write_constructor(out, &fields, "        ")?;
+    }
+
+    let classes = variants
+        .iter()
+        .map(|(index, variant)| format!("    {}: {}__{},\n", index, name, variant.name))
+        .collect::<String>();
+    writeln!(out, "\n\n{}.VARIANTS = {{\n{}}}", name, classes)
+}
+
+fn write_attributes(out: &mut String, fields: &Fields) -> fmt::Result {
+    for (attribute, format) in fields.attributes() {
+        writeln!(
+            out,
+            "    {}: {}",
+            escape(&attribute, KEYWORDS),
+            quote_type(format)
+        )?;
+    }
+    Ok(())
+}
+
+fn write_serialize_fields(out: &mut String, fields: &Fields) -> fmt::Result {
+    for (attribute, format) in fields.attributes() {
+        writeln!(
+            out,
+            "        {}(serializer, self.{})",
+            quote_function("ser", format),
+            escape(&attribute, KEYWORDS)
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a call to the constructor of the class, deserializing its attributes in order.
+fn write_constructor(out: &mut String, fields: &Fields, indent: &str) -> fmt::Result {
+    let attributes = fields.attributes();
+    if attributes.is_empty() {
+        return writeln!(out, "cls()");
+    }
+    writeln!(out, "cls(")?;
+    for (attribute, format) in attributes {
+        writeln!(
+            out,
+            "{}    {}={}(deserializer),",
+            indent,
+            escape(&attribute, KEYWORDS),
+            quote_function("de", format)
+        )?;
+    }
+    writeln!(out, "{})", indent)
+}
+
+/// The type annotation of the values of a format.
+fn quote_type(format: &Format) -> String {
+    match format {
+        Format::Unit => "None".to_string(),
+        Format::Bool => "bool".to_string(),
+        Format::I8
+        | Format::I16
+        | Format::I32
+        | Format::I64
+        | Format::I128
+        | Format::U8
+        | Format::U16
+        | Format::U32
+        | Format::U64
+        | Format::U128 => "int".to_string(),
+        Format::Str => "str".to_string(),
+        format if is_bytes(format) => "bytes".to_string(),
+        Format::TypeName(name) => match Builtin::of_name(name) {
+            Some(Builtin::Uleb32) | Some(Builtin::Uleb64) => "int".to_string(),
+            Some(Builtin::FixedBytes(_)) => "bytes".to_string(),
+            None => name.clone(),
+        },
+        Format::Option(content) => format!("typing.Optional[{}]", quote_type(content)),
+        Format::Seq(content) | Format::TupleArray { content, .. } => {
+            format!("typing.List[{}]", quote_type(content))
+        }
+        Format::Map { key, value } => {
+            format!("typing.Dict[{}, {}]", quote_type(key), quote_type(value))
+        }
+        Format::Tuple(formats) if formats.is_empty() => "typing.Tuple[()]".to_string(),
+        Format::Tuple(formats) => format!(
+            "typing.Tuple[{}]",
+            formats
+                .iter()
+                .map(quote_type)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // Rejected by `check_format`.
+        _ => unreachable!("unsupported format {:?}", format),
+    }
+}
+
+/// The runtime function serializing (`prefix` is `ser`) or deserializing (`prefix` is `de`)
+/// values of a format.
+fn quote_function(prefix: &str, format: &Format) -> String {
+    match format {
+        Format::Unit => format!("{}_unit", prefix),
+        Format::Bool => format!("{}_bool", prefix),
+        Format::I8 => format!("{}_i8", prefix),
+        Format::I16 => format!("{}_i16", prefix),
+        Format::I32 => format!("{}_i32", prefix),
+        Format::I64 => format!("{}_i64", prefix),
+        Format::I128 => format!("{}_i128", prefix),
+        Format::U8 => format!("{}_u8", prefix),
+        Format::U16 => format!("{}_u16", prefix),
+        Format::U32 => format!("{}_u32", prefix),
+        Format::U64 => format!("{}_u64", prefix),
+        Format::U128 => format!("{}_u128", prefix),
+        Format::Str => format!("{}_str", prefix),
+        Format::TupleArray { content, size } if **content == Format::U8 => {
+            format!("{}_fixed_bytes({})", prefix, size)
+        }
+        format if is_bytes(format) => format!("{}_bytes", prefix),
+        Format::TypeName(name) => match Builtin::of_name(name) {
+            Some(Builtin::Uleb32) => format!("{}_uleb32", prefix),
+            Some(Builtin::Uleb64) => format!("{}_uleb64", prefix),
+            Some(Builtin::FixedBytes(size)) => format!("{}_fixed_bytes({})", prefix, size),
+            None => format!("{}_object({})", prefix, name),
+        },
+        Format::Option(content) => {
+            format!("{}_option({})", prefix, quote_function(prefix, content))
+        }
+        Format::Seq(content) => format!("{}_seq({})", prefix, quote_function(prefix, content)),
+        Format::TupleArray { content, size } => format!(
+            "{}_array({}, {})",
+            prefix,
+            quote_function(prefix, content),
+            size
+        ),
+        Format::Map { key, value } => format!(
+            "{}_map({}, {})",
+            prefix,
+            quote_function(prefix, key),
+            quote_function(prefix, value)
+        ),
+        Format::Tuple(formats) => format!(
+            "{}_tuple({})",
+            prefix,
+            formats
+                .iter()
+                .map(|format| quote_function(prefix, format))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // Rejected by `check_format`.
+        _ => unreachable!("unsupported format {:?}", format),
+    }
+}
@@ -0,0 +1,342 @@
+# Runtime of the generated BCS code. Mirrors the rules of the Rust implementation:
+# little-endian integers, ULEB128 lengths and variant indices, maps sorted by the bytes of
+# their keys, and limits on lengths and container depth.
+
+from __future__ import annotations
+
+import dataclasses
+import typing
+
+MAX_SEQUENCE_LENGTH = (1 << 31) - 1
+MAX_CONTAINER_DEPTH = 500
+
+
+class BcsError(ValueError):
+    pass
+
+
+class BcsSerializer:
+    def __init__(self, remaining_depth: int = MAX_CONTAINER_DEPTH):
+        self.output = bytearray()
+        self.remaining_depth = remaining_depth
+
+    def enter(self, name: str) -> None:
+        if self.remaining_depth == 0:
+            raise BcsError("exceeded max container depth while entering: " + name)
+        self.remaining_depth -= 1
+
+    def leave(self) -> None:
+        self.remaining_depth += 1
+
+    def uleb128(self, value: int) -> None:
+        while value >= 0x80:
+            self.output.append((value & 0x7F) | 0x80)
+            value >>= 7
+        self.output.append(value)
+
+    def length(self, length: int) -> None:
+        if length > MAX_SEQUENCE_LENGTH:
+            raise BcsError("exceeded max sequence length: %d" % length)
+        self.uleb128(length)
+
+    def integer(self, value: int, size: int, signed: bool) -> None:
+        if isinstance(value, bool) or not isinstance(value, int):
+            raise BcsError("expected an integer, got %r" % (value,))
+        try:
+            self.output += value.to_bytes(size, "little", signed=signed)
+        except OverflowError:
+            raise BcsError("integer out of range: %d" % value) from None
+
+
+class BcsDeserializer:
+    def __init__(self, data: bytes, remaining_depth: int = MAX_CONTAINER_DEPTH):
+        self.data = bytes(data)
+        self.position = 0
+        self.remaining_depth = remaining_depth
+
+    def enter(self, name: str) -> None:
+        if self.remaining_depth == 0:
+            raise BcsError("exceeded max container depth while entering: " + name)
+        self.remaining_depth -= 1
+
+    def leave(self) -> None:
+        self.remaining_depth += 1
+
+    def read(self, size: int) -> bytes:
+        end = self.position + size
+        if end > len(self.data):
+            raise BcsError("unexpected end of input")
+        value = self.data[self.position : end]
+        self.position = end
+        return value
+
+    def uleb128(self, bits: int = 32) -> int:
+        value = 0
+        for shift in range(0, bits, 7):
+            byte = self.read(1)[0]
+            digit = byte & 0x7F
+            value |= digit << shift
+            if digit == byte:
+                if shift > 0 and digit == 0:
+                    raise BcsError("ULEB128 encoding was not minimal in size")
+                if value >= 1 << bits:
+                    break
+                return value
+        raise BcsError("ULEB128-encoded integer did not fit in the target size")
+
+    def length(self) -> int:
+        length = self.uleb128()
+        if length > MAX_SEQUENCE_LENGTH:
+            raise BcsError("exceeded max sequence length: %d" % length)
+        return length
+
+    def integer(self, size: int, signed: bool) -> int:
+        return int.from_bytes(self.read(size), "little", signed=signed)
+
+    def end(self) -> None:
+        if self.position != len(self.data):
+            raise BcsError("remaining input")
+
+
+def _integer(size: int, signed: bool):
+    def serialize(serializer: BcsSerializer, value: int) -> None:
+        serializer.integer(value, size, signed)
+
+    def deserialize(deserializer: BcsDeserializer) -> int:
+        return deserializer.integer(size, signed)
+
+    return serialize, deserialize
+
+
+ser_u8, de_u8 = _integer(1, False)
+ser_u16, de_u16 = _integer(2, False)
+ser_u32, de_u32 = _integer(4, False)
+ser_u64, de_u64 = _integer(8, False)
+ser_u128, de_u128 = _integer(16, False)
+ser_i8, de_i8 = _integer(1, True)
+ser_i16, de_i16 = _integer(2, True)
+ser_i32, de_i32 = _integer(4, True)
+ser_i64, de_i64 = _integer(8, True)
+ser_i128, de_i128 = _integer(16, True)
+
+
+def _uleb128(bits: int):
+    def serialize(serializer: BcsSerializer, value: int) -> None:
+        if isinstance(value, bool) or not isinstance(value, int):
+            raise BcsError("expected an integer, got %r" % (value,))
+        if not 0 <= value < 1 << bits:
+            raise BcsError("integer out of range: %d" % value)
+        serializer.uleb128(value)
+
+    def deserialize(deserializer: BcsDeserializer) -> int:
+        return deserializer.uleb128(bits)
+
+    return serialize, deserialize
+
+
+ser_uleb32, de_uleb32 = _uleb128(32)
+ser_uleb64, de_uleb64 = _uleb128(64)
+
+
+def ser_unit(serializer: BcsSerializer, value: None) -> None:
+    if value is not None:
+        raise BcsError("expected None, got %r" % (value,))
+
+
+def de_unit(deserializer: BcsDeserializer) -> None:
+    return None
+
+
+def ser_bool(serializer: BcsSerializer, value: bool) -> None:
+    if not isinstance(value, bool):
+        raise BcsError("expected a boolean, got %r" % (value,))
+    serializer.output.append(1 if value else 0)
+
+
+def de_bool(deserializer: BcsDeserializer) -> bool:
+    byte = deserializer.read(1)[0]
+    if byte > 1:
+        raise BcsError("expected boolean")
+    return byte == 1
+
+
+def ser_bytes(serializer: BcsSerializer, value: bytes) -> None:
+    serializer.length(len(value))
+    serializer.output += value
+
+
+def de_bytes(deserializer: BcsDeserializer) -> bytes:
+    return deserializer.read(deserializer.length())
+
+
+def ser_str(serializer: BcsSerializer, value: str) -> None:
+    ser_bytes(serializer, value.encode("utf-8"))
+
+
+def de_str(deserializer: BcsDeserializer) -> str:
+    try:
+        return de_bytes(deserializer).decode("utf-8")
+    except UnicodeDecodeError:
+        raise BcsError("malformed utf8") from None
+
+
+def ser_fixed_bytes(size: int):
+    def serialize(serializer: BcsSerializer, value: bytes) -> None:
+        if len(value) != size:
+            raise BcsError("expected %d bytes, got %d" % (size, len(value)))
+        serializer.output += value
+
+    return serialize
+
+
+def de_fixed_bytes(size: int):
+    def deserialize(deserializer: BcsDeserializer) -> bytes:
+        return deserializer.read(size)
+
+    return deserialize
+
+
+def ser_option(serialize):
+    def serialize_option(serializer: BcsSerializer, value) -> None:
+        if value is None:
+            serializer.output.append(0)
+        else:
+            serializer.output.append(1)
+            serialize(serializer, value)
+
+    return serialize_option
+
+
+def de_option(deserialize):
+    def deserialize_option(deserializer: BcsDeserializer):
+        tag = deserializer.read(1)[0]
+        if tag == 0:
+            return None
+        if tag == 1:
+            return deserialize(deserializer)
+        raise BcsError("expected option type")
+
+    return deserialize_option
+
+
+def ser_seq(serialize):
+    def serialize_seq(serializer: BcsSerializer, value: list) -> None:
+        serializer.length(len(value))
+        for item in value:
+            serialize(serializer, item)
+
+    return serialize_seq
+
+
+def de_seq(deserialize):
+    def deserialize_seq(deserializer: BcsDeserializer) -> list:
+        return [deserialize(deserializer) for _ in range(deserializer.length())]
+
+    return deserialize_seq
+
+
+def ser_array(serialize, size: int):
+    def serialize_array(serializer: BcsSerializer, value: list) -> None:
+        if len(value) != size:
+            raise BcsError("expected %d elements, got %d" % (size, len(value)))
+        for item in value:
+            serialize(serializer, item)
+
+    return serialize_array
+
+
+def de_array(deserialize, size: int):
+    def deserialize_array(deserializer: BcsDeserializer) -> list:
+        return [deserialize(deserializer) for _ in range(size)]
+
+    return deserialize_array
+
+
+def ser_tuple(*serializers):
+    def serialize_tuple(serializer: BcsSerializer, value: tuple) -> None:
+        if len(value) != len(serializers):
+            raise BcsError("expected %d elements, got %d" % (len(serializers), len(value)))
+        for serialize, item in zip(serializers, value):
+            serialize(serializer, item)
+
+    return serialize_tuple
+
+
+def de_tuple(*deserializers):
+    def deserialize_tuple(deserializer: BcsDeserializer) -> tuple:
+        return tuple(deserialize(deserializer) for deserialize in deserializers)
+
+    return deserialize_tuple
+
+
+def ser_map(serialize_key, serialize_value):
+    def serialize_map(serializer: BcsSerializer, value: dict) -> None:
+        entries = []
+        for key, item in value.items():
+            # Keys are encoded with the depth of the map.
+            key_serializer = BcsSerializer(serializer.remaining_depth)
+            serialize_key(key_serializer, key)
+            entries.append((bytes(key_serializer.output), item))
+        entries.sort(key=lambda entry: entry[0])
+        for previous, entry in zip(entries, entries[1:]):
+            if previous[0] == entry[0]:
+                raise BcsError("keys of serialized maps must be unique")
+        serializer.length(len(entries))
+        for key, item in entries:
+            serializer.output += key
+            serialize_value(serializer, item)
+
+    return serialize_map
+
+
+def de_map(deserialize_key, deserialize_value):
+    def deserialize_map(deserializer: BcsDeserializer) -> dict:
+        result = {}
+        previous_key = None
+        for _ in range(deserializer.length()):
+            start = deserializer.position
+            key = deserialize_key(deserializer)
+            key_bytes = deserializer.data[start : deserializer.position]
+            if previous_key is not None and key_bytes <= previous_key:
+                raise BcsError("keys of serialized maps must be unique and in increasing order")
+            previous_key = key_bytes
+            result[key] = deserialize_value(deserializer)
+        return result
+
+    return deserialize_map
+
+
+def ser_object(cls):
+    def serialize_object(serializer: BcsSerializer, value) -> None:
+        if not isinstance(value, cls):
+            raise BcsError("expected %s, got %r" % (cls.__name__, value))
+        value.bcs_serialize_into(serializer)
+
+    return serialize_object
+
+
+def de_object(cls):
+    return cls.bcs_deserialize_from
+
+
+class BcsObject:
+    """Base class of the generated types."""
+
+    def bcs_serialize_into(self, serializer: BcsSerializer) -> None:
+        raise NotImplementedError
+
+    @classmethod
+    def bcs_deserialize_from(cls, deserializer: BcsDeserializer):
+        raise NotImplementedError
+
+    def bcs_serialize(self) -> bytes:
+        serializer = BcsSerializer()
+        self.bcs_serialize_into(serializer)
+        return bytes(serializer.output)
+
+    @classmethod
+    def bcs_deserialize(cls, data: bytes):
+        deserializer = BcsDeserializer(data)
+        value = cls.bcs_deserialize_from(deserializer)
+        deserializer.end()
+        return value
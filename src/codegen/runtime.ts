@@ -0,0 +1,451 @@
+// Runtime of the generated BCS code. Mirrors the rules of the Rust implementation:
+// little-endian integers, ULEB128 lengths and variant indices, maps sorted by the bytes of
+// their keys, and limits on lengths and container depth.
+
+export const MAX_SEQUENCE_LENGTH = 2 ** 31 - 1;
+export const MAX_CONTAINER_DEPTH = 500;
+
+export class BcsError extends Error {}
+
+export type Serialize<T> = (serializer: BcsSerializer, value: T) => void;
+export type Deserialize<T> = (deserializer: BcsDeserializer) => T;
+
+export class BcsSerializer {
+  private buffer = new Uint8Array(64);
+  private size = 0;
+
+  constructor(public remainingDepth: number = MAX_CONTAINER_DEPTH) {}
+
+  enter(name: string): void {
+    if (this.remainingDepth === 0) {
+      throw new BcsError("exceeded max container depth while entering: " + name);
+    }
+    this.remainingDepth -= 1;
+  }
+
+  leave(): void {
+    this.remainingDepth += 1;
+  }
+
+  write(bytes: Uint8Array): void {
+    if (this.size + bytes.length > this.buffer.length) {
+      const buffer = new Uint8Array(Math.max(2 * this.buffer.length, this.size + bytes.length));
+      buffer.set(this.buffer.subarray(0, this.size));
+      this.buffer = buffer;
+    }
+    this.buffer.set(bytes, this.size);
+    this.size += bytes.length;
+  }
+
+  writeByte(byte: number): void {
+    this.write(Uint8Array.of(byte));
+  }
+
+  uleb128(value: number): void {
+    while (value >= 0x80) {
+      this.writeByte((value % 0x80) | 0x80);
+      value = Math.floor(value / 0x80);
+    }
+    this.writeByte(value);
+  }
+
+  length(length: number): void {
+    if (length > MAX_SEQUENCE_LENGTH) {
+      throw new BcsError("exceeded max sequence length: " + length);
+    }
+    this.uleb128(length);
+  }
+
+  integer(value: bigint, size: number, signed: boolean): void {
+    const bits = BigInt(8 * size);
+    const min = signed ? -(1n << (bits - 1n)) : 0n;
+    const max = signed ? (1n << (bits - 1n)) - 1n : (1n << bits) - 1n;
+    if (typeof value !== "bigint" || value < min || value > max) {
+      throw new BcsError("integer out of range: " + value);
+    }
+    let unsigned = BigInt.asUintN(Number(bits), value);
+    const bytes = new Uint8Array(size);
+    for (let i = 0; i < size; i++) {
+      bytes[i] = Number(unsigned & 0xffn);
+      unsigned >>= 8n;
+    }
+    this.write(bytes);
+  }
+
+  output(): Uint8Array {
+    return this.buffer.slice(0, this.size);
+  }
+}
+
+export class BcsDeserializer {
+  position = 0;
+
+  constructor(
+    public readonly data: Uint8Array,
+    public remainingDepth: number = MAX_CONTAINER_DEPTH,
+  ) {}
+
+  enter(name: string): void {
+    if (this.remainingDepth === 0) {
+      throw new BcsError("exceeded max container depth while entering: " + name);
+    }
+    this.remainingDepth -= 1;
+  }
+
+  leave(): void {
+    this.remainingDepth += 1;
+  }
+
+  read(size: number): Uint8Array {
+    const end = this.position + size;
+    if (end > this.data.length) {
+      throw new BcsError("unexpected end of input");
+    }
+    const value = this.data.slice(this.position, end);
+    this.position = end;
+    return value;
+  }
+
+  readByte(): number {
+    return this.read(1)[0];
+  }
+
+  uleb128(): number {
+    let value = 0;
+    for (let shift = 0; shift < 32; shift += 7) {
+      const byte = this.readByte();
+      const digit = byte & 0x7f;
+      value += digit * 2 ** shift;
+      if (digit === byte) {
+        if (shift > 0 && digit === 0) {
+          throw new BcsError("ULEB128 encoding was not minimal in size");
+        }
+        if (value >= 2 ** 32) {
+          break;
+        }
+        return value;
+      }
+    }
+    throw new BcsError("ULEB128-encoded integer did not fit in the target size");
+  }
+
+  length(): number {
+    const length = this.uleb128();
+    if (length > MAX_SEQUENCE_LENGTH) {
+      throw new BcsError("exceeded max sequence length: " + length);
+    }
+    return length;
+  }
+
+  integer(size: number, signed: boolean): bigint {
+    const bytes = this.read(size);
+    let value = 0n;
+    for (let i = size - 1; i >= 0; i--) {
+      value = (value << 8n) | BigInt(bytes[i]);
+    }
+    return signed ? BigInt.asIntN(8 * size, value) : value;
+  }
+
+  end(): void {
+    if (this.position !== this.data.length) {
+      throw new BcsError("remaining input");
+    }
+  }
+}
+
+function smallInteger(size: number, signed: boolean): [Serialize<number>, Deserialize<number>] {
+  return [
+    (serializer, value) => {
+      if (!Number.isInteger(value)) {
+        throw new BcsError("expected an integer, got " + value);
+      }
+      serializer.integer(BigInt(value), size, signed);
+    },
+    (deserializer) => Number(deserializer.integer(size, signed)),
+  ];
+}
+
+function largeInteger(size: number, signed: boolean): [Serialize<bigint>, Deserialize<bigint>] {
+  return [
+    (serializer, value) => serializer.integer(value, size, signed),
+    (deserializer) => deserializer.integer(size, signed),
+  ];
+}
+
+export const [serU8, deU8] = smallInteger(1, false);
+export const [serU16, deU16] = smallInteger(2, false);
+export const [serU32, deU32] = smallInteger(4, false);
+export const [serU64, deU64] = largeInteger(8, false);
+export const [serU128, deU128] = largeInteger(16, false);
+export const [serI8, deI8] = smallInteger(1, true);
+export const [serI16, deI16] = smallInteger(2, true);
+export const [serI32, deI32] = smallInteger(4, true);
+export const [serI64, deI64] = largeInteger(8, true);
+export const [serI128, deI128] = largeInteger(16, true);
+
+export function serUleb32(serializer: BcsSerializer, value: number): void {
+  if (!Number.isInteger(value) || value < 0 || value >= 2 ** 32) {
+    throw new BcsError("integer out of range: " + value);
+  }
+  serializer.uleb128(value);
+}
+
+export function deUleb32(deserializer: BcsDeserializer): number {
+  return deserializer.uleb128();
+}
+
+export function serUleb64(serializer: BcsSerializer, value: bigint): void {
+  if (typeof value !== "bigint" || value < 0n || value >= 1n << 64n) {
+    throw new BcsError("integer out of range: " + value);
+  }
+  while (value >= 0x80n) {
+    serializer.writeByte(Number(value & 0x7fn) | 0x80);
+    value >>= 7n;
+  }
+  serializer.writeByte(Number(value));
+}
+
+export function deUleb64(deserializer: BcsDeserializer): bigint {
+  let value = 0n;
+  for (let shift = 0n; shift < 64n; shift += 7n) {
+    const byte = deserializer.readByte();
+    const digit = byte & 0x7f;
+    value |= BigInt(digit) << shift;
+    if (digit === byte) {
+      if (shift > 0n && digit === 0) {
+        throw new BcsError("ULEB128 encoding was not minimal in size");
+      }
+      if (value >= 1n << 64n) {
+        break;
+      }
+      return value;
+    }
+  }
+  throw new BcsError("ULEB128-encoded integer did not fit in the target size");
+}
+
+export function serUnit(serializer: BcsSerializer, value: null): void {
+  if (value !== null) {
+    throw new BcsError("expected null, got " + value);
+  }
+}
+
+export function deUnit(deserializer: BcsDeserializer): null {
+  return null;
+}
+
+export function serBool(serializer: BcsSerializer, value: boolean): void {
+  serializer.writeByte(value ? 1 : 0);
+}
+
+export function deBool(deserializer: BcsDeserializer): boolean {
+  const byte = deserializer.readByte();
+  if (byte > 1) {
+    throw new BcsError("expected boolean");
+  }
+  return byte === 1;
+}
+
+export function serBytes(serializer: BcsSerializer, value: Uint8Array): void {
+  serializer.length(value.length);
+  serializer.write(value);
+}
+
+export function deBytes(deserializer: BcsDeserializer): Uint8Array {
+  return deserializer.read(deserializer.length());
+}
+
+export function serStr(serializer: BcsSerializer, value: string): void {
+  serBytes(serializer, new TextEncoder().encode(value));
+}
+
+export function deStr(deserializer: BcsDeserializer): string {
+  try {
+    return new TextDecoder("utf-8", { fatal: true }).decode(deBytes(deserializer));
+  } catch (error) {
+    if (error instanceof BcsError) {
+      throw error;
+    }
+    throw new BcsError("malformed utf8");
+  }
+}
+
+export function serFixedBytes(size: number): Serialize<Uint8Array> {
+  return (serializer, value) => {
+    if (value.length !== size) {
+      throw new BcsError(`expected ${size} bytes, got ${value.length}`);
+    }
+    serializer.write(value);
+  };
+}
+
+export function deFixedBytes(size: number): Deserialize<Uint8Array> {
+  return (deserializer) => deserializer.read(size);
+}
+
+export function serOption<T>(serialize: Serialize<T>): Serialize<T | null> {
+  return (serializer, value) => {
+    if (value === null) {
+      serializer.writeByte(0);
+    } else {
+      serializer.writeByte(1);
+      serialize(serializer, value);
+    }
+  };
+}
+
+export function deOption<T>(deserialize: Deserialize<T>): Deserialize<T | null> {
+  return (deserializer) => {
+    const tag = deserializer.readByte();
+    if (tag === 0) {
+      return null;
+    }
+    if (tag === 1) {
+      return deserialize(deserializer);
+    }
+    throw new BcsError("expected option type");
+  };
+}
+
+export function serSeq<T>(serialize: Serialize<T>): Serialize<T[]> {
+  return (serializer, value) => {
+    serializer.length(value.length);
+    for (const item of value) {
+      serialize(serializer, item);
+    }
+  };
+}
+
+export function deSeq<T>(deserialize: Deserialize<T>): Deserialize<T[]> {
+  return (deserializer) => {
+    const length = deserializer.length();
+    const value: T[] = [];
+    for (let i = 0; i < length; i++) {
+      value.push(deserialize(deserializer));
+    }
+    return value;
+  };
+}
+
+export function serArray<T>(serialize: Serialize<T>, size: number): Serialize<T[]> {
+  return (serializer, value) => {
+    if (value.length !== size) {
+      throw new BcsError(`expected ${size} elements, got ${value.length}`);
+    }
+    for (const item of value) {
+      serialize(serializer, item);
+    }
+  };
+}
+
+export function deArray<T>(deserialize: Deserialize<T>, size: number): Deserialize<T[]> {
+  return (deserializer) => {
+    const value: T[] = [];
+    for (let i = 0; i < size; i++) {
+      value.push(deserialize(deserializer));
+    }
+    return value;
+  };
+}
+
+export function serTuple<T extends unknown[]>(
+  ...serializers: { [K in keyof T]: Serialize<T[K]> }
+): Serialize<T> {
+  return (serializer, value) => {
+    if (value.length !== serializers.length) {
+      throw new BcsError(`expected ${serializers.length} elements, got ${value.length}`);
+    }
+    serializers.forEach((serialize: Serialize<unknown>, i: number) => serialize(serializer, value[i]));
+  };
+}
+
+export function deTuple<T extends unknown[]>(
+  ...deserializers: { [K in keyof T]: Deserialize<T[K]> }
+): Deserialize<T> {
+  return (deserializer) =>
+    deserializers.map((deserialize: Deserialize<unknown>) => deserialize(deserializer)) as T;
+}
+
+function compareBytes(left: Uint8Array, right: Uint8Array): number {
+  const length = Math.min(left.length, right.length);
+  for (let i = 0; i < length; i++) {
+    if (left[i] !== right[i]) {
+      return left[i] - right[i];
+    }
+  }
+  return left.length - right.length;
+}
+
+export function serMap<K, V>(serializeKey: Serialize<K>, serializeValue: Serialize<V>): Serialize<Map<K, V>> {
+  return (serializer, value) => {
+    const entries: [Uint8Array, V][] = [];
+    for (const [key, item] of value) {
+      // Keys are encoded with the depth of the map.
+      const keySerializer = new BcsSerializer(serializer.remainingDepth);
+      serializeKey(keySerializer, key);
+      entries.push([keySerializer.output(), item]);
+    }
+    entries.sort((left, right) => compareBytes(left[0], right[0]));
+    for (let i = 1; i < entries.length; i++) {
+      if (compareBytes(entries[i - 1][0], entries[i][0]) === 0) {
+        throw new BcsError("keys of serialized maps must be unique");
+      }
+    }
+    serializer.length(entries.length);
+    for (const [key, item] of entries) {
+      serializer.write(key);
+      serializeValue(serializer, item);
+    }
+  };
+}
+
+export function deMap<K, V>(deserializeKey: Deserialize<K>, deserializeValue: Deserialize<V>): Deserialize<Map<K, V>> {
+  return (deserializer) => {
+    const length = deserializer.length();
+    const value = new Map<K, V>();
+    let previousKey: Uint8Array | null = null;
+    for (let i = 0; i < length; i++) {
+      const start = deserializer.position;
+      const key = deserializeKey(deserializer);
+      const keyBytes = deserializer.data.subarray(start, deserializer.position);
+      if (previousKey !== null && compareBytes(keyBytes, previousKey) <= 0) {
+        throw new BcsError("keys of serialized maps must be unique and in increasing order");
+      }
+      previousKey = keyBytes;
+      value.set(key, deserializeValue(deserializer));
+    }
+    return value;
+  };
+}
+
+export interface BcsClass<T> {
+  name: string;
+  deserialize(deserializer: BcsDeserializer): T;
+}
+
+export function serObject<T extends BcsObject>(cls: BcsClass<T>): Serialize<T> {
+  return (serializer, value) => value.serialize(serializer);
+}
+
+export function deObject<T>(cls: BcsClass<T>): Deserialize<T> {
+  return (deserializer) => cls.deserialize(deserializer);
+}
+
+/** Base class of the generated types. */
+export abstract class BcsObject {
+  abstract serialize(serializer: BcsSerializer): void;
+
+  bcsSerialize(): Uint8Array {
+    const serializer = new BcsSerializer();
+    this.serialize(serializer);
+    return serializer.output();
+  }
+}
+
+/** Deserialize a value of a generated type, rejecting trailing bytes. */
+export function bcsDeserialize<T>(cls: BcsClass<T>, data: Uint8Array): T {
+  const deserializer = new BcsDeserializer(data);
+  const value = cls.deserialize(deserializer);
+  deserializer.end();
+  return value;
+}
@@ -0,0 +1,320 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{check_container, containers, escape, is_bytes, Builtin, Fields};
+use crate::Result;
+use serde_reflection::{ContainerFormat, Format, Named, Registry, VariantFormat};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+const RUNTIME: &str = include_str!("runtime.ts");
+
+const KEYWORDS: &[&str] = &[
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Generate a TypeScript module defining the containers of `registry` as classes, with
+/// the methods `bcsSerialize` and, through the function `bcsDeserialize`, deserialization.
+///
+/// The module targets ES2020 or later, for `bigint` and `TextEncoder`.
+pub fn generate_typescript(registry: &Registry) -> Result<String> {
+    for (name, container) in containers(registry) {
+        check_container(name, container)?;
+    }
+    let mut output = String::new();
+    write_module(&mut output, registry).expect("writing to a String cannot fail");
+    Ok(output)
+}
+
+fn write_module(out: &mut String, registry: &Registry) -> fmt::Result {
+    writeln!(out, "// Generated by the bcs crate. Do not edit.")?;
+    writeln!(out, "//")?;
+    out.push_str(RUNTIME);
+    for (name, container) in containers(registry) {
+        match container {
+            ContainerFormat::Enum(variants) => write_enum(out, name, variants)?,
+            container => write_struct(out, name, &Fields::of_container(container).unwrap())?,
+        }
+    }
+    Ok(())
+}
+
+fn write_struct(out: &mut String, name: &str, fields: &Fields) -> fmt::Result {
+    writeln!(out, "\nexport class {} extends BcsObject {{", name)?;
+    write_constructor(out, fields)?;
+    writeln!(out, "  serialize(serializer: BcsSerializer): void {{")?;
+    writeln!(out, "    serializer.enter(\"{}\");", name)?;
+    write_serialize_fields(out, fields)?;
+    writeln!(out, "    serializer.leave();")?;
+    writeln!(out, "  }}")?;
+    writeln!(
+        out,
+        "\n  static deserialize(deserializer: BcsDeserializer): {} {{",
+        name
+    )?;
+    writeln!(out, "    deserializer.enter(\"{}\");", name)?;
+    write!(out, "    const value = ")?;
+    write_new(out, name, fields)?;
+    writeln!(out, "    deserializer.leave();")?;
+    writeln!(out, "    return value;")?;
+    writeln!(out, "  }}")?;
+    writeln!(out, "}}")
+}
+
+fn write_enum(
+    out: &mut String,
+    name: &str,
+    variants: &BTreeMap<u32, Named<VariantFormat>>,
+) -> fmt::Result {
+    writeln!(out, "\nexport abstract class {} extends BcsObject {{", name)?;
+    writeln!(out, "  abstract readonly bcsVariantIndex: number;")?;
+    writeln!(
+        out,
+        "\n  abstract serializePayload(serializer: BcsSerializer): void;"
+    )?;
+    writeln!(out, "\n  serialize(serializer: BcsSerializer): void {{")?;
+    writeln!(out, "    serializer.enter(\"{}\");", name)?;
+    writeln!(out, "    serializer.uleb128(this.bcsVariantIndex);")?;
+    writeln!(out, "    this.serializePayload(serializer);")?;
+    writeln!(out, "    serializer.leave();")?;
+    writeln!(out, "  }}")?;
+    writeln!(
+        out,
+        "\n  static deserialize(deserializer: BcsDeserializer): {} {{",
+        name
+    )?;
+    writeln!(out, "    deserializer.enter(\"{}\");", name)?;
+    writeln!(out, "    const index = deserializer.uleb128();")?;
+    writeln!(out, "    let value: {};", name)?;
+    writeln!(out, "    switch (index) {{")?;
+    for (index, variant) in variants {
+        writeln!(out, "      case {}:", index)?;
+        writeln!(
+            out,
+            "        value = {}__{}.deserializePayload(deserializer);",
+            name, variant.name
+        )?;
+        writeln!(out, "        break;")?;
+    }
+    writeln!(out, "      default:")?;
+    writeln!(out, "        throw new BcsError(")?;
+    writeln!(
+        out,
+        "          `invalid value: integer \\`${{index}}\\`, expected variant index 0 <= i < {}`,",
+        variants.len()
+    )?;
+    writeln!(out, "        );")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "    deserializer.leave();")?;
+    writeln!(out, "    return value;")?;
+    writeln!(out, "  }}")?;
+    writeln!(out, "}}")?;
+
+    for (index, variant) in variants {
+        // Variant formats were checked before writing.
+        let fields = Fields::of_variant(variant).unwrap();
+        let class = format!("{}__{}", name, variant.name);
+        writeln!(out, "\nexport class {} extends {} {{", class, name)?;
+        writeln!(out, "  readonly bcsVariantIndex = {};\n", index)?;
+        write_constructor(out, &fields)?;
+        writeln!(
+            out,
+            "  serializePayload(serializer: BcsSerializer): void {{"
+        )?;
+        write_serialize_fields(out, &fields)?;
+        writeln!(out, "  }}")?;
+        writeln!(
+            out,
+            "\n  static deserializePayload(deserializer: BcsDeserializer): {} {{",
+            class
+        )?;
+        write!(out, "    return ")?;
+        write_new(out, &class, &fields)?;
+        writeln!(out, "  }}")?;
+        writeln!(out, "}}")?;
+    }
+    Ok(())
+}
+
+/// Write a constructor declaring the attributes as parameter properties.
+fn write_constructor(out: &mut String, fields: &Fields) -> fmt::Result {
+    let attributes = fields.attributes();
+    if attributes.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "  constructor(")?;
+    for (attribute, format) in attributes {
+        writeln!(
+            out,
+            "    public readonly {}: {},",
+            escape(&attribute, KEYWORDS),
+            quote_type(format)
+        )?;
+    }
+    writeln!(out, "  ) {{")?;
+    writeln!(out, "    super();")?;
+    writeln!(out, "  }}\n")
+}
+
+fn write_serialize_fields(out: &mut String, fields: &Fields) -> fmt::Result {
+    for (attribute, format) in fields.attributes() {
+        writeln!(
+            out,
+            "    {}(serializer, this.{});",
+            quote_function("ser", format),
+            escape(&attribute, KEYWORDS)
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a call to the constructor of the class, deserializing its attributes in order.
+fn write_new(out: &mut String, class: &str, fields: &Fields) -> fmt::Result {
+    let attributes = fields.attributes();
+    if attributes.is_empty() {
+        return writeln!(out, "new {}();", class);
+    }
+    writeln!(out, "new {}(", class)?;
+    for (_, format) in attributes {
+        writeln!(out, "      {}(deserializer),", quote_function("de", format))?;
+    }
+    writeln!(out, "    );")
+}
+
+/// The type of the values of a format.
+fn quote_type(format: &Format) -> String {
+    match format {
+        Format::Unit => "null".to_string(),
+        Format::Bool => "boolean".to_string(),
+        Format::I8 | Format::I16 | Format::I32 | Format::U8 | Format::U16 | Format::U32 => {
+            "number".to_string()
+        }
+        Format::I64 | Format::I128 | Format::U64 | Format::U128 => "bigint".to_string(),
+        Format::Str => "string".to_string(),
+        format if is_bytes(format) => "Uint8Array".to_string(),
+        Format::TypeName(name) => match Builtin::of_name(name) {
+            Some(Builtin::Uleb32) => "number".to_string(),
+            Some(Builtin::Uleb64) => "bigint".to_string(),
+            Some(Builtin::FixedBytes(_)) => "Uint8Array".to_string(),
+            None => name.clone(),
+        },
+        Format::Option(content) => format!("{} | null", quote_type(content)),
+        Format::Seq(content) | Format::TupleArray { content, .. } => match content.as_ref() {
+            Format::Option(_) => format!("({})[]", quote_type(content)),
+            content => format!("{}[]", quote_type(content)),
+        },
+        Format::Map { key, value } => format!("Map<{}, {}>", quote_type(key), quote_type(value)),
+        Format::Tuple(formats) => format!(
+            "[{}]",
+            formats
+                .iter()
+                .map(quote_type)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // Rejected by `check_format`.
+        _ => unreachable!("unsupported format {:?}", format),
+    }
+}
+
+/// The runtime function serializing (`prefix` is `ser`) or deserializing (`prefix` is `de`)
+/// values of a format.
+fn quote_function(prefix: &str, format: &Format) -> String {
+    match format {
+        Format::Unit => format!("{}Unit", prefix),
+        Format::Bool => format!("{}Bool", prefix),
+        Format::I8 => format!("{}I8", prefix),
+        Format::I16 => format!("{}I16", prefix),
+        Format::I32 => format!("{}I32", prefix),
+        Format::I64 => format!("{}I64", prefix),
+        Format::I128 => format!("{}I128", prefix),
+        Format::U8 => format!("{}U8", prefix),
+        Format::U16 => format!("{}U16", prefix),
+        Format::U32 => format!("{}U32", prefix),
+        Format::U64 => format!("{}U64", prefix),
+        Format::U128 => format!("{}U128", prefix),
+        Format::Str => format!("{}Str", prefix),
+        Format::TupleArray { content, size } if **content == Format::U8 => {
+            format!("{}FixedBytes({})", prefix, size)
+        }
+        format if is_bytes(format) => format!("{}Bytes", prefix),
+        Format::TypeName(name) => match Builtin::of_name(name) {
+            Some(Builtin::Uleb32) => format!("{}Uleb32", prefix),
+            Some(Builtin::Uleb64) => format!("{}Uleb64", prefix),
+            Some(Builtin::FixedBytes(size)) => format!("{}FixedBytes({})", prefix, size),
+            None => format!("{}Object({})", prefix, name),
+        },
+        Format::Option(content) => format!("{}Option({})", prefix, quote_function(prefix, content)),
+        Format::Seq(content) => format!("{}Seq({})", prefix, quote_function(prefix, content)),
+        Format::TupleArray { content, size } => format!(
+            "{}Array({}, {})",
+            prefix,
+            quote_function(prefix, content),
+            size
+        ),
+        Format::Map { key, value } => format!(
+            "{}Map({}, {})",
+            prefix,
+            quote_function(prefix, key),
+            quote_function(prefix, value)
+        ),
+        Format::Tuple(formats) => format!(
+            "{}Tuple({})",
+            prefix,
+            formats
+                .iter()
+                .map(|format| quote_function(prefix, format))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // Rejected by `check_format`.
+        _ => unreachable!("unsupported format {:?}", format),
+    }
+}
@@ -0,0 +1,500 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Detection of wire-format changes between two versions of a format registry.
+//!
+//! BCS carries no versioning information: a peer decoding bytes produced by a different
+//! version of a type will either fail or, worse, silently decode a different value. This
+//! module compares two [`Registry`] values (typically traced with `serde-reflection` and
+//! checked into the repository) and reports every change, flagging those that alter the
+//! wire format.
+//!
+//! ```rust
+//! # use serde_reflection::{Tracer, TracerConfig};
+//! mod v1 {
+//!     #[derive(serde::Deserialize)]
+//!     pub enum Message { Ping, Data(Vec<u8>) }
+//! }
+//! mod v2 {
+//!     #[derive(serde::Deserialize)]
+//!     pub enum Message { Ping, Data(Vec<u8>), Close }
+//! }
+//!
+//! let mut tracer = Tracer::new(TracerConfig::default());
+//! tracer.trace_simple_type::<v1::Message>().unwrap();
+//! let old = tracer.registry().unwrap();
+//!
+//! let mut tracer = Tracer::new(TracerConfig::default());
+//! tracer.trace_simple_type::<v2::Message>().unwrap();
+//! let new = tracer.registry().unwrap();
+//!
+//! // Appending a variant is the canonical way to extend an enum.
+//! let report = bcs::compat::check_compatibility(&old, &new);
+//! assert_eq!(report.changes().len(), 1);
+//! assert!(report.is_compatible());
+//! ```
+
+use serde_reflection::{ContainerFormat, Format, Named, Registry, VariantFormat};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// A single difference between two registries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// Location of the change, e.g. `Foo`, `Foo.bar` or `Foo::Variant.0`.
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+/// The nature of a [`Change`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A new container appeared in the registry.
+    ContainerAdded,
+    /// A container disappeared from the registry.
+    ContainerRemoved,
+    /// A container changed between struct, tuple struct, newtype struct, unit struct or enum.
+    ContainerKindChanged {
+        old: &'static str,
+        new: &'static str,
+    },
+    /// A field was added to a struct or struct variant.
+    FieldInserted { name: String },
+    /// A field was removed from a struct or struct variant.
+    FieldRemoved { name: String },
+    /// A field was renamed without changing its position.
+    FieldRenamed { old: String, new: String },
+    /// Fields present in both versions are not in the same order.
+    FieldsReordered,
+    /// The number of elements of a tuple struct or tuple variant changed.
+    ArityChanged { old: usize, new: usize },
+    /// The format of a field, element or payload changed.
+    TypeChanged { old: Format, new: Format },
+    /// A variant was added to an enum.
+    VariantAdded { index: u32, name: String },
+    /// A variant was removed from an enum.
+    VariantRemoved { index: u32, name: String },
+    /// A variant is still present but its index changed.
+    VariantMoved {
+        name: String,
+        old_index: u32,
+        new_index: u32,
+    },
+    /// A variant was renamed without changing its index.
+    VariantRenamed {
+        index: u32,
+        old: String,
+        new: String,
+    },
+    /// The shape of a variant payload changed (e.g. from unit to newtype).
+    VariantKindChanged {
+        old: &'static str,
+        new: &'static str,
+    },
+}
+
+impl ChangeKind {
+    /// Whether values encoded with one version of the registry may fail to decode, or decode
+    /// to a different value, with the other version.
+    ///
+    /// Renamings and new containers do not affect the wire format. A new enum variant is
+    /// safe by itself: existing values keep their encoding.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            ChangeKind::ContainerAdded
+            | ChangeKind::FieldRenamed { .. }
+            | ChangeKind::VariantRenamed { .. } => false,
+            // Inserting a variant before existing ones is reported as `VariantMoved` on
+            // each shifted variant, which is breaking.
+            ChangeKind::VariantAdded { .. } => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeKind::ContainerAdded => write!(f, "container added"),
+            ChangeKind::ContainerRemoved => write!(f, "container removed"),
+            ChangeKind::ContainerKindChanged { old, new } => {
+                write!(f, "container changed from {} to {}", old, new)
+            }
+            ChangeKind::FieldInserted { name } => write!(f, "field `{}` inserted", name),
+            ChangeKind::FieldRemoved { name } => write!(f, "field `{}` removed", name),
+            ChangeKind::FieldRenamed { old, new } => {
+                write!(f, "field `{}` renamed to `{}`", old, new)
+            }
+            ChangeKind::FieldsReordered => write!(f, "fields reordered"),
+            ChangeKind::ArityChanged { old, new } => {
+                write!(f, "number of elements changed from {} to {}", old, new)
+            }
+            ChangeKind::TypeChanged { old, new } => write!(
+                f,
+                "type changed from {} to {}",
+                describe_format(old),
+                describe_format(new)
+            ),
+            ChangeKind::VariantAdded { index, name } => {
+                write!(f, "variant `{}` added with index {}", name, index)
+            }
+            ChangeKind::VariantRemoved { index, name } => {
+                write!(f, "variant `{}` with index {} removed", name, index)
+            }
+            ChangeKind::VariantMoved {
+                name,
+                old_index,
+                new_index,
+            } => write!(
+                f,
+                "variant `{}` moved from index {} to index {}",
+                name, old_index, new_index
+            ),
+            ChangeKind::VariantRenamed { index, old, new } => write!(
+                f,
+                "variant `{}` with index {} renamed to `{}`",
+                old, index, new
+            ),
+            ChangeKind::VariantKindChanged { old, new } => {
+                write!(f, "variant changed from {} to {}", old, new)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = if self.kind.is_breaking() {
+            "breaking"
+        } else {
+            "safe"
+        };
+        write!(f, "[{}] {}: {}", severity, self.path, self.kind)
+    }
+}
+
+/// The list of changes found by [`check_compatibility`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    changes: Vec<Change>,
+}
+
+impl CompatibilityReport {
+    /// All the changes, breaking or not, ordered by container name.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// The changes that alter the wire format.
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(|c| c.kind.is_breaking())
+    }
+
+    /// Whether the two registries share the same wire format (up to safe extensions).
+    pub fn is_compatible(&self) -> bool {
+        self.breaking_changes().next().is_none()
+    }
+}
+
+impl fmt::Display for CompatibilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the containers of two registries and report how the wire format changed
+/// between `old` and `new`.
+///
+/// Containers are matched by name. Fields referring to other containers are compared by name
+/// too, so renaming a container is reported as a type change on every field using it.
+pub fn check_compatibility(old: &Registry, new: &Registry) -> CompatibilityReport {
+    let mut checker = Checker::default();
+    for (name, old_container) in old {
+        match new.get(name) {
+            Some(new_container) => checker.check_container(name, old_container, new_container),
+            None => checker.report(name.clone(), ChangeKind::ContainerRemoved),
+        }
+    }
+    for name in new.keys() {
+        if !old.contains_key(name) {
+            checker.report(name.clone(), ChangeKind::ContainerAdded);
+        }
+    }
+    checker.changes.sort_by(|c1, c2| c1.path.cmp(&c2.path));
+    CompatibilityReport {
+        changes: checker.changes,
+    }
+}
+
+#[derive(Default)]
+struct Checker {
+    changes: Vec<Change>,
+}
+
+impl Checker {
+    fn report(&mut self, path: String, kind: ChangeKind) {
+        self.changes.push(Change { path, kind });
+    }
+
+    fn check_container(&mut self, path: &str, old: &ContainerFormat, new: &ContainerFormat) {
+        use ContainerFormat::*;
+
+        match (old, new) {
+            (UnitStruct, UnitStruct) => (),
+            (NewTypeStruct(old), NewTypeStruct(new)) => self.check_format(path, old, new),
+            (TupleStruct(old), TupleStruct(new)) => self.check_tuple(path, old, new),
+            (Struct(old), Struct(new)) => self.check_struct(path, old, new),
+            (Enum(old), Enum(new)) => self.check_enum(path, old, new),
+            _ => self.report(
+                path.to_string(),
+                ChangeKind::ContainerKindChanged {
+                    old: container_kind(old),
+                    new: container_kind(new),
+                },
+            ),
+        }
+    }
+
+    fn check_format(&mut self, path: &str, old: &Format, new: &Format) {
+        if old != new {
+            self.report(
+                path.to_string(),
+                ChangeKind::TypeChanged {
+                    old: old.clone(),
+                    new: new.clone(),
+                },
+            );
+        }
+    }
+
+    fn check_tuple(&mut self, path: &str, old: &[Format], new: &[Format]) {
+        if old.len() != new.len() {
+            self.report(
+                path.to_string(),
+                ChangeKind::ArityChanged {
+                    old: old.len(),
+                    new: new.len(),
+                },
+            );
+        }
+        for (i, (old, new)) in old.iter().zip(new).enumerate() {
+            self.check_format(&format!("{}.{}", path, i), old, new);
+        }
+    }
+
+    fn check_struct(&mut self, path: &str, old: &[Named<Format>], new: &[Named<Format>]) {
+        // Match fields by name first.
+        let new_positions: BTreeMap<_, _> = new
+            .iter()
+            .enumerate()
+            .map(|(i, field)| (field.name.as_str(), i))
+            .collect();
+        let old_names: BTreeSet<_> = old.iter().map(|field| field.name.as_str()).collect();
+        let mut common_positions = Vec::new();
+        for (position, field) in old.iter().enumerate() {
+            let field_path = format!("{}.{}", path, field.name);
+            if let Some(&i) = new_positions.get(field.name.as_str()) {
+                common_positions.push(i);
+                self.check_format(&field_path, &field.value, &new[i].value);
+                continue;
+            }
+            // A field at the same position whose name is new is taken as renamed.
+            match new.get(position) {
+                Some(renamed) if !old_names.contains(renamed.name.as_str()) => {
+                    self.report(
+                        field_path,
+                        ChangeKind::FieldRenamed {
+                            old: field.name.clone(),
+                            new: renamed.name.clone(),
+                        },
+                    );
+                    self.check_format(
+                        &format!("{}.{}", path, renamed.name),
+                        &field.value,
+                        &renamed.value,
+                    );
+                }
+                _ => self.report(
+                    field_path,
+                    ChangeKind::FieldRemoved {
+                        name: field.name.clone(),
+                    },
+                ),
+            }
+        }
+        if common_positions.windows(2).any(|w| w[0] > w[1]) {
+            self.report(path.to_string(), ChangeKind::FieldsReordered);
+        }
+        for (position, field) in new.iter().enumerate() {
+            let renamed = old
+                .get(position)
+                .is_some_and(|f| !new_positions.contains_key(f.name.as_str()));
+            if !old_names.contains(field.name.as_str()) && !renamed {
+                self.report(
+                    format!("{}.{}", path, field.name),
+                    ChangeKind::FieldInserted {
+                        name: field.name.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    fn check_enum(
+        &mut self,
+        path: &str,
+        old: &BTreeMap<u32, Named<VariantFormat>>,
+        new: &BTreeMap<u32, Named<VariantFormat>>,
+    ) {
+        let new_indices: BTreeMap<_, _> = new
+            .iter()
+            .map(|(index, variant)| (variant.name.as_str(), *index))
+            .collect();
+        let old_indices: BTreeMap<_, _> = old
+            .iter()
+            .map(|(index, variant)| (variant.name.as_str(), *index))
+            .collect();
+
+        for (&old_index, old_variant) in old {
+            let variant_path = format!("{}::{}", path, old_variant.name);
+            if let Some(&new_index) = new_indices.get(old_variant.name.as_str()) {
+                if new_index != old_index {
+                    self.report(
+                        variant_path.clone(),
+                        ChangeKind::VariantMoved {
+                            name: old_variant.name.clone(),
+                            old_index,
+                            new_index,
+                        },
+                    );
+                }
+                self.check_variant(&variant_path, &old_variant.value, &new[&new_index].value);
+                continue;
+            }
+            match new.get(&old_index) {
+                // The index is now used by a variant that did not exist before.
+                Some(new_variant) if !old_indices.contains_key(new_variant.name.as_str()) => {
+                    self.report(
+                        variant_path,
+                        ChangeKind::VariantRenamed {
+                            index: old_index,
+                            old: old_variant.name.clone(),
+                            new: new_variant.name.clone(),
+                        },
+                    );
+                    self.check_variant(
+                        &format!("{}::{}", path, new_variant.name),
+                        &old_variant.value,
+                        &new_variant.value,
+                    );
+                }
+                _ => self.report(
+                    variant_path,
+                    ChangeKind::VariantRemoved {
+                        index: old_index,
+                        name: old_variant.name.clone(),
+                    },
+                ),
+            }
+        }
+
+        for (&new_index, new_variant) in new {
+            let is_rename = old
+                .get(&new_index)
+                .is_some_and(|v| !new_indices.contains_key(v.name.as_str()));
+            if !old_indices.contains_key(new_variant.name.as_str()) && !is_rename {
+                self.report(
+                    format!("{}::{}", path, new_variant.name),
+                    ChangeKind::VariantAdded {
+                        index: new_index,
+                        name: new_variant.name.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    fn check_variant(&mut self, path: &str, old: &VariantFormat, new: &VariantFormat) {
+        use VariantFormat::*;
+
+        match (old, new) {
+            (Unit, Unit) => (),
+            (NewType(old), NewType(new)) => self.check_format(path, old, new),
+            (Tuple(old), Tuple(new)) => self.check_tuple(path, old, new),
+            (Struct(old), Struct(new)) => self.check_struct(path, old, new),
+            _ => self.report(
+                path.to_string(),
+                ChangeKind::VariantKindChanged {
+                    old: variant_kind(old),
+                    new: variant_kind(new),
+                },
+            ),
+        }
+    }
+}
+
+fn container_kind(format: &ContainerFormat) -> &'static str {
+    match format {
+        ContainerFormat::UnitStruct => "unit struct",
+        ContainerFormat::NewTypeStruct(_) => "newtype struct",
+        ContainerFormat::TupleStruct(_) => "tuple struct",
+        ContainerFormat::Struct(_) => "struct",
+        ContainerFormat::Enum(_) => "enum",
+    }
+}
+
+fn variant_kind(format: &VariantFormat) -> &'static str {
+    match format {
+        VariantFormat::Variable(_) => "unknown variant",
+        VariantFormat::Unit => "unit variant",
+        VariantFormat::NewType(_) => "newtype variant",
+        VariantFormat::Tuple(_) => "tuple variant",
+        VariantFormat::Struct(_) => "struct variant",
+    }
+}
+
+/// Render a format with a Rust-like syntax, e.g. `Option<Vec<u8>>`.
+fn describe_format(format: &Format) -> String {
+    match format {
+        Format::Variable(_) => "_".to_string(),
+        Format::TypeName(name) => name.clone(),
+        Format::Unit => "()".to_string(),
+        Format::Bool => "bool".to_string(),
+        Format::I8 => "i8".to_string(),
+        Format::I16 => "i16".to_string(),
+        Format::I32 => "i32".to_string(),
+        Format::I64 => "i64".to_string(),
+        Format::I128 => "i128".to_string(),
+        Format::U8 => "u8".to_string(),
+        Format::U16 => "u16".to_string(),
+        Format::U32 => "u32".to_string(),
+        Format::U64 => "u64".to_string(),
+        Format::U128 => "u128".to_string(),
+        Format::F32 => "f32".to_string(),
+        Format::F64 => "f64".to_string(),
+        Format::Char => "char".to_string(),
+        Format::Str => "String".to_string(),
+        Format::Bytes => "Bytes".to_string(),
+        Format::Option(format) => format!("Option<{}>", describe_format(format)),
+        Format::Seq(format) => format!("Vec<{}>", describe_format(format)),
+        Format::Map { key, value } => {
+            format!("Map<{}, {}>", describe_format(key), describe_format(value))
+        }
+        Format::Tuple(formats) => format!(
+            "({})",
+            formats
+                .iter()
+                .map(describe_format)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Format::TupleArray { content, size } => {
+            format!("[{}; {}]", describe_format(content), size)
+        }
+    }
+}
@@ -1,9 +1,11 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, Result},
+    fixed_bytes, uleb128,
+};
 use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, Visitor};
-use std::convert::TryFrom;
 
 /// Deserializes a `&[u8]` into a type.
 ///
@@ -54,25 +56,38 @@ where
 }
 
 /// Deserialization implementation for BCS
-struct Deserializer<'de> {
-    input: &'de [u8],
+pub(crate) struct Deserializer<'de> {
+    pub(crate) input: &'de [u8],
     max_remaining_depth: usize,
+    /// Number of elements that collections may still preallocate, see `preallocation`.
+    preallocation_budget: usize,
 }
 
 impl<'de> Deserializer<'de> {
     /// Creates a new `Deserializer` which will be deserializing the provided
     /// input.
-    fn new(input: &'de [u8], max_remaining_depth: usize) -> Self {
+    pub(crate) fn new(input: &'de [u8], max_remaining_depth: usize) -> Self {
         Deserializer {
             input,
             max_remaining_depth,
+            preallocation_budget: input.len(),
         }
     }
 
+    /// Returns the number of elements that a collection announcing `len` elements may
+    /// preallocate. Announced lengths are not trusted: across the whole input, collections
+    /// may preallocate at most one element per input byte, so that memory use is bounded by
+    /// the size of the input even when collections are nested.
+    pub(crate) fn preallocation(&mut self, len: usize) -> usize {
+        let preallocation = len.min(self.preallocation_budget);
+        self.preallocation_budget -= preallocation;
+        preallocation
+    }
+
     /// The `Deserializer::end` method should be called after a type has been
     /// fully deserialized. This allows the `Deserializer` to validate that
     /// the there are no more bytes remaining in the input stream.
-    fn end(&mut self) -> Result<()> {
+    pub(crate) fn end(&mut self) -> Result<()> {
         if self.input.is_empty() {
             Ok(())
         } else {
@@ -86,13 +101,13 @@ impl<'de> Deserializer<'de> {
         self.input.first().copied().ok_or(Error::Eof)
     }
 
-    fn next(&mut self) -> Result<u8> {
+    pub(crate) fn next(&mut self) -> Result<u8> {
         let byte = self.peek()?;
         self.input = &self.input[1..];
         Ok(byte)
     }
 
-    fn parse_bool(&mut self) -> Result<bool> {
+    pub(crate) fn parse_bool(&mut self) -> Result<bool> {
         let byte = self.next()?;
 
         match byte {
@@ -102,14 +117,14 @@ impl<'de> Deserializer<'de> {
         }
     }
 
-    fn fill_slice(&mut self, slice: &mut [u8]) -> Result<()> {
+    pub(crate) fn fill_slice(&mut self, slice: &mut [u8]) -> Result<()> {
         for byte in slice {
             *byte = self.next()?;
         }
         Ok(())
     }
 
-    fn parse_u8(&mut self) -> Result<u8> {
+    pub(crate) fn parse_u8(&mut self) -> Result<u8> {
         self.next()
     }
 
@@ -137,12 +152,25 @@ impl<'de> Deserializer<'de> {
         Ok(u128::from_le_bytes(le_bytes))
     }
 
-    #[allow(clippy::integer_arithmetic)]
-    fn parse_u32_from_uleb128(&mut self) -> Result<u32> {
+    pub(crate) fn parse_u32_from_uleb128(&mut self) -> Result<u32> {
+        self.parse_uleb128(32).map(|value| value as u32)
+    }
+
+    pub(crate) fn parse_u64_from_uleb128(&mut self) -> Result<u64> {
+        self.parse_uleb128(64)
+    }
+
+    /// Parse a ULEB128-encoded integer of at most `bits` bits (32 or 64).
+    #[allow(clippy::arithmetic_side_effects)]
+    fn parse_uleb128(&mut self, bits: u32) -> Result<u64> {
         let mut value: u64 = 0;
-        for shift in (0..32).step_by(7) {
+        for shift in (0..bits).step_by(7) {
             let byte = self.next()?;
             let digit = byte & 0x7f;
+            // Decoded integer must not overflow: bits shifted out of a `u64` must be zero.
+            if shift + 7 > 64 && u64::from(digit) >> (64 - shift) != 0 {
+                return Err(Error::IntegerOverflowDuringUleb128Decoding);
+            }
             value |= u64::from(digit) << shift;
             // If the highest bit of `byte` is 0, return the final value.
             if digit == byte {
@@ -152,15 +180,17 @@ impl<'de> Deserializer<'de> {
                     return Err(Error::NonCanonicalUleb128Encoding);
                 }
                 // Decoded integer must not overflow.
-                return u32::try_from(value)
-                    .map_err(|_| Error::IntegerOverflowDuringUleb128Decoding);
+                if bits < 64 && value >> bits != 0 {
+                    return Err(Error::IntegerOverflowDuringUleb128Decoding);
+                }
+                return Ok(value);
             }
         }
         // Decoded integer must not overflow.
         Err(Error::IntegerOverflowDuringUleb128Decoding)
     }
 
-    fn parse_length(&mut self) -> Result<usize> {
+    pub(crate) fn parse_length(&mut self) -> Result<usize> {
         let len = self.parse_u32_from_uleb128()? as usize;
         if len > crate::MAX_SEQUENCE_LENGTH {
             return Err(Error::ExceededMaxLen(len));
@@ -168,19 +198,23 @@ impl<'de> Deserializer<'de> {
         Ok(len)
     }
 
-    fn parse_bytes(&mut self) -> Result<&'de [u8]> {
+    pub(crate) fn parse_bytes(&mut self) -> Result<&'de [u8]> {
         let len = self.parse_length()?;
+        self.parse_fixed_bytes(len)
+    }
+
+    pub(crate) fn parse_fixed_bytes(&mut self, len: usize) -> Result<&'de [u8]> {
         let slice = self.input.get(..len).ok_or(Error::Eof)?;
         self.input = &self.input[len..];
         Ok(slice)
     }
 
-    fn parse_string(&mut self) -> Result<&'de str> {
+    pub(crate) fn parse_string(&mut self) -> Result<&'de str> {
         let slice = self.parse_bytes()?;
         std::str::from_utf8(slice).map_err(|_| Error::Utf8)
     }
 
-    fn enter_named_container(&mut self, name: &'static str) -> Result<()> {
+    pub(crate) fn enter_named_container(&mut self, name: &'static str) -> Result<()> {
         if self.max_remaining_depth == 0 {
             return Err(Error::ExceededContainerDepthLimit(name));
         }
@@ -188,12 +222,12 @@ impl<'de> Deserializer<'de> {
         Ok(())
     }
 
-    fn leave_named_container(&mut self) {
+    pub(crate) fn leave_named_container(&mut self) {
         self.max_remaining_depth += 1;
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     // BCS is not a self-describing format so we can't implement `deserialize_any`
@@ -365,7 +399,20 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         self.enter_named_container(name)?;
-        let r = visitor.visit_newtype_struct(&mut *self);
+        let r = match name {
+            uleb128::ULEB32_NAME => self
+                .parse_u32_from_uleb128()
+                .and_then(|value| visitor.visit_u32(value)),
+            uleb128::ULEB64_NAME => self
+                .parse_u64_from_uleb128()
+                .and_then(|value| visitor.visit_u64(value)),
+            name => match fixed_bytes::len(name) {
+                Some(len) => self
+                    .parse_fixed_bytes(len)
+                    .and_then(|bytes| visitor.visit_borrowed_bytes(bytes)),
+                None => visitor.visit_newtype_struct(&mut *self),
+            },
+        };
         self.leave_named_container();
         r
     }
@@ -375,7 +422,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         let len = self.parse_length()?;
-        visitor.visit_seq(SeqDeserializer::new(&mut self, len))
+        visitor.visit_seq(SeqDeserializer::with_untrusted_len(&mut self, len))
     }
     #[allow(clippy::needless_borrow)]
     fn deserialize_tuple<V>(mut self, len: usize, visitor: V) -> Result<V::Value>
@@ -463,11 +510,27 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 struct SeqDeserializer<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
     remaining: usize,
+    preallocation: usize,
 }
 #[allow(clippy::needless_borrow)]
 impl<'a, 'de> SeqDeserializer<'a, 'de> {
+    /// For tuples and structs, whose length is given by the type.
     fn new(de: &'a mut Deserializer<'de>, remaining: usize) -> Self {
-        Self { de, remaining }
+        Self {
+            de,
+            remaining,
+            preallocation: remaining,
+        }
+    }
+
+    /// For sequences, whose length is read from the input.
+    fn with_untrusted_len(de: &'a mut Deserializer<'de>, remaining: usize) -> Self {
+        let preallocation = de.preallocation(remaining);
+        Self {
+            de,
+            remaining,
+            preallocation,
+        }
     }
 }
 
@@ -487,21 +550,24 @@ impl<'de, 'a> de::SeqAccess<'de> for SeqDeserializer<'a, 'de> {
     }
 
     fn size_hint(&self) -> Option<usize> {
-        Some(self.remaining)
+        Some(self.remaining.min(self.preallocation))
     }
 }
 
 struct MapDeserializer<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
     remaining: usize,
+    preallocation: usize,
     previous_key_bytes: Option<&'a [u8]>,
 }
 
 impl<'a, 'de> MapDeserializer<'a, 'de> {
     fn new(de: &'a mut Deserializer<'de>, remaining: usize) -> Self {
+        let preallocation = de.preallocation(remaining);
         Self {
             de,
             remaining,
+            preallocation,
             previous_key_bytes: None,
         }
     }
@@ -543,11 +609,11 @@ impl<'de, 'a> de::MapAccess<'de> for MapDeserializer<'a, 'de> {
     }
 
     fn size_hint(&self) -> Option<usize> {
-        Some(self.remaining)
+        Some(self.remaining.min(self.preallocation))
     }
 }
 
-impl<'de, 'a> de::EnumAccess<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
     type Error = Error;
     type Variant = Self;
 
@@ -561,7 +627,7 @@ impl<'de, 'a> de::EnumAccess<'de> for &'a mut Deserializer<'de> {
     }
 }
 
-impl<'de, 'a> de::VariantAccess<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
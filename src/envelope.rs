@@ -0,0 +1,221 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Self-describing BCS values, tagged with their type and the version of their format.
+//!
+//! BCS deliberately carries no type or versioning information. When bytes outlive the code
+//! that wrote them, e.g. in storage, an [`Envelope`] records which type and which version
+//! of its format the payload was encoded with, so that readers can reject unexpected data
+//! or decode older versions through an [`EnvelopeRegistry`].
+//!
+//! ```rust
+//! use bcs::envelope::{from_enveloped_bytes, to_enveloped_bytes, Enveloped, EnvelopeRegistry};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize)]
+//! struct AccountV1 {
+//!     balance: u64,
+//! }
+//!
+//! impl Enveloped for AccountV1 {
+//!     const TYPE_TAG: &'static str = "Account";
+//!     const VERSION: u32 = 1;
+//! }
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Account {
+//!     balance: u64,
+//!     frozen: bool,
+//! }
+//!
+//! impl Enveloped for Account {
+//!     const TYPE_TAG: &'static str = "Account";
+//!     const VERSION: u32 = 2;
+//! }
+//!
+//! let account = Account { balance: 7, frozen: false };
+//! let bytes = to_enveloped_bytes(&account).unwrap();
+//! assert_eq!(from_enveloped_bytes::<Account>(&bytes).unwrap(), account);
+//! assert!(from_enveloped_bytes::<AccountV1>(&bytes).is_err());
+//!
+//! // Upgrade older versions while reading.
+//! let mut registry = EnvelopeRegistry::new();
+//! registry
+//!     .register(|account: AccountV1| Account { balance: account.balance, frozen: false })
+//!     .register(|account: Account| account);
+//! assert_eq!(registry.decode(&bytes).unwrap(), account);
+//! ```
+
+use crate::{Error, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// A type whose values can be stored in an [`Envelope`].
+pub trait Enveloped {
+    /// Identifies the type. The tag should stay the same across versions of the type.
+    const TYPE_TAG: &'static str;
+    /// The version of the BCS format of the type, to be changed whenever the format changes.
+    const VERSION: u32;
+}
+
+/// The BCS encoding of a value together with its type tag and version.
+///
+/// Envelopes are themselves encoded in BCS, as the tag, the version, then the length of
+/// the payload followed by the payload.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Envelope {
+    pub type_tag: String,
+    pub version: u32,
+    pub payload: Vec<u8>,
+}
+
+/// An envelope borrowing its fields from the input, with the same encoding as `Envelope`.
+#[derive(Deserialize)]
+struct BorrowedEnvelope<'a> {
+    type_tag: &'a str,
+    version: u32,
+    payload: &'a [u8],
+}
+
+impl Envelope {
+    /// Encode `value` in an envelope.
+    pub fn seal<T>(value: &T) -> Result<Self>
+    where
+        T: ?Sized + Enveloped + Serialize,
+    {
+        Ok(Self {
+            type_tag: T::TYPE_TAG.to_string(),
+            version: T::VERSION,
+            payload: crate::to_bytes(value)?,
+        })
+    }
+
+    /// Decode the payload, after checking that it holds the expected type and version.
+    pub fn open<'a, T>(&'a self) -> Result<T>
+    where
+        T: Enveloped + Deserialize<'a>,
+    {
+        check::<T>(&self.type_tag, self.version)?;
+        crate::from_bytes(&self.payload)
+    }
+}
+
+/// Serialize `value` in an [`Envelope`].
+pub fn to_enveloped_bytes<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Enveloped + Serialize,
+{
+    crate::to_bytes(&Envelope::seal(value)?)
+}
+
+/// Deserialize a value of type `T` from an [`Envelope`].
+///
+/// Fails with `Error::UnexpectedTypeTag` or `Error::UnsupportedVersion` if the envelope
+/// holds another type or another version of `T`.
+pub fn from_enveloped_bytes<'a, T>(bytes: &'a [u8]) -> Result<T>
+where
+    T: Enveloped + Deserialize<'a>,
+{
+    let envelope: BorrowedEnvelope = crate::from_bytes(bytes)?;
+    check::<T>(envelope.type_tag, envelope.version)?;
+    crate::from_bytes(envelope.payload)
+}
+
+fn check<T: Enveloped>(type_tag: &str, version: u32) -> Result<()> {
+    if type_tag != T::TYPE_TAG {
+        return Err(Error::UnexpectedTypeTag(type_tag.to_string()));
+    }
+    if version != T::VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    Ok(())
+}
+
+type Decoder<R> = Box<dyn Fn(&[u8]) -> Result<R> + Send + Sync>;
+
+/// Decodes envelopes holding any of the registered types and versions into values of type
+/// `R`, e.g. an enum of the types stored together, or the latest version of a type.
+pub struct EnvelopeRegistry<R> {
+    decoders: BTreeMap<&'static str, BTreeMap<u32, Decoder<R>>>,
+}
+
+impl<R> EnvelopeRegistry<R> {
+    pub fn new() -> Self {
+        Self {
+            decoders: BTreeMap::new(),
+        }
+    }
+
+    /// Decode envelopes holding `T` and convert their value with `convert`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type tag and version of `T` are already registered.
+    pub fn register<T, F>(&mut self, convert: F) -> &mut Self
+    where
+        T: Enveloped + DeserializeOwned,
+        F: Fn(T) -> R + Send + Sync + 'static,
+    {
+        let decoder: Decoder<R> = Box::new(move |payload| crate::from_bytes(payload).map(&convert));
+        let previous = self
+            .decoders
+            .entry(T::TYPE_TAG)
+            .or_default()
+            .insert(T::VERSION, decoder);
+        assert!(
+            previous.is_none(),
+            "version {} of {} is already registered",
+            T::VERSION,
+            T::TYPE_TAG
+        );
+        self
+    }
+
+    /// Whether envelopes with this type tag and version can be decoded.
+    pub fn contains(&self, type_tag: &str, version: u32) -> bool {
+        self.decoders
+            .get(type_tag)
+            .is_some_and(|versions| versions.contains_key(&version))
+    }
+
+    /// Deserialize an [`Envelope`] and decode its payload with the decoder registered for
+    /// its type tag and version.
+    pub fn decode(&self, bytes: &[u8]) -> Result<R> {
+        let envelope: BorrowedEnvelope = crate::from_bytes(bytes)?;
+        self.decode_payload(envelope.type_tag, envelope.version, envelope.payload)
+    }
+
+    /// Decode the payload of `envelope` with the decoder registered for its type tag and
+    /// version.
+    pub fn decode_envelope(&self, envelope: &Envelope) -> Result<R> {
+        self.decode_payload(&envelope.type_tag, envelope.version, &envelope.payload)
+    }
+
+    fn decode_payload(&self, type_tag: &str, version: u32, payload: &[u8]) -> Result<R> {
+        let versions = self
+            .decoders
+            .get(type_tag)
+            .ok_or_else(|| Error::UnexpectedTypeTag(type_tag.to_string()))?;
+        let decoder = versions
+            .get(&version)
+            .ok_or(Error::UnsupportedVersion(version))?;
+        decoder(payload)
+    }
+}
+
+impl<R> Default for EnvelopeRegistry<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> fmt::Debug for EnvelopeRegistry<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.decoders.iter().flat_map(|(type_tag, versions)| {
+            versions.keys().map(move |version| (type_tag, version))
+        });
+        f.debug_struct("EnvelopeRegistry")
+            .field("types", &entries.collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use serde::{de, ser};
-use std::fmt;
+use std::{fmt, io, sync::Arc};
 use thiserror::Error;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -12,7 +12,7 @@ pub enum Error {
     #[error("unexpected end of input")]
     Eof,
     #[error("I/O error: {0}")]
-    Io(String),
+    Io(#[source] IoError),
     #[error("exceeded max sequence length: {0}")]
     ExceededMaxLen(usize),
     #[error("exceeded max container depth while entering: {0}")]
@@ -41,11 +41,199 @@ pub enum Error {
     NonCanonicalUleb128Encoding,
     #[error("ULEB128-encoded integer did not fit in the target size")]
     IntegerOverflowDuringUleb128Decoding,
+    #[error("output buffer too small")]
+    BufferTooSmall,
+    #[error("unexpected type tag: {0}")]
+    UnexpectedTypeTag(String),
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(u32),
 }
 
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
-        Error::Io(err.to_string())
+impl Error {
+    /// The stable code identifying the variant of this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Eof => ErrorCode::Eof,
+            Error::Io(_) => ErrorCode::Io,
+            Error::ExceededMaxLen(_) => ErrorCode::ExceededMaxLen,
+            Error::ExceededContainerDepthLimit(_) => ErrorCode::ExceededContainerDepthLimit,
+            Error::ExpectedBoolean => ErrorCode::ExpectedBoolean,
+            Error::ExpectedMapKey => ErrorCode::ExpectedMapKey,
+            Error::ExpectedMapValue => ErrorCode::ExpectedMapValue,
+            Error::NonCanonicalMap => ErrorCode::NonCanonicalMap,
+            Error::ExpectedOption => ErrorCode::ExpectedOption,
+            Error::Custom(_) => ErrorCode::Custom,
+            Error::MissingLen => ErrorCode::MissingLen,
+            Error::NotSupported(_) => ErrorCode::NotSupported,
+            Error::RemainingInput => ErrorCode::RemainingInput,
+            Error::Utf8 => ErrorCode::Utf8,
+            Error::NonCanonicalUleb128Encoding => ErrorCode::NonCanonicalUleb128Encoding,
+            Error::IntegerOverflowDuringUleb128Decoding => {
+                ErrorCode::IntegerOverflowDuringUleb128Decoding
+            }
+            Error::BufferTooSmall => ErrorCode::BufferTooSmall,
+            Error::UnexpectedTypeTag(_) => ErrorCode::UnexpectedTypeTag,
+            Error::UnsupportedVersion(_) => ErrorCode::UnsupportedVersion,
+        }
+    }
+
+    /// Whether the input is not the BCS encoding of a value of the expected type.
+    ///
+    /// This includes `Error::Custom`, which `Deserialize` implementations return when the
+    /// input violates the invariants of the type (e.g. an unknown variant index).
+    pub fn is_malformed_input(&self) -> bool {
+        matches!(
+            self.code(),
+            ErrorCode::Eof
+                | ErrorCode::ExpectedBoolean
+                | ErrorCode::NonCanonicalMap
+                | ErrorCode::ExpectedOption
+                | ErrorCode::Custom
+                | ErrorCode::RemainingInput
+                | ErrorCode::Utf8
+                | ErrorCode::NonCanonicalUleb128Encoding
+                | ErrorCode::IntegerOverflowDuringUleb128Decoding
+                | ErrorCode::UnexpectedTypeTag
+                | ErrorCode::UnsupportedVersion
+        )
+    }
+
+    /// Whether a sequence is longer than `MAX_SEQUENCE_LENGTH`, containers are nested
+    /// deeper than `MAX_CONTAINER_DEPTH`, or the serialized bytes do not fit in the buffer
+    /// given to `serialize_into_slice`.
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(
+            self.code(),
+            ErrorCode::ExceededMaxLen
+                | ErrorCode::ExceededContainerDepthLimit
+                | ErrorCode::BufferTooSmall
+        )
+    }
+
+    /// Whether the type being (de)serialized cannot be represented in BCS (e.g. floats), or
+    /// its implementation of `Serialize` does not follow the expectations of BCS.
+    pub fn is_unsupported_type(&self) -> bool {
+        matches!(
+            self.code(),
+            ErrorCode::NotSupported
+                | ErrorCode::MissingLen
+                | ErrorCode::ExpectedMapKey
+                | ErrorCode::ExpectedMapValue
+        )
+    }
+
+    /// Whether writing the serialized bytes to an `std::io::Write` object failed.
+    pub fn is_io(&self) -> bool {
+        matches!(self.code(), ErrorCode::Io)
+    }
+
+    /// The kind of the underlying I/O error, if this is an `Error::Io`.
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            Error::Io(err) => Some(err.kind()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err.into())
+    }
+}
+
+/// Identifies the variant of an `Error`, e.g. for metrics or logs.
+///
+/// The numeric value of each code is stable: it will not change in future versions and
+/// codes are never reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+#[repr(u16)]
+pub enum ErrorCode {
+    Eof = 1,
+    Io = 2,
+    ExceededMaxLen = 3,
+    ExceededContainerDepthLimit = 4,
+    ExpectedBoolean = 5,
+    ExpectedMapKey = 6,
+    ExpectedMapValue = 7,
+    NonCanonicalMap = 8,
+    ExpectedOption = 9,
+    Custom = 10,
+    MissingLen = 11,
+    NotSupported = 12,
+    RemainingInput = 13,
+    Utf8 = 14,
+    NonCanonicalUleb128Encoding = 15,
+    IntegerOverflowDuringUleb128Decoding = 16,
+    BufferTooSmall = 17,
+    UnexpectedTypeTag = 18,
+    UnsupportedVersion = 19,
+}
+
+impl ErrorCode {
+    /// The stable numeric value of the code.
+    pub fn as_u16(self) -> u16 {
+        self as u16
+    }
+}
+
+/// An I/O error raised while writing serialized bytes.
+///
+/// Unlike `std::io::Error`, this type implements `Clone` and `PartialEq`. Two values are
+/// equal if they have the same kind and message.
+#[derive(Clone, Debug)]
+pub struct IoError {
+    kind: io::ErrorKind,
+    message: String,
+    error: Option<Arc<io::Error>>,
+}
+
+impl IoError {
+    pub fn new(kind: io::ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            error: None,
+        }
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        self.kind
+    }
+
+    /// The original error, if this value was converted from a `std::io::Error`.
+    pub fn get_ref(&self) -> Option<&io::Error> {
+        self.error.as_deref()
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(err: io::Error) -> Self {
+        Self {
+            kind: err.kind(),
+            message: err.to_string(),
+            error: Some(Arc::new(err)),
+        }
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.message == other.message
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for IoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // `self` stands for the original error, so its source comes next in the chain.
+        self.error.as_ref()?.source()
     }
 }
 
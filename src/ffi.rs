@@ -0,0 +1,255 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! C ABI for validating and transcoding BCS bytes against a format registry.
+//!
+//! The functions of this module are declared in `include/bcs.h`, which is generated from
+//! this file with cbindgen (`BCS_UPDATE_GOLDEN=1 cargo test --features ffi --test ffi`).
+//! To link them into a C or C++ program, build a static or dynamic library with e.g.
+//! `cargo rustc --release --features ffi --crate-type staticlib`.
+//!
+//! A schema handle is created with `bcs_schema_new` from a registry serialized in JSON, as
+//! produced by `serde_json::to_vec(&registry)`, and the name of one of its containers. It
+//! is immutable and can be shared between threads. Values are converted to and from JSON
+//! with the conventions of [`crate::json`].
+//!
+//! Every fallible function returns `BCS_OK` (zero) on success, or the stable numeric value
+//! of the [`crate::ErrorCode`] of the error. Invalid arguments, such as null pointers or a
+//! registry that cannot be parsed, are reported as `ErrorCode::Custom`.
+//!
+//! Ownership of memory follows these rules:
+//! * Input pointers are borrowed for the duration of the call only. A null pointer is
+//!   accepted for an empty input.
+//! * Output buffers are written on return, whatever the outcome: the result on success, or
+//!   a UTF-8 error message (not terminated by a NUL byte) on failure. Buffers that are not
+//!   filled are set to `{NULL, 0}`. Their previous content is overwritten, not released. An
+//!   error buffer may be null if the message is not needed.
+//! * Every buffer written by this library must be released exactly once with
+//!   `bcs_buffer_free`, which accepts empty buffers. Buffers must not be freed with `free`.
+//! * Schema handles must be released exactly once with `bcs_schema_free`.
+
+use crate::{
+    json::{bcs_to_json, json_to_bcs},
+    Error, Result,
+};
+use serde_reflection::{Format, Registry};
+use std::{ffi::CStr, os::raw::c_char, ptr};
+
+/// Status returned on success.
+pub const BCS_OK: u16 = 0;
+
+/// A format registry together with the container to validate and transcode.
+pub struct BcsSchema {
+    format: Format,
+    registry: Registry,
+}
+
+/// A byte buffer allocated by this library.
+#[repr(C)]
+pub struct BcsBuffer {
+    /// The bytes of the buffer, or null if the buffer is empty.
+    pub data: *mut u8,
+    /// The number of bytes of the buffer.
+    pub len: usize,
+}
+
+impl BcsBuffer {
+    fn new(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            return Self::empty();
+        }
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        BcsBuffer { data, len }
+    }
+
+    fn empty() -> Self {
+        BcsBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+}
+
+/// Create a schema handle from a registry serialized in JSON and the name of one of its
+/// containers.
+///
+/// On success, `*schema` is set to a handle to release with `bcs_schema_free`. Otherwise,
+/// it is set to null.
+///
+/// # Safety
+///
+/// `registry` must point to `registry_len` readable bytes, `type_name` to a NUL-terminated
+/// string, `schema` to a writable pointer, and `error` must be null or point to a writable
+/// `BcsBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn bcs_schema_new(
+    registry: *const u8,
+    registry_len: usize,
+    type_name: *const c_char,
+    schema: *mut *mut BcsSchema,
+    error: *mut BcsBuffer,
+) -> u16 {
+    if schema.is_null() {
+        return finish(Err(null_argument("schema")), ptr::null_mut(), error);
+    }
+    *schema = ptr::null_mut();
+    let result = new_schema(input(registry, registry_len, "registry"), type_name).map(|handle| {
+        *schema = Box::into_raw(Box::new(handle));
+        Vec::new()
+    });
+    finish(result, ptr::null_mut(), error)
+}
+
+unsafe fn new_schema(registry: Result<&[u8]>, type_name: *const c_char) -> Result<BcsSchema> {
+    let registry: Registry = serde_json::from_slice(registry?)
+        .map_err(|e| Error::Custom(format!("invalid registry: {}", e)))?;
+    if type_name.is_null() {
+        return Err(null_argument("type_name"));
+    }
+    let name = CStr::from_ptr(type_name)
+        .to_str()
+        .map_err(|_| Error::Utf8)?;
+    if !registry.contains_key(name) {
+        return Err(Error::Custom(format!("unknown container {}", name)));
+    }
+    Ok(BcsSchema {
+        format: Format::TypeName(name.to_string()),
+        registry,
+    })
+}
+
+/// Release a schema handle. Does nothing if `schema` is null.
+///
+/// # Safety
+///
+/// `schema` must be null or a handle returned by `bcs_schema_new` that was not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn bcs_schema_free(schema: *mut BcsSchema) {
+    if !schema.is_null() {
+        drop(Box::from_raw(schema));
+    }
+}
+
+/// Check that `bytes` is the canonical BCS encoding of a value of the schema.
+///
+/// # Safety
+///
+/// `schema` must be a live handle, `bytes` must point to `len` readable bytes, and `error`
+/// must be null or point to a writable `BcsBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn bcs_validate(
+    schema: *const BcsSchema,
+    bytes: *const u8,
+    len: usize,
+    error: *mut BcsBuffer,
+) -> u16 {
+    let result = decode(schema, input(bytes, len, "bytes")).map(|_| Vec::new());
+    finish(result, ptr::null_mut(), error)
+}
+
+/// Convert the BCS encoding of a value of the schema to JSON, written to `json` as UTF-8.
+///
+/// # Safety
+///
+/// `schema` must be a live handle, `bytes` must point to `len` readable bytes, `json` must
+/// point to a writable `BcsBuffer`, and `error` must be null or point to a writable
+/// `BcsBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn bcs_decode_to_json(
+    schema: *const BcsSchema,
+    bytes: *const u8,
+    len: usize,
+    json: *mut BcsBuffer,
+    error: *mut BcsBuffer,
+) -> u16 {
+    if json.is_null() {
+        return finish(Err(null_argument("json")), json, error);
+    }
+    let result = decode(schema, input(bytes, len, "bytes"))
+        .and_then(|value| serde_json::to_vec(&value).map_err(|e| Error::Custom(e.to_string())));
+    finish(result, json, error)
+}
+
+/// Convert a JSON value, given in UTF-8, to the BCS encoding of a value of the schema,
+/// written to `bytes`.
+///
+/// # Safety
+///
+/// `schema` must be a live handle, `json` must point to `len` readable bytes, `bytes` must
+/// point to a writable `BcsBuffer`, and `error` must be null or point to a writable
+/// `BcsBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn bcs_encode_from_json(
+    schema: *const BcsSchema,
+    json: *const u8,
+    len: usize,
+    bytes: *mut BcsBuffer,
+    error: *mut BcsBuffer,
+) -> u16 {
+    if bytes.is_null() {
+        return finish(Err(null_argument("bytes")), bytes, error);
+    }
+    let result = schema_ref(schema).and_then(|schema| {
+        let value = serde_json::from_slice(input(json, len, "json")?)
+            .map_err(|e| Error::Custom(format!("invalid JSON: {}", e)))?;
+        json_to_bcs(&value, &schema.format, &schema.registry)
+    });
+    finish(result, bytes, error)
+}
+
+/// Release the bytes of a buffer written by this library and reset it to `{NULL, 0}`.
+/// Does nothing if `buffer` is null or empty.
+///
+/// # Safety
+///
+/// `buffer` must be null or point to a buffer written by this library, or reset by this
+/// function, whose bytes were not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn bcs_buffer_free(buffer: *mut BcsBuffer) {
+    if buffer.is_null() || (*buffer).data.is_null() {
+        return;
+    }
+    let BcsBuffer { data, len } = ptr::replace(buffer, BcsBuffer::empty());
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+}
+
+unsafe fn input<'a>(data: *const u8, len: usize, name: &str) -> Result<&'a [u8]> {
+    if data.is_null() {
+        if len == 0 {
+            Ok(&[])
+        } else {
+            Err(null_argument(name))
+        }
+    } else {
+        Ok(std::slice::from_raw_parts(data, len))
+    }
+}
+
+unsafe fn schema_ref<'a>(schema: *const BcsSchema) -> Result<&'a BcsSchema> {
+    schema.as_ref().ok_or_else(|| null_argument("schema"))
+}
+
+unsafe fn decode(schema: *const BcsSchema, bytes: Result<&[u8]>) -> Result<serde_json::Value> {
+    let schema = schema_ref(schema)?;
+    bcs_to_json(bytes?, &schema.format, &schema.registry)
+}
+
+fn null_argument(name: &str) -> Error {
+    Error::Custom(format!("null pointer passed as {}", name))
+}
+
+/// Write the outcome of a call to the output and error buffers, and return its status.
+unsafe fn finish(result: Result<Vec<u8>>, output: *mut BcsBuffer, error: *mut BcsBuffer) -> u16 {
+    let (bytes, message, status) = match result {
+        Ok(bytes) => (bytes, Vec::new(), BCS_OK),
+        Err(e) => (Vec::new(), e.to_string().into_bytes(), e.code().as_u16()),
+    };
+    if !output.is_null() {
+        *output = BcsBuffer::new(bytes);
+    }
+    if !error.is_null() {
+        *error = BcsBuffer::new(message);
+    }
+    status
+}
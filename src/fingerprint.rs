@@ -0,0 +1,67 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{trace::trace_type, Result};
+use serde::{Deserialize, Serialize};
+use serde_reflection::{Format, Registry};
+use sha3::{Digest, Sha3_256};
+use std::fmt;
+
+/// A deterministic hash of the BCS format of a type.
+///
+/// The fingerprint covers the names, order and formats of the fields of every container
+/// reachable from the type, as well as the names and order of the variants of every enum.
+/// Two types with the same fingerprint have the same wire format; two peers can compare
+/// fingerprints at handshake and refuse to talk when the layouts of their messages differ.
+/// Renaming a field or a container changes the fingerprint even though it does not change
+/// the wire format.
+///
+/// The fingerprint is the SHA3-256 hash of the BCS serialization of the traced format and
+/// registry, as returned by [`crate::trace::trace_type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    /// Compute the fingerprint of a format, whose containers are defined in `registry`.
+    pub fn of_format(format: &Format, registry: &Registry) -> Result<Self> {
+        let bytes = crate::to_bytes(&(format, registry))?;
+        Ok(Self(Sha3_256::digest(bytes).into()))
+    }
+
+    /// The bytes of the fingerprint.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+/// Compute the [`Fingerprint`] of the BCS format of `T`.
+///
+/// ```rust
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Hello {
+///     version: u32,
+///     peer: String,
+/// }
+///
+/// let fingerprint = bcs::fingerprint::<Hello>().unwrap();
+/// assert_eq!(fingerprint, bcs::fingerprint::<Hello>().unwrap());
+/// assert_ne!(fingerprint, bcs::fingerprint::<(u32, String)>().unwrap());
+/// ```
+///
+/// Enums nested in `T` must be traced first with a [`crate::trace::FormatTracer`], whose
+/// registry is then passed to [`Fingerprint::of_format`].
+pub fn fingerprint<T>() -> Result<Fingerprint>
+where
+    T: Deserialize<'static>,
+{
+    let (format, registry) = trace_type::<T>()?;
+    Fingerprint::of_format(&format, &registry)
+}
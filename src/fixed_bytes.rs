@@ -0,0 +1,264 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Byte arrays of any length `N`, encoded in BCS as their `N` bytes without a length prefix.
+//!
+//! Serde only implements `Serialize` and `Deserialize` for arrays of up to 32 elements, and
+//! deserializes them one element at a time. This module provides [`FixedBytes<N>`] as well
+//! as adapters for `[u8; N]` fields and for `&'de [u8; N]` fields borrowed from the input:
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Signed<'a> {
+//!     #[serde(with = "bcs::fixed_bytes")]
+//!     public_key: [u8; 96],
+//!     #[serde(borrow, with = "bcs::fixed_bytes::borrowed")]
+//!     signature: &'a [u8; 64],
+//!     nonce: bcs::FixedBytes<48>,
+//! }
+//!
+//! let bytes = vec![7; 96 + 64 + 48];
+//! let signed: Signed = bcs::from_bytes(&bytes).unwrap();
+//! assert_eq!(signed.signature, &[7; 64]);
+//! assert_eq!(bcs::to_bytes(&signed).unwrap(), bytes);
+//! ```
+//!
+//! The arrays are serialized as newtype structs named `$bcs::FixedBytes<N>` around a tuple
+//! of `N` bytes. The BCS deserializer recognizes these names and reads the `N` bytes at
+//! once. In serde-reflection registries, they are newtype structs wrapping a
+//! `TupleArray` of `U8`, so that arrays of different lengths do not conflict.
+
+use serde::{de, ser::SerializeTuple, Deserialize, Deserializer, Serialize, Serializer};
+use std::{convert::TryInto, fmt};
+
+const NAME_PREFIX: &str = "$bcs::FixedBytes<";
+
+/// Room for the prefix, the decimal digits of any `usize` and the closing bracket.
+const NAME_CAPACITY: usize = NAME_PREFIX.len() + 20 + 1;
+
+/// Write `$bcs::FixedBytes<len>` in a buffer and return it with the length of the name.
+const fn write_name(len: usize) -> ([u8; NAME_CAPACITY], usize) {
+    let mut buffer = [0; NAME_CAPACITY];
+    let prefix = NAME_PREFIX.as_bytes();
+    let mut i = 0;
+    while i < prefix.len() {
+        buffer[i] = prefix[i];
+        i += 1;
+    }
+    let mut digits = 1;
+    while digits < 20 && len / 10usize.pow(digits) != 0 {
+        digits += 1;
+    }
+    let mut j = digits;
+    while j > 0 {
+        j -= 1;
+        buffer[i] = b'0' + (len / 10usize.pow(j) % 10) as u8;
+        i += 1;
+    }
+    buffer[i] = b'>';
+    (buffer, i + 1)
+}
+
+/// The name written by `write_name`.
+const fn as_name(buffer: &'static ([u8; NAME_CAPACITY], usize)) -> &'static str {
+    match std::str::from_utf8(buffer.0.split_at(buffer.1).0) {
+        Ok(name) => name,
+        Err(_) => panic!("names are ASCII"),
+    }
+}
+
+/// The length of the arrays serialized under `name`, if any.
+pub(crate) fn len(name: &str) -> Option<usize> {
+    name.strip_prefix(NAME_PREFIX)?
+        .strip_suffix('>')?
+        .parse()
+        .ok()
+}
+
+/// An array of `N` bytes, such as a signature or a public key, encoded in BCS without a
+/// length prefix.
+///
+/// ```rust
+/// use bcs::FixedBytes;
+///
+/// let signature = FixedBytes([0xab; 64]);
+/// let bytes = bcs::to_bytes(&signature).unwrap();
+/// assert_eq!(bytes, vec![0xab; 64]);
+/// assert_eq!(bcs::from_bytes::<FixedBytes<64>>(&bytes).unwrap(), signature);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedBytes<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Default for FixedBytes<N> {
+    fn default() -> Self {
+        FixedBytes([0; N])
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for FixedBytes<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        FixedBytes(bytes)
+    }
+}
+
+impl<const N: usize> From<FixedBytes<N>> for [u8; N] {
+    fn from(bytes: FixedBytes<N>) -> Self {
+        bytes.0
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for FixedBytes<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> Serialize for FixedBytes<N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for FixedBytes<N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(deserializer).map(FixedBytes)
+    }
+}
+
+impl<const N: usize> FixedBytes<N> {
+    const NAME_BUFFER: &'static ([u8; NAME_CAPACITY], usize) = &write_name(N);
+
+    /// The name under which arrays of `N` bytes are serialized.
+    pub(crate) const NAME: &'static str = as_name(Self::NAME_BUFFER);
+}
+
+/// Serialize an array of `N` bytes, for use with `#[serde(with = "bcs::fixed_bytes")]`.
+pub fn serialize<S, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_newtype_struct(FixedBytes::<N>::NAME, &Tuple(bytes))
+}
+
+/// Deserialize an array of `N` bytes, for use with `#[serde(with = "bcs::fixed_bytes")]`.
+pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_newtype_struct(FixedBytes::<N>::NAME, ArrayVisitor::<N>)
+}
+
+/// Adapters for arrays of `N` bytes borrowed from the input, for use with
+/// `#[serde(borrow, with = "bcs::fixed_bytes::borrowed")]`.
+///
+/// Deserialization only succeeds with formats that can lend their input, such as BCS.
+pub mod borrowed {
+    use super::{BorrowedVisitor, FixedBytes};
+    use serde::{Deserializer, Serializer};
+
+    /// Serialize a borrowed array of `N` bytes.
+    pub fn serialize<S, const N: usize>(bytes: &&[u8; N], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        super::serialize(bytes, serializer)
+    }
+
+    /// Deserialize an array of `N` bytes borrowed from the input.
+    pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<&'de [u8; N], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(FixedBytes::<N>::NAME, BorrowedVisitor::<N>)
+    }
+}
+
+/// The bytes as a tuple, like serde serializes arrays of up to 32 elements.
+struct Tuple<'a, const N: usize>(&'a [u8; N]);
+
+impl<const N: usize> Serialize for Tuple<'_, N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for byte in self.0 {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+struct ArrayVisitor<const N: usize>;
+
+impl<'de, const N: usize> de::Visitor<'de> for ArrayVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "an array of {} bytes", N)
+    }
+
+    // Called by the BCS deserializer, which reads the `N` bytes at once.
+    fn visit_bytes<E>(self, bytes: &[u8]) -> Result<[u8; N], E>
+    where
+        E: de::Error,
+    {
+        bytes
+            .try_into()
+            .map_err(|_| E::invalid_length(bytes.len(), &self))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<[u8; N], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(N, self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<[u8; N], A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        Ok(bytes)
+    }
+}
+
+struct BorrowedVisitor<const N: usize>;
+
+impl<'de, const N: usize> de::Visitor<'de> for BorrowedVisitor<N> {
+    type Value = &'de [u8; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "an array of {} bytes borrowed from the input", N)
+    }
+
+    fn visit_borrowed_bytes<E>(self, bytes: &'de [u8]) -> Result<&'de [u8; N], E>
+    where
+        E: de::Error,
+    {
+        bytes
+            .try_into()
+            .map_err(|_| E::invalid_length(bytes.len(), &self))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<&'de [u8; N], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(N, self)
+    }
+}
@@ -0,0 +1,601 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Generation of valid and malformed BCS inputs for fuzzing decoders.
+//!
+//! Property tests over Rust values only ever feed canonical bytes to the deserializer. This
+//! module works at the byte level instead: given a format description traced with
+//! `serde-reflection`, it generates
+//! * valid canonical BCS encodings of arbitrary values of that format, and
+//! * near-valid encodings carrying exactly one [`Defect`] (a non-minimal ULEB128 length,
+//!   unsorted map keys, an invalid option tag, ...) together with the [`Error`] that
+//!   decoding them must produce.
+//!
+//! Generation is driven by an [`arbitrary::Unstructured`] source of entropy, so that the
+//! same functions serve `cargo fuzz` targets and, through [`valid_bytes`] and
+//! [`malformed_bytes`], `proptest` strategies.
+//!
+//! ```rust
+//! # use proptest::prelude::*;
+//! # use serde_reflection::{Tracer, TracerConfig};
+//! #[derive(Debug, serde::Deserialize)]
+//! enum Message { Ping, Data(Vec<u8>), Close { reason: Option<String> } }
+//!
+//! let mut tracer = Tracer::new(TracerConfig::default());
+//! let (format, _) = tracer.trace_simple_type::<Message>().unwrap();
+//! let registry = tracer.registry().unwrap();
+//!
+//! proptest!(|(input in bcs::fuzzing::malformed_bytes(format.clone(), registry.clone()))| {
+//!     let error = bcs::from_bytes::<Message>(&input.bytes).unwrap_err();
+//!     prop_assert_eq!(error, input.expected_error);
+//! });
+//! ```
+//!
+//! The formats `F32`, `F64` and `Char` are not supported by BCS and make generation fail
+//! with [`arbitrary::Error::IncorrectFormat`], as do unresolved `Variable` formats and
+//! names missing from the registry.
+
+use crate::{
+    uleb128::{self, ULEB32_NAME, ULEB64_NAME},
+    Error,
+};
+use arbitrary::Unstructured;
+use proptest::{collection::vec, prelude::*};
+use serde_reflection::{ContainerFormat, Format, Named, Registry, VariantFormat};
+use std::{collections::BTreeSet, ops::Range};
+
+/// Number of named containers that may be nested before generation only picks the
+/// smallest values (`None`, empty sequences, non-recursive variants).
+const RECURSION_BUDGET: usize = 8;
+/// Maximal number of elements of generated sequences and maps.
+const MAX_SEQ_LEN: usize = 8;
+/// Maximal length of generated byte arrays and strings. Larger than 127 so that
+/// multi-byte ULEB128 lengths are exercised.
+const MAX_BYTES_LEN: usize = 300;
+/// Maximal number of entropy bytes fed to the generators by the `proptest` strategies.
+const MAX_ENTROPY_LEN: usize = 1024;
+
+/// The single defect introduced in a [`MalformedBytes`] input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Defect {
+    /// A length or variant index is encoded with a superfluous trailing zero digit.
+    NonMinimalUleb128,
+    /// A length or variant index is encoded as a ULEB128 integer larger than `u32::MAX`.
+    Uleb128Overflow,
+    /// Two consecutive entries of a map are swapped.
+    UnsortedMapKeys,
+    /// An entry of a map is replaced by a copy of the previous one.
+    DuplicateMapKeys,
+    /// A boolean is encoded as a byte other than 0 or 1.
+    InvalidBool,
+    /// An option tag is a byte other than 0 or 1.
+    InvalidOptionTag,
+    /// A variant index is out of range for its enum.
+    InvalidVariantIndex,
+    /// A byte of a string is replaced by 0xff, which never occurs in UTF-8.
+    InvalidUtf8,
+    /// The input is a strict prefix of a valid encoding.
+    Truncated,
+    /// A byte is appended to a valid encoding.
+    TrailingBytes,
+}
+
+/// A near-valid BCS input and the error expected from decoding it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MalformedBytes {
+    pub bytes: Vec<u8>,
+    pub defect: Defect,
+    /// The error returned by `bcs::from_bytes` on `bytes`. For
+    /// [`Defect::InvalidVariantIndex`], this is the message produced by enums deriving
+    /// `Deserialize` with `serde_derive`.
+    pub expected_error: Error,
+}
+
+/// Generate the canonical BCS encoding of an arbitrary value of the given format.
+pub fn arbitrary_valid_bytes(
+    u: &mut Unstructured<'_>,
+    format: &Format,
+    registry: &Registry,
+) -> arbitrary::Result<Vec<u8>> {
+    Ok(Generator::new(u, registry).generate(format)?.bytes)
+}
+
+/// Generate an arbitrary value of the given format, then alter its canonical BCS encoding
+/// with one arbitrary [`Defect`] among those applicable to it.
+pub fn arbitrary_malformed_bytes(
+    u: &mut Unstructured<'_>,
+    format: &Format,
+    registry: &Registry,
+) -> arbitrary::Result<MalformedBytes> {
+    let valid = Generator::new(u, registry).generate(format)?;
+    let mut mutations = vec![Mutation::TrailingBytes];
+    if !valid.bytes.is_empty() {
+        mutations.push(Mutation::Truncated);
+    }
+    for site in &valid.sites {
+        site.push_mutations(&mut mutations);
+    }
+    let mutation = u.choose(&mutations)?;
+    mutation.apply(u, valid.bytes)
+}
+
+/// A `proptest` strategy for the canonical BCS encodings of values of the given format.
+///
+/// Panics when generating from an unsupported format.
+pub fn valid_bytes(format: Format, registry: Registry) -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..MAX_ENTROPY_LEN).prop_map(move |entropy| {
+        arbitrary_valid_bytes(&mut Unstructured::new(&entropy), &format, &registry)
+            .expect("format should be supported by BCS")
+    })
+}
+
+/// A `proptest` strategy for near-valid BCS encodings of values of the given format.
+///
+/// Panics when generating from an unsupported format.
+pub fn malformed_bytes(
+    format: Format,
+    registry: Registry,
+) -> impl Strategy<Value = MalformedBytes> {
+    vec(any::<u8>(), 0..MAX_ENTROPY_LEN).prop_map(move |entropy| {
+        arbitrary_malformed_bytes(&mut Unstructured::new(&entropy), &format, &registry)
+            .expect("format should be supported by BCS")
+    })
+}
+
+/// Canonical bytes together with the locations where a defect may be introduced.
+#[derive(Default)]
+struct Generated {
+    bytes: Vec<u8>,
+    sites: Vec<Site>,
+}
+
+struct Site {
+    range: Range<usize>,
+    kind: SiteKind,
+}
+
+enum SiteKind {
+    Length,
+    VariantIndex {
+        count: u32,
+    },
+    Bool,
+    OptionTag,
+    /// The content of a string, without its length.
+    Str,
+    /// The ranges of the key-value entries of a map, without its length.
+    Map {
+        entries: Vec<Range<usize>>,
+    },
+}
+
+impl Generated {
+    fn push(&mut self, bytes: &[u8], kind: SiteKind) {
+        let start = self.bytes.len();
+        self.bytes.extend_from_slice(bytes);
+        self.sites.push(Site {
+            range: start..self.bytes.len(),
+            kind,
+        });
+    }
+
+    fn append(&mut self, other: Generated) {
+        let offset = self.bytes.len();
+        let shift = |range: Range<usize>| range.start + offset..range.end + offset;
+        self.bytes.extend(other.bytes);
+        self.sites.extend(other.sites.into_iter().map(|site| Site {
+            range: shift(site.range),
+            kind: match site.kind {
+                SiteKind::Map { entries } => SiteKind::Map {
+                    entries: entries.into_iter().map(shift).collect(),
+                },
+                kind => kind,
+            },
+        }));
+    }
+}
+
+struct Generator<'a, 'b> {
+    u: &'a mut Unstructured<'b>,
+    registry: &'a Registry,
+    /// Containers that have finite values reachable without choosing a non-empty
+    /// sequence or a `Some` option.
+    terminating: BTreeSet<&'a str>,
+    depth: usize,
+}
+
+impl<'a, 'b> Generator<'a, 'b> {
+    fn new(u: &'a mut Unstructured<'b>, registry: &'a Registry) -> Self {
+        let mut terminating = BTreeSet::new();
+        loop {
+            let found: Vec<_> = registry
+                .iter()
+                .filter(|(name, container)| {
+                    !terminating.contains(name.as_str())
+                        && container_terminates(container, &terminating)
+                })
+                .map(|(name, _)| name.as_str())
+                .collect();
+            if found.is_empty() {
+                break;
+            }
+            terminating.extend(found);
+        }
+        Self {
+            u,
+            registry,
+            terminating,
+            depth: 0,
+        }
+    }
+
+    fn generate(mut self, format: &Format) -> arbitrary::Result<Generated> {
+        if !format_terminates(format, &self.terminating) {
+            return Err(arbitrary::Error::IncorrectFormat);
+        }
+        let mut output = Generated::default();
+        self.format(format, &mut output)?;
+        Ok(output)
+    }
+
+    fn exhausted(&self) -> bool {
+        self.depth >= RECURSION_BUDGET
+    }
+
+    fn length(&mut self, max: usize, output: &mut Generated) -> arbitrary::Result<usize> {
+        let len = if self.exhausted() {
+            0
+        } else {
+            self.u.int_in_range(0..=max)?
+        };
+        output.push(&uleb128(len as u32), SiteKind::Length);
+        Ok(len)
+    }
+
+    fn format(&mut self, format: &Format, output: &mut Generated) -> arbitrary::Result<()> {
+        use Format::*;
+
+        match format {
+            Variable(_) | F32 | F64 | Char => return Err(arbitrary::Error::IncorrectFormat),
+            TypeName(name) => self.container(name, output)?,
+            Unit => (),
+            Bool => {
+                let value: bool = self.u.arbitrary()?;
+                output.push(&[value as u8], SiteKind::Bool);
+            }
+            I8 => output.bytes.push(self.u.arbitrary::<i8>()? as u8),
+            I16 => output
+                .bytes
+                .extend(self.u.arbitrary::<i16>()?.to_le_bytes()),
+            I32 => output
+                .bytes
+                .extend(self.u.arbitrary::<i32>()?.to_le_bytes()),
+            I64 => output
+                .bytes
+                .extend(self.u.arbitrary::<i64>()?.to_le_bytes()),
+            I128 => output
+                .bytes
+                .extend(self.u.arbitrary::<i128>()?.to_le_bytes()),
+            U8 => output.bytes.push(self.u.arbitrary()?),
+            U16 => output
+                .bytes
+                .extend(self.u.arbitrary::<u16>()?.to_le_bytes()),
+            U32 => output
+                .bytes
+                .extend(self.u.arbitrary::<u32>()?.to_le_bytes()),
+            U64 => output
+                .bytes
+                .extend(self.u.arbitrary::<u64>()?.to_le_bytes()),
+            U128 => output
+                .bytes
+                .extend(self.u.arbitrary::<u128>()?.to_le_bytes()),
+            Str => {
+                let mut value: String = self.u.arbitrary()?;
+                if self.exhausted() {
+                    value.clear();
+                }
+                let mut len = value.len().min(MAX_BYTES_LEN);
+                while !value.is_char_boundary(len) {
+                    len -= 1;
+                }
+                value.truncate(len);
+                output.push(&uleb128(len as u32), SiteKind::Length);
+                output.push(value.as_bytes(), SiteKind::Str);
+            }
+            Bytes => {
+                let len = self.length(MAX_BYTES_LEN, output)?;
+                for _ in 0..len {
+                    output.bytes.push(self.u.arbitrary()?);
+                }
+            }
+            Option(format) => {
+                let is_some = !self.exhausted() && self.u.arbitrary()?;
+                output.push(&[is_some as u8], SiteKind::OptionTag);
+                if is_some {
+                    self.format(format, output)?;
+                }
+            }
+            Seq(format) => {
+                let len = self.length(MAX_SEQ_LEN, output)?;
+                for _ in 0..len {
+                    self.format(format, output)?;
+                }
+            }
+            Map { key, value } => {
+                let len = if self.exhausted() {
+                    0
+                } else {
+                    self.u.int_in_range(0..=MAX_SEQ_LEN)?
+                };
+                let mut entries = Vec::new();
+                for _ in 0..len {
+                    let mut entry = Generated::default();
+                    self.format(key, &mut entry)?;
+                    let key_len = entry.bytes.len();
+                    self.format(value, &mut entry)?;
+                    entries.push((key_len, entry));
+                }
+                // Canonical maps are sorted by the bytes of their keys, without duplicates.
+                entries.sort_by(|(len1, e1), (len2, e2)| e1.bytes[..*len1].cmp(&e2.bytes[..*len2]));
+                entries.dedup_by(|(len1, e1), (len2, e2)| e1.bytes[..*len1] == e2.bytes[..*len2]);
+                output.push(&uleb128(entries.len() as u32), SiteKind::Length);
+                let start = output.bytes.len();
+                let mut ranges = Vec::new();
+                for (_, entry) in entries {
+                    let entry_start = output.bytes.len();
+                    output.append(entry);
+                    ranges.push(entry_start..output.bytes.len());
+                }
+                output.sites.push(Site {
+                    range: start..output.bytes.len(),
+                    kind: SiteKind::Map { entries: ranges },
+                });
+            }
+            Tuple(formats) => {
+                for format in formats {
+                    self.format(format, output)?;
+                }
+            }
+            TupleArray { content, size } => {
+                for _ in 0..*size {
+                    self.format(content, output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn container(&mut self, name: &str, output: &mut Generated) -> arbitrary::Result<()> {
+        let container = self
+            .registry
+            .get(name)
+            .ok_or(arbitrary::Error::IncorrectFormat)?;
+        if self.depth >= crate::MAX_CONTAINER_DEPTH {
+            return Err(arbitrary::Error::IncorrectFormat);
+        }
+        self.depth += 1;
+        match container {
+            ContainerFormat::NewTypeStruct(_) if name == ULEB32_NAME => {
+                let value = self.u.arbitrary::<u32>()?;
+                output.bytes.extend(uleb128_u64(value.into()));
+            }
+            ContainerFormat::NewTypeStruct(_) if name == ULEB64_NAME => {
+                let value = self.u.arbitrary::<u64>()?;
+                output.bytes.extend(uleb128_u64(value));
+            }
+            ContainerFormat::UnitStruct => (),
+            ContainerFormat::NewTypeStruct(format) => self.format(format, output)?,
+            ContainerFormat::TupleStruct(formats) => {
+                for format in formats {
+                    self.format(format, output)?;
+                }
+            }
+            ContainerFormat::Struct(fields) => {
+                for field in fields {
+                    self.format(&field.value, output)?;
+                }
+            }
+            ContainerFormat::Enum(variants) => {
+                let count = variants.len() as u32;
+                let (index, variant) = if self.exhausted() {
+                    variants
+                        .iter()
+                        .find(|(_, variant)| variant_terminates(&variant.value, &self.terminating))
+                        .ok_or(arbitrary::Error::IncorrectFormat)?
+                } else {
+                    self.u.choose_iter(variants.iter())?
+                };
+                output.push(&uleb128(*index), SiteKind::VariantIndex { count });
+                match &variant.value {
+                    VariantFormat::Variable(_) => return Err(arbitrary::Error::IncorrectFormat),
+                    VariantFormat::Unit => (),
+                    VariantFormat::NewType(format) => self.format(format, output)?,
+                    VariantFormat::Tuple(formats) => {
+                        for format in formats {
+                            self.format(format, output)?;
+                        }
+                    }
+                    VariantFormat::Struct(fields) => {
+                        for field in fields {
+                            self.format(&field.value, output)?;
+                        }
+                    }
+                }
+            }
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+fn format_terminates(format: &Format, terminating: &BTreeSet<&str>) -> bool {
+    match format {
+        Format::Variable(_) => false,
+        Format::TypeName(name) => terminating.contains(name.as_str()),
+        Format::Tuple(formats) => formats.iter().all(|f| format_terminates(f, terminating)),
+        Format::TupleArray { content, size } => {
+            *size == 0 || format_terminates(content, terminating)
+        }
+        _ => true,
+    }
+}
+
+fn fields_terminate(fields: &[Named<Format>], terminating: &BTreeSet<&str>) -> bool {
+    fields
+        .iter()
+        .all(|field| format_terminates(&field.value, terminating))
+}
+
+fn variant_terminates(variant: &VariantFormat, terminating: &BTreeSet<&str>) -> bool {
+    match variant {
+        VariantFormat::Variable(_) => false,
+        VariantFormat::Unit => true,
+        VariantFormat::NewType(format) => format_terminates(format, terminating),
+        VariantFormat::Tuple(formats) => formats.iter().all(|f| format_terminates(f, terminating)),
+        VariantFormat::Struct(fields) => fields_terminate(fields, terminating),
+    }
+}
+
+fn container_terminates(container: &ContainerFormat, terminating: &BTreeSet<&str>) -> bool {
+    match container {
+        ContainerFormat::UnitStruct => true,
+        ContainerFormat::NewTypeStruct(format) => format_terminates(format, terminating),
+        ContainerFormat::TupleStruct(formats) => {
+            formats.iter().all(|f| format_terminates(f, terminating))
+        }
+        ContainerFormat::Struct(fields) => fields_terminate(fields, terminating),
+        ContainerFormat::Enum(variants) => variants
+            .values()
+            .any(|variant| variant_terminates(&variant.value, terminating)),
+    }
+}
+
+/// A defect applicable to a particular location of a valid input.
+#[derive(Clone, Debug)]
+enum Mutation {
+    Truncated,
+    TrailingBytes,
+    NonMinimalUleb128(Range<usize>),
+    Uleb128Overflow(Range<usize>),
+    InvalidVariantIndex(Range<usize>, u32),
+    InvalidBool(usize),
+    InvalidOptionTag(usize),
+    InvalidUtf8(Range<usize>),
+    UnsortedMapKeys(Range<usize>, Range<usize>),
+    DuplicateMapKeys(Range<usize>, Range<usize>),
+}
+
+impl Site {
+    fn push_mutations(&self, mutations: &mut Vec<Mutation>) {
+        let range = self.range.clone();
+        match &self.kind {
+            SiteKind::Length | SiteKind::VariantIndex { .. } => {
+                // The decoder reads at most 5 bytes of ULEB128.
+                if range.len() < 5 {
+                    mutations.push(Mutation::NonMinimalUleb128(range.clone()));
+                }
+                mutations.push(Mutation::Uleb128Overflow(range.clone()));
+                if let SiteKind::VariantIndex { count } = self.kind {
+                    mutations.push(Mutation::InvalidVariantIndex(range, count));
+                }
+            }
+            SiteKind::Bool => mutations.push(Mutation::InvalidBool(range.start)),
+            SiteKind::OptionTag => mutations.push(Mutation::InvalidOptionTag(range.start)),
+            SiteKind::Str => {
+                if !range.is_empty() {
+                    mutations.push(Mutation::InvalidUtf8(range));
+                }
+            }
+            SiteKind::Map { entries } => {
+                for pair in entries.windows(2) {
+                    mutations.push(Mutation::UnsortedMapKeys(pair[0].clone(), pair[1].clone()));
+                    mutations.push(Mutation::DuplicateMapKeys(pair[0].clone(), pair[1].clone()));
+                }
+            }
+        }
+    }
+}
+
+impl Mutation {
+    fn apply(
+        &self,
+        u: &mut Unstructured<'_>,
+        mut bytes: Vec<u8>,
+    ) -> arbitrary::Result<MalformedBytes> {
+        let (defect, expected_error) = match self {
+            Mutation::Truncated => {
+                let len = u.int_in_range(0..=bytes.len() - 1)?;
+                bytes.truncate(len);
+                (Defect::Truncated, Error::Eof)
+            }
+            Mutation::TrailingBytes => {
+                bytes.push(u.arbitrary()?);
+                (Defect::TrailingBytes, Error::RemainingInput)
+            }
+            Mutation::NonMinimalUleb128(range) => {
+                let last = range.end - 1;
+                bytes[last] |= 0x80;
+                bytes.insert(range.end, 0);
+                (
+                    Defect::NonMinimalUleb128,
+                    Error::NonCanonicalUleb128Encoding,
+                )
+            }
+            Mutation::Uleb128Overflow(range) => {
+                let value = u.int_in_range(1u64 << 32..=(1 << 35) - 1)?;
+                bytes.splice(range.clone(), uleb128_u64(value));
+                (
+                    Defect::Uleb128Overflow,
+                    Error::IntegerOverflowDuringUleb128Decoding,
+                )
+            }
+            Mutation::InvalidVariantIndex(range, count) => {
+                let index = u.int_in_range(*count..=u32::MAX)?;
+                bytes.splice(range.clone(), uleb128(index));
+                (
+                    Defect::InvalidVariantIndex,
+                    Error::Custom(format!(
+                        "invalid value: integer `{}`, expected variant index 0 <= i < {}",
+                        index, count
+                    )),
+                )
+            }
+            Mutation::InvalidBool(offset) => {
+                bytes[*offset] = u.int_in_range(2..=255)?;
+                (Defect::InvalidBool, Error::ExpectedBoolean)
+            }
+            Mutation::InvalidOptionTag(offset) => {
+                bytes[*offset] = u.int_in_range(2..=255)?;
+                (Defect::InvalidOptionTag, Error::ExpectedOption)
+            }
+            Mutation::InvalidUtf8(range) => {
+                let offset = u.int_in_range(range.start..=range.end - 1)?;
+                bytes[offset] = 0xff;
+                (Defect::InvalidUtf8, Error::Utf8)
+            }
+            Mutation::UnsortedMapKeys(first, second) => {
+                let swapped = [&bytes[second.clone()], &bytes[first.clone()]].concat();
+                bytes.splice(first.start..second.end, swapped);
+                (Defect::UnsortedMapKeys, Error::NonCanonicalMap)
+            }
+            Mutation::DuplicateMapKeys(first, second) => {
+                let copy = bytes[first.clone()].to_vec();
+                bytes.splice(second.clone(), copy);
+                (Defect::DuplicateMapKeys, Error::NonCanonicalMap)
+            }
+        };
+        Ok(MalformedBytes {
+            bytes,
+            defect,
+            expected_error,
+        })
+    }
+}
+
+fn uleb128(value: u32) -> Vec<u8> {
+    uleb128_u64(value.into())
+}
+
+fn uleb128_u64(value: u64) -> Vec<u8> {
+    uleb128::encode(value, &mut [0; uleb128::MAX_LEN]).to_vec()
+}
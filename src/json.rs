@@ -0,0 +1,612 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Transcoding between JSON and BCS, guided by the formats traced with `serde-reflection`.
+//!
+//! A JSON value is converted to the canonical BCS encoding of a value of a given [`Format`],
+//! and back, using the following conventions:
+//!
+//! | Format                                      | JSON                                             |
+//! |---------------------------------------------|--------------------------------------------------|
+//! | `Unit`, unit structs                        | `null`                                           |
+//! | `Bool`                                      | boolean                                          |
+//! | `I8` to `I32`, `U8` to `U32`                | number                                           |
+//! | `I64`, `I128`, `U64`, `U128`                | decimal string, e.g. `"-12"`                     |
+//! | `Str`                                       | string                                           |
+//! | `Bytes`, sequences and arrays of `U8`       | hexadecimal string, e.g. `"00ff"`                |
+//! | `Option`                                    | `null` or the value                              |
+//! | `Seq`, `Tuple`, `TupleArray`, tuple structs | array                                            |
+//! | `Map` with `Str` keys                       | object                                           |
+//! | other `Map`s                                | array of `[key, value]` arrays                   |
+//! | newtype structs, `Uleb32` and `Uleb64`      | the inner value                                  |
+//! | structs                                     | object with exactly the fields                   |
+//! | enums                                       | `{"Variant": payload}`, `null` for unit variants |
+//!
+//! Integers and hexadecimal strings must be canonical: no sign for non-negative
+//! numbers, no leading zeros, and an even number of hexadecimal digits, which are written in
+//! lowercase but may be read in any case. Map entries are sorted by the BCS encoding of their
+//! keys, and duplicate keys are rejected with `Error::NonCanonicalMap`. Floats and `char` are
+//! not supported, nor are options of options or of `()`, which cannot be told apart in JSON.
+//!
+//! ```rust
+//! use bcs::json::{bcs_to_json, json_to_bcs};
+//! use serde::{Deserialize, Serialize};
+//! use serde_json::json;
+//! use serde_reflection::{Format, Tracer, TracerConfig};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! enum Transfer {
+//!     Coins { amount: u64, to: Vec<u8> },
+//!     Burn(u64),
+//! }
+//!
+//! let mut tracer = Tracer::new(TracerConfig::default());
+//! tracer.trace_simple_type::<Transfer>().unwrap();
+//! let registry = tracer.registry().unwrap();
+//! let format = Format::TypeName("Transfer".to_string());
+//!
+//! let value = json!({"Coins": {"amount": "100", "to": "abcd"}});
+//! let bytes = json_to_bcs(&value, &format, &registry).unwrap();
+//! let transfer = Transfer::Coins { amount: 100, to: vec![0xab, 0xcd] };
+//! assert_eq!(bytes, bcs::to_bytes(&transfer).unwrap());
+//! assert_eq!(bcs_to_json(&bytes, &format, &registry).unwrap(), value);
+//! ```
+
+use crate::{
+    de::Deserializer,
+    hex,
+    uleb128::{self, ULEB32_NAME, ULEB64_NAME},
+    Error, Result,
+};
+use serde_json::{Map, Value};
+use serde_reflection::{ContainerFormat, Format, Named, Registry, VariantFormat};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
+
+/// Convert a JSON value to the BCS encoding of a value of the given format.
+///
+/// The containers used by `format` must be defined in `registry`.
+pub fn json_to_bcs(value: &Value, format: &Format, registry: &Registry) -> Result<Vec<u8>> {
+    let mut encoder = Encoder {
+        output: Vec::new(),
+        registry,
+        max_remaining_depth: crate::MAX_CONTAINER_DEPTH,
+    };
+    encoder.encode(value, format)?;
+    Ok(encoder.output)
+}
+
+/// Convert the BCS encoding of a value of the given format to JSON.
+///
+/// The containers used by `format` must be defined in `registry`. The input is checked as
+/// strictly as by [`crate::from_bytes`].
+pub fn bcs_to_json(bytes: &[u8], format: &Format, registry: &Registry) -> Result<Value> {
+    let mut decoder = Decoder {
+        de: Deserializer::new(bytes, crate::MAX_CONTAINER_DEPTH),
+        registry,
+        max_remaining_depth: crate::MAX_CONTAINER_DEPTH,
+    };
+    let value = decoder.decode(format)?;
+    decoder.de.end()?;
+    Ok(value)
+}
+
+fn container<'a>(registry: &'a Registry, name: &str) -> Result<&'a ContainerFormat> {
+    registry
+        .get(name)
+        .ok_or_else(|| Error::Custom(format!("unknown container {}", name)))
+}
+
+fn enter_container(max_remaining_depth: &mut usize, name: &str) -> Result<()> {
+    if *max_remaining_depth == 0 {
+        return Err(Error::ExceededContainerDepthLimit(static_name(name)));
+    }
+    *max_remaining_depth -= 1;
+    Ok(())
+}
+
+/// Container names come from the registry, while errors hold `&'static str`: leak each
+/// distinct name once.
+fn static_name(name: &str) -> &'static str {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut names = NAMES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+/// Formats whose values cannot be told apart from `None` in JSON.
+fn check_option_content(format: &Format) -> Result<()> {
+    match format {
+        Format::Option(_) | Format::Unit => Err(Error::NotSupported("options of options or of ()")),
+        _ => Ok(()),
+    }
+}
+
+fn unsupported(format: &Format) -> Error {
+    match format {
+        Format::F32 => Error::NotSupported("f32"),
+        Format::F64 => Error::NotSupported("f64"),
+        Format::Char => Error::NotSupported("char"),
+        _ => Error::Custom(format!("unsupported format {:?}", format)),
+    }
+}
+
+fn invalid(value: &Value, expected: &str) -> Error {
+    Error::Custom(format!(
+        "invalid JSON value {}, expected {}",
+        value, expected
+    ))
+}
+
+fn find_variant<'v, 'f>(
+    value: &'v Value,
+    name: &str,
+    variants: &'f BTreeMap<u32, Named<VariantFormat>>,
+) -> Result<(u32, &'f Named<VariantFormat>, &'v Value)> {
+    let (variant, payload) = match value.as_object() {
+        Some(object) if object.len() == 1 => object.iter().next().unwrap(),
+        _ => return Err(invalid(value, "an object with a single variant")),
+    };
+    variants
+        .iter()
+        .find(|(_, named)| named.name == *variant)
+        .map(|(index, named)| (*index, named, payload))
+        .ok_or_else(|| Error::Custom(format!("unknown variant `{}` of {}", variant, name)))
+}
+
+/// The error of `#[derive(Deserialize)]`.
+fn unknown_variant_index(index: u32, count: usize) -> Error {
+    Error::Custom(format!(
+        "invalid value: integer `{}`, expected variant index 0 <= i < {}",
+        index, count
+    ))
+}
+
+fn unknown_variant_format(variant: &Named<VariantFormat>) -> Error {
+    Error::Custom(format!("unknown format of variant {}", variant.name))
+}
+
+struct Encoder<'a> {
+    output: Vec<u8>,
+    registry: &'a Registry,
+    max_remaining_depth: usize,
+}
+
+impl<'a> Encoder<'a> {
+    // Recursive functions only dispatch, to keep their stack frames small.
+    fn encode(&mut self, value: &Value, format: &Format) -> Result<()> {
+        match format {
+            Format::TypeName(name) => self.encode_container(value, name),
+            Format::Option(format) => self.encode_option(value, format),
+            Format::Seq(format) => self.encode_seq(value, format),
+            Format::Map { key, value: format } => self.encode_map(value, key, format),
+            Format::Tuple(formats) => self.encode_tuple(value, formats),
+            Format::TupleArray { content, size } => self.encode_array(value, content, *size),
+            _ => self.encode_primitive(value, format),
+        }
+    }
+
+    fn encode_primitive(&mut self, value: &Value, format: &Format) -> Result<()> {
+        match format {
+            Format::Unit => match value {
+                Value::Null => Ok(()),
+                _ => Err(invalid(value, "null")),
+            },
+            Format::Bool => match value {
+                Value::Bool(b) => {
+                    self.output.push(u8::from(*b));
+                    Ok(())
+                }
+                _ => Err(invalid(value, "a boolean")),
+            },
+            Format::I8 => self.encode_number::<i8>(value, |n| n.to_le_bytes().to_vec()),
+            Format::I16 => self.encode_number::<i16>(value, |n| n.to_le_bytes().to_vec()),
+            Format::I32 => self.encode_number::<i32>(value, |n| n.to_le_bytes().to_vec()),
+            Format::U8 => self.encode_number::<u8>(value, |n| vec![n]),
+            Format::U16 => self.encode_number::<u16>(value, |n| n.to_le_bytes().to_vec()),
+            Format::U32 => self.encode_number::<u32>(value, |n| n.to_le_bytes().to_vec()),
+            Format::I64 => self.encode_decimal::<i64>(value, |n| n.to_le_bytes().to_vec()),
+            Format::I128 => self.encode_decimal::<i128>(value, |n| n.to_le_bytes().to_vec()),
+            Format::U64 => self.encode_decimal::<u64>(value, |n| n.to_le_bytes().to_vec()),
+            Format::U128 => self.encode_decimal::<u128>(value, |n| n.to_le_bytes().to_vec()),
+            Format::Str => match value {
+                Value::String(s) => self.encode_bytes(s.as_bytes()),
+                _ => Err(invalid(value, "a string")),
+            },
+            Format::Bytes => {
+                let bytes = decode_hex(value)?;
+                self.encode_bytes(&bytes)
+            }
+            _ => Err(unsupported(format)),
+        }
+    }
+
+    fn encode_option(&mut self, value: &Value, format: &Format) -> Result<()> {
+        check_option_content(format)?;
+        if value.is_null() {
+            self.output.push(0);
+            Ok(())
+        } else {
+            self.output.push(1);
+            self.encode(value, format)
+        }
+    }
+
+    fn encode_seq(&mut self, value: &Value, format: &Format) -> Result<()> {
+        if *format == Format::U8 {
+            return self.encode_primitive(value, &Format::Bytes);
+        }
+        let values = value.as_array().ok_or_else(|| invalid(value, "an array"))?;
+        self.encode_len(values.len())?;
+        values
+            .iter()
+            .try_for_each(|value| self.encode(value, format))
+    }
+
+    fn encode_array(&mut self, value: &Value, content: &Format, size: usize) -> Result<()> {
+        if *content == Format::U8 {
+            let bytes = decode_hex(value)?;
+            if bytes.len() != size {
+                return Err(invalid(value, &format!("{} bytes", size)));
+            }
+            self.output.extend_from_slice(&bytes);
+            return Ok(());
+        }
+        match value.as_array() {
+            Some(values) if values.len() == size => values
+                .iter()
+                .try_for_each(|value| self.encode(value, content)),
+            _ => Err(invalid(value, &format!("an array of length {}", size))),
+        }
+    }
+
+    fn encode_container(&mut self, value: &Value, name: &str) -> Result<()> {
+        let registry = self.registry;
+        enter_container(&mut self.max_remaining_depth, name)?;
+        match container(registry, name)? {
+            ContainerFormat::NewTypeStruct(_) if name == ULEB32_NAME => self
+                .encode_number::<u32>(value, |n| {
+                    uleb128::encode(n.into(), &mut [0; uleb128::MAX_LEN]).to_vec()
+                })?,
+            ContainerFormat::NewTypeStruct(_) if name == ULEB64_NAME => self
+                .encode_decimal::<u64>(value, |n| {
+                    uleb128::encode(n, &mut [0; uleb128::MAX_LEN]).to_vec()
+                })?,
+            ContainerFormat::UnitStruct => self.encode_primitive(value, &Format::Unit)?,
+            ContainerFormat::NewTypeStruct(format) => self.encode(value, format)?,
+            ContainerFormat::TupleStruct(formats) => self.encode_tuple(value, formats)?,
+            ContainerFormat::Struct(fields) => self.encode_struct(value, fields)?,
+            ContainerFormat::Enum(variants) => self.encode_variant(value, name, variants)?,
+        }
+        self.max_remaining_depth += 1;
+        Ok(())
+    }
+
+    fn encode_variant(
+        &mut self,
+        value: &Value,
+        name: &str,
+        variants: &BTreeMap<u32, Named<VariantFormat>>,
+    ) -> Result<()> {
+        let (index, variant, payload) = find_variant(value, name, variants)?;
+        self.output
+            .extend_from_slice(uleb128::encode(index.into(), &mut [0; uleb128::MAX_LEN]));
+        match &variant.value {
+            VariantFormat::Unit => self.encode_primitive(payload, &Format::Unit),
+            VariantFormat::NewType(format) => self.encode(payload, format),
+            VariantFormat::Tuple(formats) => self.encode_tuple(payload, formats),
+            VariantFormat::Struct(fields) => self.encode_struct(payload, fields),
+            VariantFormat::Variable(_) => Err(unknown_variant_format(variant)),
+        }
+    }
+
+    fn encode_number<T>(&mut self, value: &Value, to_bytes: fn(T) -> Vec<u8>) -> Result<()>
+    where
+        T: std::convert::TryFrom<i64> + std::convert::TryFrom<u64>,
+    {
+        let number = match value {
+            Value::Number(n) => n
+                .as_u64()
+                .and_then(|n| T::try_from(n).ok())
+                .or_else(|| n.as_i64().and_then(|n| T::try_from(n).ok())),
+            _ => None,
+        };
+        let number = number.ok_or_else(|| {
+            invalid(
+                value,
+                &format!("an integer of type {}", std::any::type_name::<T>()),
+            )
+        })?;
+        self.output.extend(to_bytes(number));
+        Ok(())
+    }
+
+    fn encode_decimal<T>(&mut self, value: &Value, to_bytes: fn(T) -> Vec<u8>) -> Result<()>
+    where
+        T: FromStr + Display,
+    {
+        let number = value
+            .as_str()
+            .and_then(|s| s.parse::<T>().ok().filter(|n| n.to_string() == s))
+            .ok_or_else(|| {
+                invalid(
+                    value,
+                    &format!("a decimal string of type {}", std::any::type_name::<T>()),
+                )
+            })?;
+        self.output.extend(to_bytes(number));
+        Ok(())
+    }
+
+    fn encode_len(&mut self, len: usize) -> Result<()> {
+        if len > crate::MAX_SEQUENCE_LENGTH {
+            return Err(Error::ExceededMaxLen(len));
+        }
+        self.output
+            .extend_from_slice(uleb128::encode(len as u64, &mut [0; uleb128::MAX_LEN]));
+        Ok(())
+    }
+
+    fn encode_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.encode_len(bytes.len())?;
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn encode_tuple(&mut self, value: &Value, formats: &[Format]) -> Result<()> {
+        match value.as_array() {
+            Some(values) if values.len() == formats.len() => values
+                .iter()
+                .zip(formats)
+                .try_for_each(|(value, format)| self.encode(value, format)),
+            _ => Err(invalid(
+                value,
+                &format!("an array of length {}", formats.len()),
+            )),
+        }
+    }
+
+    fn encode_struct(&mut self, value: &Value, fields: &[Named<Format>]) -> Result<()> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| invalid(value, "an object"))?;
+        if let Some(unknown) = object
+            .keys()
+            .find(|key| fields.iter().all(|field| field.name != **key))
+        {
+            return Err(Error::Custom(format!("unknown field `{}`", unknown)));
+        }
+        fields.iter().try_for_each(|field| {
+            let value = object
+                .get(&field.name)
+                .ok_or_else(|| Error::Custom(format!("missing field `{}`", field.name)))?;
+            self.encode(value, &field.value)
+        })
+    }
+
+    fn encode_map(&mut self, value: &Value, key_format: &Format, format: &Format) -> Result<()> {
+        let entries: Vec<(Value, &Value)> = match (key_format, value) {
+            (Format::Str, Value::Object(object)) => object
+                .iter()
+                .map(|(key, value)| (Value::String(key.clone()), value))
+                .collect(),
+            (Format::Str, _) => return Err(invalid(value, "an object")),
+            (_, Value::Array(entries)) => entries
+                .iter()
+                .map(|entry| match entry.as_array().map(Vec::as_slice) {
+                    Some([key, value]) => Ok((key.clone(), value)),
+                    _ => Err(invalid(entry, "a [key, value] array")),
+                })
+                .collect::<Result<_>>()?,
+            _ => return Err(invalid(value, "an array of [key, value] arrays")),
+        };
+        let mut encoded = entries
+            .iter()
+            .map(|(key, value)| Ok((self.encode_nested(key, key_format)?, *value)))
+            .collect::<Result<Vec<_>>>()?;
+        encoded.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if encoded.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::NonCanonicalMap);
+        }
+        self.encode_len(encoded.len())?;
+        for (key, value) in encoded {
+            self.output.extend_from_slice(&key);
+            self.encode(value, format)?;
+        }
+        Ok(())
+    }
+
+    /// Encode `value` separately, with the current depth.
+    fn encode_nested(&mut self, value: &Value, format: &Format) -> Result<Vec<u8>> {
+        let output = std::mem::take(&mut self.output);
+        let result = self.encode(value, format);
+        let encoded = std::mem::replace(&mut self.output, output);
+        result.map(|()| encoded)
+    }
+}
+
+struct Decoder<'de, 'a> {
+    de: Deserializer<'de>,
+    registry: &'a Registry,
+    max_remaining_depth: usize,
+}
+
+impl<'de, 'a> Decoder<'de, 'a> {
+    // Recursive functions only dispatch, to keep their stack frames small.
+    fn decode(&mut self, format: &Format) -> Result<Value> {
+        match format {
+            Format::TypeName(name) => self.decode_container(name),
+            Format::Option(format) => self.decode_option(format),
+            Format::Seq(format) => self.decode_seq(format),
+            Format::Map { key, value } => self.decode_map(key, value),
+            Format::Tuple(formats) => self.decode_tuple(formats),
+            Format::TupleArray { content, size } => self.decode_array(content, *size),
+            _ => self.decode_primitive(format),
+        }
+    }
+
+    fn decode_primitive(&mut self, format: &Format) -> Result<Value> {
+        Ok(match format {
+            Format::Unit => Value::Null,
+            Format::Bool => Value::Bool(self.de.parse_bool()?),
+            Format::I8 => Value::from(i8::from_le_bytes(self.read()?)),
+            Format::I16 => Value::from(i16::from_le_bytes(self.read()?)),
+            Format::I32 => Value::from(i32::from_le_bytes(self.read()?)),
+            Format::U8 => Value::from(self.de.parse_u8()?),
+            Format::U16 => Value::from(u16::from_le_bytes(self.read()?)),
+            Format::U32 => Value::from(u32::from_le_bytes(self.read()?)),
+            Format::I64 => Value::String(i64::from_le_bytes(self.read()?).to_string()),
+            Format::I128 => Value::String(i128::from_le_bytes(self.read()?).to_string()),
+            Format::U64 => Value::String(u64::from_le_bytes(self.read()?).to_string()),
+            Format::U128 => Value::String(u128::from_le_bytes(self.read()?).to_string()),
+            Format::Str => Value::String(self.de.parse_string()?.to_string()),
+            Format::Bytes => encode_hex(self.de.parse_bytes()?),
+            _ => return Err(unsupported(format)),
+        })
+    }
+
+    fn decode_option(&mut self, format: &Format) -> Result<Value> {
+        check_option_content(format)?;
+        match self.de.parse_u8()? {
+            0 => Ok(Value::Null),
+            1 => self.decode(format),
+            _ => Err(Error::ExpectedOption),
+        }
+    }
+
+    fn decode_seq(&mut self, format: &Format) -> Result<Value> {
+        if *format == Format::U8 {
+            return self.decode_primitive(&Format::Bytes);
+        }
+        let len = self.de.parse_length()?;
+        (0..len).map(|_| self.decode(format)).collect()
+    }
+
+    fn decode_array(&mut self, content: &Format, size: usize) -> Result<Value> {
+        if *content == Format::U8 {
+            let mut bytes = vec![0; size];
+            self.de.fill_slice(&mut bytes)?;
+            return Ok(encode_hex(&bytes));
+        }
+        (0..size).map(|_| self.decode(content)).collect()
+    }
+
+    fn decode_container(&mut self, name: &str) -> Result<Value> {
+        let registry = self.registry;
+        enter_container(&mut self.max_remaining_depth, name)?;
+        let value = match container(registry, name)? {
+            ContainerFormat::NewTypeStruct(_) if name == ULEB32_NAME => {
+                Value::from(self.de.parse_u32_from_uleb128()?)
+            }
+            ContainerFormat::NewTypeStruct(_) if name == ULEB64_NAME => {
+                Value::String(self.de.parse_u64_from_uleb128()?.to_string())
+            }
+            ContainerFormat::UnitStruct => Value::Null,
+            ContainerFormat::NewTypeStruct(format) => self.decode(format)?,
+            ContainerFormat::TupleStruct(formats) => self.decode_tuple(formats)?,
+            ContainerFormat::Struct(fields) => self.decode_struct(fields)?,
+            ContainerFormat::Enum(variants) => self.decode_variant(variants)?,
+        };
+        self.max_remaining_depth += 1;
+        Ok(value)
+    }
+
+    fn decode_variant(&mut self, variants: &BTreeMap<u32, Named<VariantFormat>>) -> Result<Value> {
+        let index = self.de.parse_u32_from_uleb128()?;
+        let variant = variants
+            .get(&index)
+            .ok_or_else(|| unknown_variant_index(index, variants.len()))?;
+        let payload = match &variant.value {
+            VariantFormat::Unit => Value::Null,
+            VariantFormat::NewType(format) => self.decode(format)?,
+            VariantFormat::Tuple(formats) => self.decode_tuple(formats)?,
+            VariantFormat::Struct(fields) => self.decode_struct(fields)?,
+            VariantFormat::Variable(_) => return Err(unknown_variant_format(variant)),
+        };
+        let mut object = Map::new();
+        object.insert(variant.name.clone(), payload);
+        Ok(Value::Object(object))
+    }
+
+    fn read<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.de.fill_slice(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn decode_tuple(&mut self, formats: &[Format]) -> Result<Value> {
+        Ok(Value::Array(
+            formats
+                .iter()
+                .map(|format| self.decode(format))
+                .collect::<Result<_>>()?,
+        ))
+    }
+
+    fn decode_struct(&mut self, fields: &[Named<Format>]) -> Result<Value> {
+        let mut object = Map::new();
+        for field in fields {
+            object.insert(field.name.clone(), self.decode(&field.value)?);
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn decode_map(&mut self, key: &Format, value: &Format) -> Result<Value> {
+        let len = self.de.parse_length()?;
+        let mut previous_key: Option<&'de [u8]> = None;
+        let mut entries = Vec::new();
+        for _ in 0..len {
+            let start = self.de.input;
+            let key = self.decode(key)?;
+            let key_bytes = &start[..start.len() - self.de.input.len()];
+            if let Some(previous_key) = previous_key {
+                if key_bytes <= previous_key {
+                    return Err(Error::NonCanonicalMap);
+                }
+            }
+            previous_key = Some(key_bytes);
+            entries.push((key, self.decode(value)?));
+        }
+        Ok(match key {
+            Format::Str => Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| match key {
+                        Value::String(key) => (key, value),
+                        _ => unreachable!("strings are decoded as JSON strings"),
+                    })
+                    .collect(),
+            ),
+            _ => Value::Array(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| Value::Array(vec![key, value]))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> Value {
+    Value::String(hex::encode(bytes))
+}
+
+fn decode_hex(value: &Value) -> Result<Vec<u8>> {
+    value
+        .as_str()
+        .and_then(hex::decode)
+        .ok_or_else(|| invalid(value, "a hexadecimal string"))
+}
@@ -1,7 +1,10 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-#![forbid(unsafe_code)]
+// The C ABI of the `ffi` module and the glue code generated for the `wasm` module are the
+// only places where unsafe code is allowed.
+#![cfg_attr(not(any(feature = "ffi", feature = "wasm")), forbid(unsafe_code))]
+#![cfg_attr(any(feature = "ffi", feature = "wasm"), deny(unsafe_code))]
 
 //! # Binary Canonical Serialization (BCS)
 //!
@@ -12,7 +15,7 @@
 //! * provide good performance and concise (binary) representations;
 //! * support a rich set of data types commonly used in Rust;
 //! * enforce canonical serialization, meaning that every value of a given type should have
-//! a single valid representation.
+//!   a single valid representation.
 //!
 //! BCS also aims to mitigate the consequence of malicious inputs by enforcing well-defined limits
 //! on large or nested containers during (de)serialization.
@@ -25,6 +28,8 @@
 //!
 //! BCS is also available in other programming languages, thanks to the separate project [serde-reflection](https://github.com/novifinancial/serde-reflection).
 //!
+//! The minimum supported Rust version is 1.74.
+//!
 //! ## Application to Cryptography
 //!
 //! The BCS format guarantees canonical serialization, meaning that for any given data type, there
@@ -44,7 +49,7 @@
 //! applications must carefully plan in advance for adhoc extension points:
 //! * Enums may be used for explicit versioning and backward compatibility (e.g. extensible query interfaces).
 //! * In some cases, data fields of type `Vec<u8>` may also be added to allow (future) unknown payloads
-//! in serialized form.
+//!   in serialized form.
 //!
 //! ## Detailed Specifications
 //!
@@ -303,10 +308,37 @@
 //! # Ok(())}
 //! ```
 
+pub mod codec;
+#[cfg(feature = "reflection")]
+pub mod codegen;
+#[cfg(feature = "reflection")]
+pub mod compat;
 mod de;
+pub mod envelope;
 mod error;
+#[cfg(feature = "ffi")]
+#[allow(unsafe_code)]
+pub mod ffi;
+#[cfg(feature = "reflection")]
+mod fingerprint;
+pub mod fixed_bytes;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+mod hex;
+#[cfg(feature = "json")]
+pub mod json;
+mod marker;
+pub mod migration;
 mod ser;
+mod size;
 pub mod test_helpers;
+#[cfg(feature = "reflection")]
+pub mod trace;
+mod u256;
+mod uleb128;
+#[cfg(feature = "wasm")]
+#[allow(unsafe_code)]
+pub mod wasm;
 
 /// Variable length sequences in BCS are limited to max length of 2^31 - 1.
 pub const MAX_SEQUENCE_LENGTH: usize = (1 << 31) - 1;
@@ -314,6 +346,35 @@ pub const MAX_SEQUENCE_LENGTH: usize = (1 << 31) - 1;
 /// Maximal allowed depth of BCS data, counting only structs and enums.
 pub const MAX_CONTAINER_DEPTH: usize = 500;
 
+pub use codec::{BcsDeserialize, BcsSerialize};
 pub use de::{from_bytes, from_bytes_seed};
-pub use error::{Error, Result};
-pub use ser::{is_human_readable, serialize_into, serialized_size, to_bytes};
+pub use error::{Error, ErrorCode, IoError, Result};
+#[cfg(feature = "reflection")]
+pub use fingerprint::{fingerprint, Fingerprint};
+pub use fixed_bytes::FixedBytes;
+pub use marker::BcsCompatible;
+pub use ser::{
+    is_human_readable, serialize_into, serialize_into_slice, to_bytes, to_bytes_into,
+    to_bytes_presized, to_bytes_with_capacity,
+};
+pub use size::{serialized_size, BcsSize};
+pub use u256::U256;
+pub use uleb128::{Uleb32, Uleb64};
+
+#[cfg(feature = "derive")]
+pub use bcs_derive::{BcsCompatible, BcsDeserialize, BcsSerialize, BcsSize};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::{
+        codec::{variant_tag, DeserializesPinnedTags, SerializesPinnedTags},
+        marker::assert_bcs_compatible,
+        size::{add_fixed_sizes, uleb128_size},
+    };
+    pub use serde;
+
+    /// The hexadecimal encoding of bytes shared with the Python bindings.
+    pub mod hex {
+        pub use crate::hex::{decode, encode};
+    }
+}
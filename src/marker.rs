@@ -0,0 +1,137 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{FixedBytes, Uleb32, Uleb64, U256};
+use std::{
+    borrow::{Cow, ToOwned},
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque},
+    marker::PhantomData,
+    rc::Rc,
+    sync::Arc,
+};
+
+/// A type whose values can be serialized in BCS, provided that its `Serialize`
+/// implementation follows its definition.
+///
+/// Floats and `char` are rejected by the serializer only at runtime, with
+/// `Error::NotSupported`. This marker trait is implemented for the primitive types and
+/// std containers that BCS supports, and can be derived with the feature `derive` for
+/// structs and enums whose fields are all `BcsCompatible`:
+///
+/// ```rust
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use bcs::BcsCompatible;
+/// use serde::Serialize;
+/// use std::collections::BTreeMap;
+///
+/// #[derive(BcsCompatible, Serialize)]
+/// struct Config {
+///     name: String,
+///     limits: BTreeMap<String, u64>,
+///     backup: Option<Box<Config>>,
+/// }
+///
+/// bcs::assert_bcs_compatible!(Config, Vec<(u8, Config)>);
+/// # }
+/// ```
+///
+/// A field of an unsupported type is then a compile-time error:
+///
+/// ```compile_fail
+/// use bcs::BcsCompatible;
+///
+/// #[derive(BcsCompatible)]
+/// struct Config {
+///     name: String,
+///     ratio: f32,
+/// }
+/// ```
+///
+/// ```compile_fail
+/// bcs::assert_bcs_compatible!(Vec<char>);
+/// ```
+pub trait BcsCompatible {
+    /// Checks the types of the fields, overridden by `#[derive(BcsCompatible)]`.
+    #[doc(hidden)]
+    fn __assert_fields_are_bcs_compatible() {}
+}
+
+/// Fail to compile unless `T` is `BcsCompatible`.
+#[doc(hidden)]
+pub fn assert_bcs_compatible<T: ?Sized + BcsCompatible>() {}
+
+/// Statically assert that the given types are [`BcsCompatible`].
+///
+/// ```rust
+/// bcs::assert_bcs_compatible!(u64, Option<String>, [u8; 32]);
+/// ```
+#[macro_export]
+macro_rules! assert_bcs_compatible {
+    ($($ty:ty),+ $(,)?) => {
+        const _: fn() = || {
+            $($crate::__private::assert_bcs_compatible::<$ty>();)+
+        };
+    };
+}
+
+macro_rules! impl_bcs_compatible {
+    ($($ty:ty),*) => {
+        $(impl BcsCompatible for $ty {})*
+    };
+}
+
+impl_bcs_compatible!(
+    (),
+    bool,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    U256,
+    Uleb32,
+    Uleb64,
+    str,
+    String
+);
+
+impl<T: ?Sized> BcsCompatible for PhantomData<T> {}
+impl<T: BcsCompatible> BcsCompatible for Option<T> {}
+impl<T: ?Sized + BcsCompatible> BcsCompatible for &T {}
+impl<T: ?Sized + BcsCompatible> BcsCompatible for &mut T {}
+impl<T: ?Sized + BcsCompatible> BcsCompatible for Box<T> {}
+impl<T: ?Sized + BcsCompatible> BcsCompatible for Rc<T> {}
+impl<T: ?Sized + BcsCompatible> BcsCompatible for Arc<T> {}
+impl<B: ?Sized + ToOwned + BcsCompatible> BcsCompatible for Cow<'_, B> {}
+impl<T: BcsCompatible, const N: usize> BcsCompatible for [T; N] {}
+impl<const N: usize> BcsCompatible for FixedBytes<N> {}
+impl<T: BcsCompatible> BcsCompatible for [T] {}
+impl<T: BcsCompatible> BcsCompatible for Vec<T> {}
+impl<T: BcsCompatible> BcsCompatible for VecDeque<T> {}
+impl<T: BcsCompatible> BcsCompatible for LinkedList<T> {}
+impl<T: BcsCompatible> BcsCompatible for BinaryHeap<T> {}
+impl<T: BcsCompatible> BcsCompatible for BTreeSet<T> {}
+impl<T: BcsCompatible, S> BcsCompatible for HashSet<T, S> {}
+impl<K: BcsCompatible, V: BcsCompatible> BcsCompatible for BTreeMap<K, V> {}
+impl<K: BcsCompatible, V: BcsCompatible, S> BcsCompatible for HashMap<K, V, S> {}
+
+macro_rules! impl_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: BcsCompatible),+> BcsCompatible for ($($name,)+) {}
+    };
+}
+
+impl_tuple!(T0);
+impl_tuple!(T0 T1);
+impl_tuple!(T0 T1 T2);
+impl_tuple!(T0 T1 T2 T3);
+impl_tuple!(T0 T1 T2 T3 T4);
+impl_tuple!(T0 T1 T2 T3 T4 T5);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7);
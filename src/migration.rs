@@ -0,0 +1,161 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Decoding of persisted values written with previous versions of their type.
+//!
+//! Each version of a type implements [`Versioned`], naming the version it replaces and how
+//! to convert from it, which forms a chain `V1 -> V2 -> V3`. Values can then be decoded
+//! in two ways:
+//!
+//! * [`from_bytes_with_fallback`] tries the current layout, then each previous layout from
+//!   the newest to the oldest. This reads bytes written by plain [`crate::to_bytes`], as
+//!   long as the layouts cannot be mistaken for one another.
+//! * [`from_versioned_bytes`] reads the version from a prefix written by
+//!   [`to_versioned_bytes`] and decodes the matching layout directly.
+//!
+//! Either way, the value is converted up the chain to the current version, and the version
+//! that was read is returned along with it.
+//!
+//! ```rust
+//! use bcs::migration::{from_bytes_with_fallback, Initial, Versioned};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct ConfigV1 {
+//!     name: String,
+//! }
+//!
+//! impl Versioned for ConfigV1 {
+//!     const VERSION: u32 = 1;
+//!     type Previous = Initial;
+//!
+//!     fn migrate(previous: Initial) -> Self {
+//!         match previous {}
+//!     }
+//! }
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     retries: u8,
+//! }
+//!
+//! impl Versioned for Config {
+//!     const VERSION: u32 = 2;
+//!     type Previous = ConfigV1;
+//!
+//!     fn migrate(previous: ConfigV1) -> Self {
+//!         Config { name: previous.name, retries: 3 }
+//!     }
+//! }
+//!
+//! let bytes = bcs::to_bytes(&ConfigV1 { name: "a".to_string() }).unwrap();
+//! let (config, version) = from_bytes_with_fallback::<Config>(&bytes).unwrap();
+//! assert_eq!(config, Config { name: "a".to_string(), retries: 3 });
+//! assert_eq!(version, 1);
+//! ```
+
+use crate::{de::Deserializer, ser::Serializer, Error, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// A version of a persisted type, which can be converted from the previous version.
+pub trait Versioned: DeserializeOwned {
+    /// The version of the layout of this type. Versions increase along the chain.
+    const VERSION: u32;
+
+    /// The version that this type replaces, or [`Initial`] for the first version.
+    type Previous: Versioned;
+
+    /// Convert a value of the previous version.
+    fn migrate(previous: Self::Previous) -> Self;
+
+    /// Decode the layout of this version or, failing that, of the previous versions.
+    #[doc(hidden)]
+    fn __decode_with_fallback(bytes: &[u8]) -> Result<(Self, u32)> {
+        match crate::from_bytes(bytes) {
+            Ok(value) => Ok((value, Self::VERSION)),
+            Err(error) => match Self::Previous::__decode_with_fallback(bytes) {
+                Ok((previous, version)) => Ok((Self::migrate(previous), version)),
+                // Report why the current layout does not match.
+                Err(_) => Err(error),
+            },
+        }
+    }
+
+    /// Decode the layout of the given version of this type.
+    #[doc(hidden)]
+    fn __decode_version(version: u32, bytes: &[u8]) -> Result<Self> {
+        if version == Self::VERSION {
+            crate::from_bytes(bytes)
+        } else if version < Self::VERSION {
+            Self::Previous::__decode_version(version, bytes).map(Self::migrate)
+        } else {
+            Err(Error::UnsupportedVersion(version))
+        }
+    }
+}
+
+/// Ends the chain of versions: the `Previous` version of the first version of a type.
+///
+/// No value of this type exists, so `migrate` can be implemented with `match previous {}`.
+#[derive(Debug, Deserialize)]
+pub enum Initial {}
+
+impl Versioned for Initial {
+    const VERSION: u32 = 0;
+    type Previous = Initial;
+
+    fn migrate(previous: Initial) -> Self {
+        previous
+    }
+
+    fn __decode_with_fallback(_bytes: &[u8]) -> Result<(Self, u32)> {
+        Err(Error::UnsupportedVersion(0))
+    }
+
+    fn __decode_version(version: u32, _bytes: &[u8]) -> Result<Self> {
+        Err(Error::UnsupportedVersion(version))
+    }
+}
+
+/// Deserialize a value of type `T`, falling back to the previous versions of `T` if the
+/// bytes do not match its current layout. Returns the value, converted to the current
+/// version, together with the version that was read.
+///
+/// Layouts are tried from the newest to the oldest. This is only reliable if the bytes of a
+/// version cannot also be read as a newer version, e.g. when a field was appended to a
+/// struct. Otherwise, use [`to_versioned_bytes`] and [`from_versioned_bytes`].
+///
+/// If no layout matches, the error is the one obtained with the current layout.
+pub fn from_bytes_with_fallback<T>(bytes: &[u8]) -> Result<(T, u32)>
+where
+    T: Versioned,
+{
+    T::__decode_with_fallback(bytes)
+}
+
+/// Serialize `value` after its version, encoded in ULEB128 like an enum variant index.
+pub fn to_versioned_bytes<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Versioned + Serialize,
+{
+    let mut output = Vec::new();
+    Serializer::new(&mut output, crate::MAX_CONTAINER_DEPTH).output_u32_as_uleb128(T::VERSION)?;
+    crate::to_bytes_into(&mut output, value)?;
+    Ok(output)
+}
+
+/// Deserialize a value written by [`to_versioned_bytes`] with the current or a previous
+/// version of `T`. Returns the value, converted to the current version, together with the
+/// version that was read.
+///
+/// Fails with `Error::UnsupportedVersion` if the version is not in the chain of `T`.
+pub fn from_versioned_bytes<T>(bytes: &[u8]) -> Result<(T, u32)>
+where
+    T: Versioned,
+{
+    let mut deserializer = Deserializer::new(bytes, crate::MAX_CONTAINER_DEPTH);
+    let version = deserializer.parse_u32_from_uleb128()?;
+    let value = T::__decode_version(version, deserializer.input)?;
+    Ok((value, version))
+}
@@ -1,7 +1,10 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, Result},
+    uleb128,
+};
 use serde::{ser, Serialize};
 
 /// Serialize the given data structure as a `Vec<u8>` of BCS.
@@ -51,44 +54,144 @@ where
     T: ?Sized + Serialize,
 {
     let mut output = Vec::new();
-    serialize_into(&mut output, value)?;
+    serialize_into_output(&mut output, value)?;
     Ok(output)
 }
 
+/// Same as `to_bytes` but reserve `capacity` bytes up front, e.g. when the size of the
+/// output is approximately known.
+pub fn to_bytes_with_capacity<T>(value: &T, capacity: usize) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = Vec::with_capacity(capacity);
+    serialize_into_output(&mut output, value)?;
+    Ok(output)
+}
+
+/// Same as `to_bytes` but compute the size of the output first, so that it is allocated
+/// exactly once. This is faster for large values that are cheap to traverse twice.
+pub fn to_bytes_presized<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    to_bytes_with_capacity(value, crate::serialized_size(value)?)
+}
+
+/// Same as `to_bytes` but append the serialized bytes to `output`. This allows reusing
+/// the same buffer for many values, e.g. by clearing it between calls.
+///
+/// In case of error, `output` is truncated back to its original length.
+///
+/// ```
+/// let mut buffer = Vec::with_capacity(1024);
+/// for i in 0u64..10 {
+///     buffer.clear();
+///     bcs::to_bytes_into(&mut buffer, &(i, "hello")).unwrap();
+///     assert_eq!(bcs::from_bytes::<(u64, String)>(&buffer).unwrap(), (i, "hello".to_string()));
+/// }
+/// ```
+pub fn to_bytes_into<T>(output: &mut Vec<u8>, value: &T) -> Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    let len = output.len();
+    let result = serialize_into_output(output, value);
+    if result.is_err() {
+        output.truncate(len);
+    }
+    result
+}
+
 /// Same as `to_bytes` but write directly into an `std::io::Write` object.
 pub fn serialize_into<W, T>(write: &mut W, value: &T) -> Result<()>
 where
     W: ?Sized + std::io::Write,
     T: ?Sized + Serialize,
 {
-    let serializer = Serializer::new(write, crate::MAX_CONTAINER_DEPTH);
-    value.serialize(serializer)
+    serialize_into_output(&mut WriteOutput(write), value)
 }
 
-struct WriteCounter(usize);
+/// Same as `to_bytes` but write into the beginning of `buffer` and return the number of
+/// bytes written. This does not go through `std::io::Write`.
+///
+/// Fails with `Error::BufferTooSmall` if the serialized bytes do not fit into `buffer`, in
+/// which case the content of `buffer` is unspecified. Note that serializing maps still
+/// requires allocating memory, in order to sort their entries.
+///
+/// ```
+/// let mut buffer = [0u8; 8];
+/// assert_eq!(bcs::serialize_into_slice(&mut buffer, &(1u16, true)), Ok(3));
+/// assert_eq!(&buffer[..3], [1, 0, 1]);
+/// assert_eq!(
+///     bcs::serialize_into_slice(&mut buffer, &[0u8; 16]),
+///     Err(bcs::Error::BufferTooSmall)
+/// );
+/// ```
+pub fn serialize_into_slice<T>(buffer: &mut [u8], value: &T) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = SliceOutput {
+        buffer,
+        position: 0,
+    };
+    serialize_into_output(&mut output, value)?;
+    Ok(output.position)
+}
 
-impl std::io::Write for WriteCounter {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let len = buf.len();
-        self.0 = self.0.checked_add(len).ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::Other, "WriteCounter reached max value")
-        })?;
-        Ok(len)
-    }
+/// Same as `serialize_into` but for any `Output`.
+pub(crate) fn serialize_into_output<O, T>(output: &mut O, value: &T) -> Result<()>
+where
+    O: ?Sized + Output,
+    T: ?Sized + Serialize,
+{
+    let serializer = Serializer::new(output, crate::MAX_CONTAINER_DEPTH);
+    value.serialize(serializer)
+}
+
+/// Destination of the bytes emitted by the `Serializer`.
+pub(crate) trait Output {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()>;
+}
 
-    fn flush(&mut self) -> std::io::Result<()> {
+impl Output for Vec<u8> {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
         Ok(())
     }
 }
 
-/// Same as `to_bytes` but only return the size of the serialized bytes.
-pub fn serialized_size<T>(value: &T) -> Result<usize>
+/// An `Output` writing into an `std::io::Write` object. Only `serialize_into` goes through
+/// `std::io`: serializing into a `Vec<u8>` or a slice does not.
+struct WriteOutput<'a, W: ?Sized>(&'a mut W);
+
+impl<W> Output for WriteOutput<'_, W>
 where
-    T: ?Sized + Serialize,
+    W: ?Sized + std::io::Write,
 {
-    let mut counter = WriteCounter(0);
-    serialize_into(&mut counter, value)?;
-    Ok(counter.0)
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        self.0.write_all(bytes)?;
+        Ok(())
+    }
+}
+
+/// An `Output` writing into a fixed-size buffer.
+struct SliceOutput<'a> {
+    buffer: &'a mut [u8],
+    position: usize,
+}
+
+impl Output for SliceOutput<'_> {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.position + bytes.len();
+        self.buffer
+            .get_mut(self.position..end)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.position = end;
+        Ok(())
+    }
 }
 
 pub fn is_human_readable() -> bool {
@@ -98,32 +201,30 @@ pub fn is_human_readable() -> bool {
 }
 
 /// Serialization implementation for BCS
-struct Serializer<'a, W: ?Sized> {
+pub(crate) struct Serializer<'a, W: ?Sized> {
     output: &'a mut W,
     max_remaining_depth: usize,
 }
 
 impl<'a, W> Serializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     /// Creates a new `Serializer` which will emit BCS.
-    fn new(output: &'a mut W, max_remaining_depth: usize) -> Self {
+    pub(crate) fn new(output: &'a mut W, max_remaining_depth: usize) -> Self {
         Self {
             output,
             max_remaining_depth,
         }
     }
 
-    fn output_u32_as_uleb128(&mut self, mut value: u32) -> Result<()> {
-        while value >= 0x80 {
-            // Write 7 (lowest) bits of data and set the 8th bit to 1.
-            let byte = (value & 0x7f) as u8;
-            self.output.write_all(&[byte | 0x80])?;
-            value >>= 7;
-        }
-        // Write the remaining bits of data and set the highest bit to 0.
-        self.output.write_all(&[value as u8])?;
+    pub(crate) fn output_u32_as_uleb128(&mut self, value: u32) -> Result<()> {
+        self.output_uleb128(value.into())
+    }
+
+    fn output_uleb128(&mut self, value: u64) -> Result<()> {
+        let mut buffer = [0; uleb128::MAX_LEN];
+        self.output.write_all(uleb128::encode(value, &mut buffer))?;
         Ok(())
     }
 
@@ -150,7 +251,7 @@ where
 
 impl<'a, W> ser::Serializer for Serializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -273,6 +374,9 @@ where
         T: ?Sized + Serialize,
     {
         self.enter_named_container(name)?;
+        if let Some(value) = uleb128::wrapped_integer(name, value)? {
+            return self.output_uleb128(value);
+        }
         value.serialize(self)
     }
 
@@ -363,7 +467,7 @@ where
 
 impl<'a, W> ser::SerializeSeq for Serializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -382,7 +486,7 @@ where
 
 impl<'a, W> ser::SerializeTuple for Serializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -401,7 +505,7 @@ where
 
 impl<'a, W> ser::SerializeTupleStruct for Serializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -420,7 +524,7 @@ where
 
 impl<'a, W> ser::SerializeTupleVariant for Serializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -438,7 +542,7 @@ where
 }
 
 #[doc(hidden)]
-struct MapSerializer<'a, W: ?Sized> {
+pub(crate) struct MapSerializer<'a, W: ?Sized> {
     serializer: Serializer<'a, W>,
     entries: Vec<(Vec<u8>, Vec<u8>)>,
     next_key: Option<Vec<u8>>,
@@ -456,7 +560,7 @@ impl<'a, W: ?Sized> MapSerializer<'a, W> {
 
 impl<'a, W> ser::SerializeMap for MapSerializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -517,7 +621,7 @@ where
 
 impl<'a, W> ser::SerializeStruct for Serializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -536,7 +640,7 @@ where
 
 impl<'a, W> ser::SerializeStructVariant for Serializer<'a, W>
 where
-    W: ?Sized + std::io::Write,
+    W: ?Sized + Output,
 {
     type Ok = ();
     type Error = Error;
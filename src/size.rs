@@ -0,0 +1,687 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    error::{Error, Result},
+    ser::Serializer,
+    uleb128, FixedBytes, Uleb32, Uleb64, U256,
+};
+use serde::{ser, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Same as `to_bytes` but only return the size of the serialized bytes.
+///
+/// The size is computed without producing the serialized bytes. Only the keys of maps
+/// are serialized, in order to detect duplicates: the memory used is therefore
+/// proportional to the size of the keys of the largest map in `value`.
+pub fn serialized_size<T>(value: &T) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    let mut size = 0;
+    value.serialize(SizeSerializer::new(&mut size, crate::MAX_CONTAINER_DEPTH))?;
+    Ok(size)
+}
+
+/// Types whose BCS size can be computed directly, without going through `Serialize`.
+///
+/// Implementations must agree with `serialized_size`, assuming that serialization
+/// succeeds. Implementations are provided for the types of the standard library supported
+/// by BCS and may be derived for structs and enums with `#[derive(BcsSize)]` (feature
+/// `derive`):
+///
+/// ```rust
+/// # #[cfg(feature = "derive")] {
+/// use bcs::BcsSize;
+///
+/// #[derive(BcsSize, serde::Serialize)]
+/// struct Header {
+///     version: u8,
+///     height: u64,
+///     parent: [u8; 32],
+/// }
+///
+/// #[derive(BcsSize, serde::Serialize)]
+/// struct Block {
+///     header: Header,
+///     transactions: Vec<Vec<u8>>,
+/// }
+///
+/// // The size of fixed-layout types is known at compile time.
+/// assert_eq!(Header::FIXED_SIZE, Some(41));
+/// assert_eq!(Block::FIXED_SIZE, None);
+///
+/// let block = Block {
+///     header: Header { version: 1, height: 2, parent: [0; 32] },
+///     transactions: vec![vec![0; 200], vec![]],
+/// };
+/// assert_eq!(block.bcs_size(), bcs::serialized_size(&block).unwrap());
+/// # }
+/// ```
+pub trait BcsSize {
+    /// The size of every value of the type, if they all have the same size.
+    const FIXED_SIZE: Option<usize> = None;
+
+    /// The number of bytes of the BCS serialization of `self`.
+    fn bcs_size(&self) -> usize;
+}
+
+/// Number of bytes of the ULEB128 encoding of `value`.
+#[doc(hidden)]
+pub const fn uleb128_size(value: u32) -> usize {
+    uleb128::encoded_len(value as u64)
+}
+
+/// Size of the fixed-size prefix `a` followed by `b`.
+#[doc(hidden)]
+pub const fn add_fixed_sizes(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        _ => None,
+    }
+}
+
+/// Size of a sequence of `len` elements, preceded by its length.
+fn seq_size<'a, T, I>(len: usize, elements: I) -> usize
+where
+    T: BcsSize + 'a + ?Sized,
+    I: IntoIterator<Item = &'a T>,
+{
+    let elements_size = match T::FIXED_SIZE {
+        Some(size) => size * len,
+        None => elements.into_iter().map(BcsSize::bcs_size).sum(),
+    };
+    uleb128_size(len as u32) + elements_size
+}
+
+macro_rules! impl_fixed_size {
+    ($($t:ty => $size:expr,)*) => {
+        $(
+            impl BcsSize for $t {
+                const FIXED_SIZE: Option<usize> = Some($size);
+
+                fn bcs_size(&self) -> usize {
+                    $size
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_size! {
+    () => 0,
+    bool => 1,
+    i8 => 1,
+    i16 => 2,
+    i32 => 4,
+    i64 => 8,
+    i128 => 16,
+    u8 => 1,
+    u16 => 2,
+    u32 => 4,
+    u64 => 8,
+    u128 => 16,
+    U256 => 32,
+}
+
+impl<const N: usize> BcsSize for FixedBytes<N> {
+    const FIXED_SIZE: Option<usize> = Some(N);
+
+    fn bcs_size(&self) -> usize {
+        N
+    }
+}
+
+impl BcsSize for Uleb32 {
+    fn bcs_size(&self) -> usize {
+        uleb128::encoded_len(self.0.into())
+    }
+}
+
+impl BcsSize for Uleb64 {
+    fn bcs_size(&self) -> usize {
+        uleb128::encoded_len(self.0)
+    }
+}
+
+impl BcsSize for str {
+    fn bcs_size(&self) -> usize {
+        uleb128_size(self.len() as u32) + self.len()
+    }
+}
+
+impl BcsSize for String {
+    fn bcs_size(&self) -> usize {
+        self.as_str().bcs_size()
+    }
+}
+
+impl<T: BcsSize> BcsSize for Option<T> {
+    fn bcs_size(&self) -> usize {
+        1 + self.as_ref().map_or(0, BcsSize::bcs_size)
+    }
+}
+
+impl<T: BcsSize + ?Sized> BcsSize for &T {
+    const FIXED_SIZE: Option<usize> = T::FIXED_SIZE;
+
+    fn bcs_size(&self) -> usize {
+        (**self).bcs_size()
+    }
+}
+
+impl<T: BcsSize + ?Sized> BcsSize for Box<T> {
+    const FIXED_SIZE: Option<usize> = T::FIXED_SIZE;
+
+    fn bcs_size(&self) -> usize {
+        (**self).bcs_size()
+    }
+}
+
+impl<T: BcsSize, const N: usize> BcsSize for [T; N] {
+    const FIXED_SIZE: Option<usize> = match T::FIXED_SIZE {
+        Some(size) => Some(size * N),
+        None => None,
+    };
+
+    fn bcs_size(&self) -> usize {
+        match Self::FIXED_SIZE {
+            Some(size) => size,
+            None => self.iter().map(BcsSize::bcs_size).sum(),
+        }
+    }
+}
+
+impl<T: BcsSize> BcsSize for [T] {
+    fn bcs_size(&self) -> usize {
+        seq_size(self.len(), self)
+    }
+}
+
+impl<T: BcsSize> BcsSize for Vec<T> {
+    fn bcs_size(&self) -> usize {
+        seq_size(self.len(), self)
+    }
+}
+
+impl<T: BcsSize, S> BcsSize for HashSet<T, S> {
+    fn bcs_size(&self) -> usize {
+        seq_size(self.len(), self)
+    }
+}
+
+impl<T: BcsSize> BcsSize for BTreeSet<T> {
+    fn bcs_size(&self) -> usize {
+        seq_size(self.len(), self)
+    }
+}
+
+fn map_size<'a, K, V, I>(len: usize, entries: I) -> usize
+where
+    K: BcsSize + 'a,
+    V: BcsSize + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    let entries_size = match add_fixed_sizes(K::FIXED_SIZE, V::FIXED_SIZE) {
+        Some(size) => size * len,
+        None => entries
+            .into_iter()
+            .map(|(k, v)| k.bcs_size() + v.bcs_size())
+            .sum(),
+    };
+    uleb128_size(len as u32) + entries_size
+}
+
+impl<K: BcsSize, V: BcsSize, S> BcsSize for HashMap<K, V, S> {
+    fn bcs_size(&self) -> usize {
+        map_size(self.len(), self)
+    }
+}
+
+impl<K: BcsSize, V: BcsSize> BcsSize for BTreeMap<K, V> {
+    fn bcs_size(&self) -> usize {
+        map_size(self.len(), self)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: BcsSize),+> BcsSize for ($($name,)+) {
+            const FIXED_SIZE: Option<usize> = {
+                let size = Some(0);
+                $(let size = add_fixed_sizes(size, $name::FIXED_SIZE);)+
+                size
+            };
+
+            #[allow(non_snake_case)]
+            fn bcs_size(&self) -> usize {
+                if let Some(size) = Self::FIXED_SIZE {
+                    return size;
+                }
+                let ($($name,)+) = self;
+                0 $(+ $name.bcs_size())+
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A B);
+impl_tuple!(A B C);
+impl_tuple!(A B C D);
+impl_tuple!(A B C D E);
+impl_tuple!(A B C D E F);
+impl_tuple!(A B C D E F G);
+impl_tuple!(A B C D E F G H);
+
+/// Computes the size of the BCS serialization, following the same rules as `Serializer`.
+struct SizeSerializer<'a> {
+    size: &'a mut usize,
+    max_remaining_depth: usize,
+}
+
+impl<'a> SizeSerializer<'a> {
+    fn new(size: &'a mut usize, max_remaining_depth: usize) -> Self {
+        Self {
+            size,
+            max_remaining_depth,
+        }
+    }
+
+    fn add(&mut self, len: usize) -> Result<()> {
+        *self.size = checked_size_add(*self.size, len)?;
+        Ok(())
+    }
+
+    fn add_seq_len(&mut self, len: usize) -> Result<()> {
+        if len > crate::MAX_SEQUENCE_LENGTH {
+            return Err(Error::ExceededMaxLen(len));
+        }
+        self.add(uleb128_size(len as u32))
+    }
+
+    fn enter_named_container(&mut self, name: &'static str) -> Result<()> {
+        if self.max_remaining_depth == 0 {
+            return Err(Error::ExceededContainerDepthLimit(name));
+        }
+        self.max_remaining_depth -= 1;
+        Ok(())
+    }
+
+    fn child(&mut self) -> SizeSerializer<'_> {
+        SizeSerializer::new(self.size, self.max_remaining_depth)
+    }
+}
+
+impl<'a> ser::Serializer for SizeSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = SizeMapSerializer<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(mut self, _v: bool) -> Result<()> {
+        self.add(1)
+    }
+
+    fn serialize_i8(mut self, _v: i8) -> Result<()> {
+        self.add(1)
+    }
+
+    fn serialize_i16(mut self, _v: i16) -> Result<()> {
+        self.add(2)
+    }
+
+    fn serialize_i32(mut self, _v: i32) -> Result<()> {
+        self.add(4)
+    }
+
+    fn serialize_i64(mut self, _v: i64) -> Result<()> {
+        self.add(8)
+    }
+
+    fn serialize_i128(mut self, _v: i128) -> Result<()> {
+        self.add(16)
+    }
+
+    fn serialize_u8(mut self, _v: u8) -> Result<()> {
+        self.add(1)
+    }
+
+    fn serialize_u16(mut self, _v: u16) -> Result<()> {
+        self.add(2)
+    }
+
+    fn serialize_u32(mut self, _v: u32) -> Result<()> {
+        self.add(4)
+    }
+
+    fn serialize_u64(mut self, _v: u64) -> Result<()> {
+        self.add(8)
+    }
+
+    fn serialize_u128(mut self, _v: u128) -> Result<()> {
+        self.add(16)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<()> {
+        Err(Error::NotSupported("serialize_f32"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<()> {
+        Err(Error::NotSupported("serialize_f64"))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        Err(Error::NotSupported("serialize_char"))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(mut self, v: &[u8]) -> Result<()> {
+        self.add_seq_len(v.len())?;
+        self.add(v.len())
+    }
+
+    fn serialize_none(mut self) -> Result<()> {
+        self.add(1)
+    }
+
+    fn serialize_some<T>(mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(mut self, name: &'static str) -> Result<()> {
+        self.enter_named_container(name)
+    }
+
+    fn serialize_unit_variant(
+        mut self,
+        name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.enter_named_container(name)?;
+        self.add(uleb128_size(variant_index))
+    }
+
+    fn serialize_newtype_struct<T>(mut self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_named_container(name)?;
+        if let Some(value) = uleb128::wrapped_integer(name, value)? {
+            return self.add(uleb128::encoded_len(value));
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        mut self,
+        name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_named_container(name)?;
+        self.add(uleb128_size(variant_index))?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(mut self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        let len = len.ok_or(Error::MissingLen)?;
+        self.add_seq_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        mut self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.enter_named_container(name)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        mut self,
+        name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.enter_named_container(name)?;
+        self.add(uleb128_size(variant_index))?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(SizeMapSerializer::new(self))
+    }
+
+    fn serialize_struct(
+        mut self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct> {
+        self.enter_named_container(name)?;
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        mut self,
+        name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.enter_named_container(name)?;
+        self.add(uleb128_size(variant_index))?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<'a> ser::SerializeSeq for SizeSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self.child())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for SizeSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self.child())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for SizeSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self.child())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for SizeSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self.child())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for SizeSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self.child())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for SizeSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self.child())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// `size + len`, failing instead of overflowing.
+fn checked_size_add(size: usize, len: usize) -> Result<usize> {
+    size.checked_add(len)
+        .ok_or_else(|| std::io::Error::other("serialized size overflows usize").into())
+}
+
+/// Sizes of keys and values add up regardless of the order of the entries, so only the
+/// serialized keys are kept, to skip duplicate entries like `MapSerializer` does. Unlike
+/// the rest of `serialized_size`, this allocates as many bytes as the keys of the map take
+/// in BCS.
+struct SizeMapSerializer<'a> {
+    serializer: SizeSerializer<'a>,
+    keys: HashSet<Vec<u8>>,
+    entries_size: usize,
+    /// Whether a key was serialized and, if so, whether it was a duplicate.
+    next_key_is_duplicate: Option<bool>,
+}
+
+impl<'a> SizeMapSerializer<'a> {
+    fn new(serializer: SizeSerializer<'a>) -> Self {
+        Self {
+            serializer,
+            keys: HashSet::new(),
+            entries_size: 0,
+            next_key_is_duplicate: None,
+        }
+    }
+}
+
+impl<'a> ser::SerializeMap for SizeMapSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.next_key_is_duplicate.is_some() {
+            return Err(Error::ExpectedMapValue);
+        }
+
+        let mut output = Vec::new();
+        key.serialize(Serializer::new(
+            &mut output,
+            self.serializer.max_remaining_depth,
+        ))?;
+        let len = output.len();
+        let is_duplicate = !self.keys.insert(output);
+        if !is_duplicate {
+            self.entries_size = checked_size_add(self.entries_size, len)?;
+        }
+        self.next_key_is_duplicate = Some(is_duplicate);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.next_key_is_duplicate.take() {
+            Some(is_duplicate) => {
+                let mut size = 0;
+                value.serialize(SizeSerializer::new(
+                    &mut size,
+                    self.serializer.max_remaining_depth,
+                ))?;
+                if !is_duplicate {
+                    self.entries_size = checked_size_add(self.entries_size, size)?;
+                }
+                Ok(())
+            }
+            None => Err(Error::ExpectedMapKey),
+        }
+    }
+
+    fn end(mut self) -> Result<()> {
+        if self.next_key_is_duplicate.is_some() {
+            return Err(Error::ExpectedMapValue);
+        }
+        self.serializer.add_seq_len(self.keys.len())?;
+        self.serializer.add(self.entries_size)
+    }
+}
@@ -1,6 +1,13 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::{hex, uleb128, Error, Result};
+use serde::{de::DeserializeOwned, ser, Serialize};
+use std::{
+    fmt::{self, Write as _},
+    path::{Path, PathBuf},
+};
+
 pub fn assert_canonical_encode_decode<T>(t: T)
 where
     T: serde::Serialize + serde::de::DeserializeOwned + std::fmt::Debug + PartialEq,
@@ -9,3 +16,641 @@ where
     let s: T = crate::from_bytes(&bytes).unwrap();
     assert_eq!(t, s);
 }
+
+/// Assert that `bytes` decode into a `T` which serializes back to exactly `bytes`.
+pub fn assert_canonical_bytes<T>(bytes: &[u8])
+where
+    T: Serialize + DeserializeOwned + fmt::Debug,
+{
+    let value: T = crate::from_bytes(bytes)
+        .unwrap_or_else(|e| panic!("failed to decode {}: {}", hex::encode(bytes), e));
+    let reencoded = crate::to_bytes(&value).unwrap();
+    assert_eq!(
+        hex::encode(bytes),
+        hex::encode(&reencoded),
+        "re-serializing {:?} does not give back the decoded bytes",
+        value
+    );
+}
+
+/// Assert that `serialized_size` agrees with the length of `to_bytes`.
+pub fn assert_serialized_size<T>(value: &T)
+where
+    T: ?Sized + Serialize + fmt::Debug,
+{
+    let bytes = crate::to_bytes(value).unwrap();
+    assert_eq!(
+        crate::serialized_size(value).unwrap(),
+        bytes.len(),
+        "wrong serialized size for {:?}",
+        value
+    );
+}
+
+/// Assert that every strict prefix of the encoding of `value` fails to decode with
+/// `Error::Eof`.
+pub fn assert_truncations_rejected<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + fmt::Debug,
+{
+    let bytes = crate::to_bytes(value).unwrap();
+    for len in 0..bytes.len() {
+        let result = crate::from_bytes::<T>(&bytes[..len]);
+        assert!(
+            matches!(result, Err(Error::Eof)),
+            "decoding the first {} of {} bytes of {:?} returned {:?} instead of `Error::Eof`",
+            len,
+            bytes.len(),
+            value,
+            result
+        );
+    }
+}
+
+/// Assert that appending a byte to the encoding of `value` fails to decode with
+/// `Error::RemainingInput`.
+pub fn assert_trailing_bytes_rejected<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + fmt::Debug,
+{
+    let bytes = crate::to_bytes(value).unwrap();
+    for extra in &[0x00, 0x01, 0x80, 0xff] {
+        let mut extended = bytes.clone();
+        extended.push(*extra);
+        let result = crate::from_bytes::<T>(&extended);
+        assert!(
+            matches!(result, Err(Error::RemainingInput)),
+            "decoding {:?} followed by {:#04x} returned {:?} instead of `Error::RemainingInput`",
+            value,
+            extra,
+            result
+        );
+    }
+}
+
+/// Assert that changing any length prefix in the encoding of `value` (sequences, strings,
+/// byte arrays, and maps) by one in either direction does not go unnoticed.
+///
+/// Depending on what follows a length prefix, a mutated input may still be valid: for
+/// instance, the pairs of vectors `([7, 1], [])` and `([7], [0])` are respectively encoded as
+/// `02 07 01 00` and `01 07 01 00`. Mutated inputs must therefore either fail to decode, or decode into another
+/// value whose canonical encoding is exactly the mutated input.
+///
+/// Length prefixes nested inside the keys or values of a map are not mutated.
+pub fn assert_length_prefix_mutations_rejected<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + fmt::Debug,
+{
+    let bytes = crate::to_bytes(value).unwrap();
+    let mut prefixes = LengthPrefixes::default();
+    value.serialize(&mut prefixes).unwrap();
+
+    for (offset, len) in prefixes.prefixes {
+        let prefix_len = uleb128::encoded_len(len as u64);
+        let mutations = [len.checked_add(1), len.checked_sub(1)];
+        for new_len in mutations.iter().flatten() {
+            let mut mutated = bytes[..offset].to_vec();
+            let mut buffer = [0; uleb128::MAX_LEN];
+            mutated.extend_from_slice(uleb128::encode(*new_len as u64, &mut buffer));
+            mutated.extend_from_slice(&bytes[offset + prefix_len..]);
+
+            if let Ok(other) = crate::from_bytes::<T>(&mutated) {
+                assert_eq!(
+                    hex::encode(&crate::to_bytes(&other).unwrap()),
+                    hex::encode(&mutated),
+                    "changing the length prefix at byte {} of {:?} from {} to {} was accepted \
+                     as the non-canonical encoding of {:?}",
+                    offset,
+                    value,
+                    len,
+                    new_len,
+                    other
+                );
+            }
+        }
+    }
+}
+
+/// Run all the assertions of this module on `value`.
+pub fn assert_canonical_properties<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + fmt::Debug + PartialEq,
+{
+    let bytes = crate::to_bytes(value).unwrap();
+    let decoded: T = crate::from_bytes(&bytes).unwrap();
+    assert_eq!(value, &decoded);
+    assert_canonical_bytes::<T>(&bytes);
+    assert_serialized_size(value);
+    assert_truncations_rejected(value);
+    assert_trailing_bytes_rejected(value);
+    assert_length_prefix_mutations_rejected(value);
+}
+
+/// Serializer walking a value again to find the length prefixes of its encoding, without
+/// writing the bytes.
+#[derive(Default)]
+struct LengthPrefixes {
+    /// Number of bytes of the encoding before the current value.
+    position: usize,
+    /// Offset and value of each length prefix, in order, except inside maps.
+    prefixes: Vec<(usize, usize)>,
+}
+
+impl LengthPrefixes {
+    fn skip<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.position += crate::serialized_size(value)?;
+        Ok(())
+    }
+
+    fn skip_variant_index(&mut self, variant_index: u32) -> Result<()> {
+        self.position += uleb128::encoded_len(variant_index.into());
+        Ok(())
+    }
+
+    fn record_len(&mut self, len: usize) -> Result<()> {
+        self.prefixes.push((self.position, len));
+        self.position += uleb128::encoded_len(len as u64);
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut LengthPrefixes {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = MapLengthPrefix<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.skip(&v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.record_len(v.len())?;
+        self.position += v.len();
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.position += 1;
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        self.position += 1;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.skip_variant_index(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        if let Some(value) = uleb128::wrapped_integer(name, value)? {
+            self.position += uleb128::encoded_len(value);
+            return Ok(());
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.skip_variant_index(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.record_len(len.ok_or(Error::MissingLen)?)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.skip_variant_index(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(MapLengthPrefix {
+            prefixes: self,
+            len: 0,
+            entries_size: 0,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.skip_variant_index(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut LengthPrefixes {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut LengthPrefixes {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut LengthPrefixes {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut LengthPrefixes {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut LengthPrefixes {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut LengthPrefixes {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Entries are sorted by their encoding, so only the length of a map is recorded.
+struct MapLengthPrefix<'a> {
+    prefixes: &'a mut LengthPrefixes,
+    len: usize,
+    entries_size: usize,
+}
+
+impl ser::SerializeMap for MapLengthPrefix<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.len += 1;
+        self.entries_size += crate::serialized_size(key)?;
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.entries_size += crate::serialized_size(value)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        self.prefixes.record_len(self.len)?;
+        self.prefixes.position += self.entries_size;
+        Ok(())
+    }
+}
+
+/// Environment variable that makes [`GoldenFile::check`] rewrite golden files instead of
+/// comparing against them, unless it is empty or `0`.
+pub const UPDATE_GOLDEN_ENV_VAR: &str = "BCS_UPDATE_GOLDEN";
+
+/// A checked-in file recording the BCS bytes of a set of named values.
+///
+/// Golden files protect against accidental changes of wire format: any drift of the
+/// encoding of a recorded value makes [`GoldenFile::check`] panic with an annotated diff.
+/// When a change is intended, run the tests again with `BCS_UPDATE_GOLDEN=1` to regenerate
+/// the files and review the result like any other change.
+///
+/// ```rust,no_run
+/// use bcs::test_helpers::GoldenFile;
+///
+/// let mut golden = GoldenFile::new("tests/golden/primitives.hex");
+/// golden.add("u16", &0x1234u16);
+/// golden.add("string", &"diem");
+/// golden.check();
+/// ```
+///
+/// Each entry is stored on its own line as `name: hex`. Relative paths are resolved from the
+/// current directory, which is the package root when running `cargo test`.
+pub struct GoldenFile {
+    path: PathBuf,
+    entries: Vec<GoldenEntry>,
+}
+
+struct GoldenEntry {
+    name: String,
+    bytes: Vec<u8>,
+    value: String,
+}
+
+impl GoldenFile {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Record the BCS encoding of `value` under `name`.
+    ///
+    /// Panics if `value` cannot be serialized or if `name` is already used.
+    pub fn add<T>(&mut self, name: &str, value: &T) -> &mut Self
+    where
+        T: ?Sized + Serialize + fmt::Debug,
+    {
+        assert!(
+            !name.is_empty() && !name.contains(':') && !name.contains('\n'),
+            "invalid golden entry name: {:?}",
+            name
+        );
+        assert!(
+            self.entries.iter().all(|e| e.name != name),
+            "duplicate golden entry name: {}",
+            name
+        );
+        let bytes = crate::to_bytes(value)
+            .unwrap_or_else(|e| panic!("failed to serialize golden entry `{}`: {}", name, e));
+        self.entries.push(GoldenEntry {
+            name: name.to_string(),
+            bytes,
+            value: format!("{:?}", value),
+        });
+        self
+    }
+
+    /// Compare the recorded entries with the golden file, or regenerate the file if the
+    /// environment variable `BCS_UPDATE_GOLDEN` is set to a value other than `0` or the
+    /// empty string.
+    pub fn check(&self) {
+        let update = std::env::var_os(UPDATE_GOLDEN_ENV_VAR)
+            .is_some_and(|value| !value.is_empty() && value != "0");
+        if update {
+            self.update();
+        } else {
+            self.verify();
+        }
+    }
+
+    /// Compare the recorded entries with the golden file and panic with an annotated diff
+    /// if they differ.
+    pub fn verify(&self) {
+        let content = std::fs::read_to_string(&self.path).unwrap_or_else(|e| {
+            panic!(
+                "failed to read golden file {} ({}); set {}=1 to create it",
+                self.path.display(),
+                e,
+                UPDATE_GOLDEN_ENV_VAR
+            )
+        });
+        let expected = parse_golden_file(&self.path, &content);
+
+        let mut report = String::new();
+        for entry in &self.entries {
+            match expected.iter().find(|(name, _)| name == &entry.name) {
+                None => {
+                    let _ = writeln!(report, "  entry `{}` is new:", entry.name);
+                    let _ = writeln!(report, "    value:    {}", entry.value);
+                    let _ = writeln!(report, "    actual:   {}", hex::encode(&entry.bytes));
+                }
+                Some((_, bytes)) if bytes != &entry.bytes => {
+                    let _ = writeln!(report, "  entry `{}` changed:", entry.name);
+                    let _ = writeln!(report, "    value:    {}", entry.value);
+                    write_byte_diff(&mut report, bytes, &entry.bytes);
+                }
+                Some(_) => (),
+            }
+        }
+        for (name, bytes) in &expected {
+            if self.entries.iter().all(|e| &e.name != name) {
+                let _ = writeln!(report, "  entry `{}` is no longer recorded:", name);
+                let _ = writeln!(report, "    expected: {}", hex::encode(bytes));
+            }
+        }
+
+        if !report.is_empty() {
+            panic!(
+                "BCS encodings differ from golden file {} (set {}=1 to regenerate it):\n{}",
+                self.path.display(),
+                UPDATE_GOLDEN_ENV_VAR,
+                report
+            );
+        }
+    }
+
+    /// Overwrite the golden file with the recorded entries.
+    pub fn update(&self) {
+        let mut content = format!(
+            "# BCS golden file. Regenerate with {}=1.\n",
+            UPDATE_GOLDEN_ENV_VAR
+        );
+        for entry in &self.entries {
+            let _ = writeln!(content, "{}: {}", entry.name, hex::encode(&entry.bytes));
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(&self.path, content).unwrap_or_else(|e| {
+            panic!("failed to write golden file {}: {}", self.path.display(), e)
+        });
+    }
+}
+
+fn parse_golden_file(path: &Path, content: &str) -> Vec<(String, Vec<u8>)> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            let malformed = || -> ! { panic!("malformed line {} in {}", i + 1, path.display()) };
+            let (name, digits) = match line.find(':') {
+                Some(pos) => (&line[..pos], line[pos + 1..].trim()),
+                None => malformed(),
+            };
+            match hex::decode(digits) {
+                Some(bytes) => (name.to_string(), bytes),
+                None => malformed(),
+            }
+        })
+        .collect()
+}
+
+/// Show both byte strings and point at the first difference.
+fn write_byte_diff(report: &mut String, expected: &[u8], actual: &[u8]) {
+    let first_difference = expected
+        .iter()
+        .zip(actual)
+        .position(|(e, a)| e != a)
+        .unwrap_or_else(|| std::cmp::min(expected.len(), actual.len()));
+    let _ = writeln!(report, "    expected: {}", hex::encode(expected));
+    let _ = writeln!(report, "    actual:   {}", hex::encode(actual));
+    let _ = writeln!(
+        report,
+        "              {}^^ first difference at byte {} (expected {} bytes, got {})",
+        "  ".repeat(first_difference),
+        first_difference,
+        expected.len(),
+        actual.len()
+    );
+}
@@ -0,0 +1,165 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tracing of the BCS format of Rust types.
+//!
+//! This module runs `serde_reflection::Tracer` on the `Deserialize` implementation of a
+//! type, with the adjustments needed for the registry to describe BCS bytes:
+//! * the variants of enums with tags pinned by `#[bcs(tag = N)]` are recorded under their
+//!   tags rather than their positions;
+//! * types that BCS cannot encode (floats and `char`) are rejected.
+//!
+//! As with `serde-reflection`, the variants of an enum are only all explored when the enum
+//! itself is traced, so enums nested in a type must be traced before it. Recursive enums
+//! must start with a variant that does not recurse.
+//!
+//! ```rust
+//! use bcs::trace::FormatTracer;
+//! use serde::Deserialize;
+//! use serde_reflection::{ContainerFormat, Format};
+//!
+//! #[derive(Deserialize)]
+//! enum Message {
+//!     Ping,
+//!     Batch(Vec<Message>),
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Envelope {
+//!     id: u64,
+//!     message: Message,
+//! }
+//!
+//! let mut tracer = FormatTracer::new();
+//! tracer.trace_type::<Message>().unwrap();
+//! let format = tracer.trace_type::<Envelope>().unwrap();
+//! let registry = tracer.registry().unwrap();
+//! assert_eq!(format, Format::TypeName("Envelope".to_string()));
+//! match &registry["Message"] {
+//!     ContainerFormat::Enum(variants) => assert_eq!(variants.len(), 2),
+//!     _ => panic!("Message is an enum"),
+//! }
+//! ```
+
+use crate::{Error, Result};
+use serde::Deserialize;
+use serde_reflection::{ContainerFormat, Format, FormatHolder, Registry, Tracer, TracerConfig};
+use std::{cell::RefCell, collections::BTreeMap, convert::TryFrom};
+
+/// Trace the format of `T` and of all the containers that it uses, when `T` does not
+/// contain other enums than itself. See [`FormatTracer`] otherwise.
+pub fn trace_type<T>() -> Result<(Format, Registry)>
+where
+    T: Deserialize<'static>,
+{
+    let mut tracer = FormatTracer::new();
+    let format = tracer.trace_type::<T>()?;
+    Ok((format, tracer.registry()?))
+}
+
+/// Traces the BCS formats of several types into a single registry.
+#[derive(Debug)]
+pub struct FormatTracer {
+    tracer: Tracer,
+    /// The pinned tags of the enums met so far, in declaration order.
+    pinned_tags: BTreeMap<&'static str, Vec<u32>>,
+}
+
+impl Default for FormatTracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FormatTracer {
+    /// Start tracing with an empty registry.
+    pub fn new() -> Self {
+        Self {
+            tracer: Tracer::new(TracerConfig::default()),
+            pinned_tags: BTreeMap::new(),
+        }
+    }
+
+    /// Trace the format of `T`, exploring all its variants if `T` is an enum.
+    ///
+    /// Containers are identified by their serde names: two containers with the same name
+    /// but different formats, such as two instantiations of a generic struct, are reported
+    /// as an error. Types borrowing from the input must be traced with the lifetime
+    /// `'static`.
+    pub fn trace_type<T>(&mut self) -> Result<Format>
+    where
+        T: Deserialize<'static>,
+    {
+        PINNED_TAGS.with(|tags| *tags.borrow_mut() = Some(std::mem::take(&mut self.pinned_tags)));
+        let result = self.tracer.trace_simple_type::<T>();
+        self.pinned_tags = PINNED_TAGS.with(|tags| tags.borrow_mut().take().unwrap_or_default());
+        let (format, _) = result.map_err(reflection_error)?;
+        check_supported(&format)?;
+        Ok(format)
+    }
+
+    /// The formats of the containers traced so far. Fails if some variants of an enum
+    /// were not explored.
+    pub fn registry(self) -> Result<Registry> {
+        let mut registry = self.tracer.registry().map_err(reflection_error)?;
+        for (name, tags) in self.pinned_tags {
+            if let Some(ContainerFormat::Enum(variants)) = registry.get_mut(name) {
+                *variants = std::mem::take(variants)
+                    .into_iter()
+                    .map(|(position, variant)| (tags[position as usize], variant))
+                    .collect();
+            }
+        }
+        registry.values().try_for_each(check_supported)?;
+        Ok(registry)
+    }
+}
+
+thread_local! {
+    /// The pinned tags of the enums met by the tracer running on this thread, if any.
+    static PINNED_TAGS: RefCell<Option<BTreeMap<&'static str, Vec<u32>>>> = const { RefCell::new(None) };
+}
+
+/// While tracing, `serde-reflection` identifies variants by position: return the tag of
+/// the variant at `position` and remember the tags of the enum. Returns `None` when no
+/// tracer is running.
+pub(crate) fn pinned_tag_while_tracing(
+    name: &'static str,
+    tags: &[u32],
+    position: u64,
+) -> Option<Option<u32>> {
+    PINNED_TAGS.with(|pinned| {
+        let mut pinned = pinned.borrow_mut();
+        let pinned = pinned.as_mut()?;
+        pinned.entry(name).or_insert_with(|| tags.to_vec());
+        Some(
+            usize::try_from(position)
+                .ok()
+                .and_then(|position| tags.get(position))
+                .copied(),
+        )
+    })
+}
+
+/// Fail on the formats that BCS does not support.
+fn check_supported<F: FormatHolder>(format: &F) -> Result<()> {
+    format
+        .visit(&mut |format| match format {
+            Format::F32 => Err(serde_reflection::Error::NotSupported("deserialize_f32")),
+            Format::F64 => Err(serde_reflection::Error::NotSupported("deserialize_f64")),
+            Format::Char => Err(serde_reflection::Error::NotSupported("deserialize_char")),
+            _ => Ok(()),
+        })
+        .map_err(reflection_error)
+}
+
+fn reflection_error(error: serde_reflection::Error) -> Error {
+    match error {
+        serde_reflection::Error::NotSupported(name) => Error::NotSupported(name),
+        serde_reflection::Error::MissingVariants(names) => Error::Custom(format!(
+            "missing variants of enums {}: nested enums must be traced first",
+            names.join(", ")
+        )),
+        error => Error::Custom(error.to_string()),
+    }
+}
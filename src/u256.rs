@@ -0,0 +1,299 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{Error, Result};
+use serde::{de, ser, Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    convert::{TryFrom, TryInto},
+    fmt,
+    str::FromStr,
+};
+
+/// An unsigned 256-bit integer, such as Move's `u256`.
+///
+/// Like other integers, a `U256` is serialized in BCS as its 32 bytes in little-endian
+/// order, without a length prefix. In human-readable formats, it is a decimal string.
+///
+/// ```rust
+/// use bcs::U256;
+///
+/// let n: U256 = "340282366920938463463374607431768211456".parse().unwrap();
+/// assert_eq!(n, U256::from(u128::MAX).checked_add(U256::ONE).unwrap());
+///
+/// let bytes = bcs::to_bytes(&n).unwrap();
+/// assert_eq!(bytes.len(), 32);
+/// assert_eq!(bytes[16], 1);
+/// assert_eq!(bcs::from_bytes::<U256>(&bytes).unwrap(), n);
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct U256 {
+    // Little-endian 64-bit limbs.
+    limbs: [u64; 4],
+}
+
+impl U256 {
+    /// The number 0.
+    pub const ZERO: U256 = U256 { limbs: [0; 4] };
+
+    /// The number 1.
+    pub const ONE: U256 = U256 {
+        limbs: [1, 0, 0, 0],
+    };
+
+    /// The largest value, 2^256 - 1.
+    pub const MAX: U256 = U256 {
+        limbs: [u64::MAX; 4],
+    };
+
+    /// Create an integer from its little-endian bytes, as encoded in BCS.
+    pub fn from_le_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        U256 { limbs }
+    }
+
+    /// The little-endian bytes of the integer, as encoded in BCS.
+    pub fn to_le_bytes(self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, limb) in bytes.chunks_exact_mut(8).zip(self.limbs.iter()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Add `other`, or return `None` on overflow.
+    pub fn checked_add(self, other: U256) -> Option<U256> {
+        let mut limbs = [0; 4];
+        let mut carry = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (sum, overflow1) = self.limbs[i].overflowing_add(other.limbs[i]);
+            let (sum, overflow2) = sum.overflowing_add(u64::from(carry));
+            *limb = sum;
+            carry = overflow1 || overflow2;
+        }
+        if carry {
+            None
+        } else {
+            Some(U256 { limbs })
+        }
+    }
+
+    /// Subtract `other`, or return `None` if it is larger than `self`.
+    pub fn checked_sub(self, other: U256) -> Option<U256> {
+        let mut limbs = [0; 4];
+        let mut borrow = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (difference, overflow1) = self.limbs[i].overflowing_sub(other.limbs[i]);
+            let (difference, overflow2) = difference.overflowing_sub(u64::from(borrow));
+            *limb = difference;
+            borrow = overflow1 || overflow2;
+        }
+        if borrow {
+            None
+        } else {
+            Some(U256 { limbs })
+        }
+    }
+
+    /// Multiply by `other`, or return `None` on overflow.
+    pub fn checked_mul(self, other: U256) -> Option<U256> {
+        // Schoolbook multiplication on 8 limbs, the upper half of which must be zero.
+        let mut product = [0u64; 8];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let n = u128::from(a) * u128::from(b) + u128::from(product[i + j]) + carry;
+                product[i + j] = n as u64;
+                carry = n >> 64;
+            }
+            product[i + 4] = carry as u64;
+        }
+        if product[4..].iter().any(|&limb| limb != 0) {
+            return None;
+        }
+        let mut limbs = [0; 4];
+        limbs.copy_from_slice(&product[..4]);
+        Some(U256 { limbs })
+    }
+
+    /// Divide by `divisor`, returning the quotient and the remainder.
+    fn div_rem_u64(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let n = (remainder << 64) | u128::from(self.limbs[i]);
+            limbs[i] = (n / u128::from(divisor)) as u64;
+            remainder = n % u128::from(divisor);
+        }
+        (U256 { limbs }, remainder as u64)
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs.iter().rev().cmp(other.limbs.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+macro_rules! impl_from_unsigned {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for U256 {
+            fn from(n: $ty) -> Self {
+                U256::from(u128::from(n))
+            }
+        }
+
+        impl TryFrom<U256> for $ty {
+            type Error = Error;
+
+            fn try_from(n: U256) -> Result<Self> {
+                u128::try_from(n)
+                    .ok()
+                    .and_then(|n| <$ty>::try_from(n).ok())
+                    .ok_or_else(|| out_of_range(n, stringify!($ty)))
+            }
+        }
+    )*};
+}
+
+impl_from_unsigned!(u8, u16, u32, u64);
+
+impl From<u128> for U256 {
+    fn from(n: u128) -> Self {
+        U256 {
+            limbs: [n as u64, (n >> 64) as u64, 0, 0],
+        }
+    }
+}
+
+impl TryFrom<U256> for u128 {
+    type Error = Error;
+
+    fn try_from(n: U256) -> Result<Self> {
+        if n.limbs[2] != 0 || n.limbs[3] != 0 {
+            return Err(out_of_range(n, "u128"));
+        }
+        Ok(u128::from(n.limbs[0]) | u128::from(n.limbs[1]) << 64)
+    }
+}
+
+fn out_of_range(n: U256, ty: &str) -> Error {
+    Error::Custom(format!("integer `{}` does not fit in {}", n, ty))
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Decimal digits, by chunks of 19 from the least significant ones.
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        loop {
+            let (quotient, remainder) = n.div_rem_u64(CHUNK);
+            chunks.push(remainder);
+            if quotient == U256::ZERO {
+                break;
+            }
+            n = quotient;
+        }
+        let mut digits = chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            digits.push_str(&format!("{:019}", chunk));
+        }
+        f.pad_integral(true, "", &digits)
+    }
+}
+
+impl fmt::Debug for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for U256 {
+    type Err = Error;
+
+    /// Parse a non-empty string of decimal digits.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::Custom(format!("invalid 256-bit integer {:?}", s));
+        if s.is_empty() {
+            return Err(invalid());
+        }
+        let ten = U256::from(10u8);
+        s.chars().try_fold(U256::ZERO, |n, c| {
+            let digit = c.to_digit(10).ok_or_else(invalid)?;
+            n.checked_mul(ten)
+                .and_then(|n| n.checked_add(U256::from(digit)))
+                .ok_or_else(invalid)
+        })
+    }
+}
+
+impl Serialize for U256 {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        if serializer.is_human_readable() {
+            return serializer.collect_str(self);
+        }
+        // A tuple of bytes, like `[u8; 32]`, so that no length is written.
+        use ser::SerializeTuple;
+        let mut tuple = serializer.serialize_tuple(32)?;
+        for byte in self.to_le_bytes().iter() {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(U256Visitor)
+        } else {
+            deserializer.deserialize_tuple(32, U256Visitor)
+        }
+    }
+}
+
+struct U256Visitor;
+
+impl<'de> de::Visitor<'de> for U256Visitor {
+    type Value = U256;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a 256-bit unsigned integer")
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<U256, E>
+    where
+        E: de::Error,
+    {
+        s.parse().map_err(E::custom)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<U256, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        Ok(U256::from_le_bytes(bytes))
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::Result;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Maximal number of bytes of the ULEB128 encoding of a `u64`.
+pub(crate) const MAX_LEN: usize = 10;
+
+/// Name under which [`Uleb32`] is serialized as a newtype struct. The BCS serializers and
+/// deserializer recognize it and use ULEB128 for the inner `u32`.
+pub(crate) const ULEB32_NAME: &str = "$bcs::Uleb32";
+
+/// Name under which [`Uleb64`] is serialized as a newtype struct.
+pub(crate) const ULEB64_NAME: &str = "$bcs::Uleb64";
+
+/// The ULEB128 encoding of `value`, written at the beginning of `buffer`.
+pub(crate) fn encode(mut value: u64, buffer: &mut [u8; MAX_LEN]) -> &[u8] {
+    let mut len = 0;
+    while value >= 0x80 {
+        // Write 7 (lowest) bits of data and set the 8th bit to 1.
+        buffer[len] = (value & 0x7f) as u8 | 0x80;
+        value >>= 7;
+        len += 1;
+    }
+    // Write the remaining bits of data and set the highest bit to 0.
+    buffer[len] = value as u8;
+    &buffer[..=len]
+}
+
+/// Number of bytes of the ULEB128 encoding of `value`.
+pub(crate) const fn encoded_len(mut value: u64) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+/// The integer wrapped by a `Uleb32` or a `Uleb64` serialized under `name`, if any.
+pub(crate) fn wrapped_integer<T>(name: &str, value: &T) -> Result<Option<u64>>
+where
+    T: ?Sized + Serialize,
+{
+    if name != ULEB32_NAME && name != ULEB64_NAME {
+        return Ok(None);
+    }
+    // The little-endian bytes of the `u32` or `u64`.
+    let mut le_bytes = [0; 8];
+    crate::serialize_into_slice(&mut le_bytes, value)?;
+    Ok(Some(u64::from_le_bytes(le_bytes)))
+}
+
+/// A `u32` encoded in BCS with ULEB128, like lengths and variant indices, instead of 4
+/// fixed bytes.
+///
+/// Decoding applies the same checks as for lengths: the encoding must be minimal and the
+/// value must fit in a `u32`. Other serde formats see a plain `u32`.
+///
+/// ```rust
+/// use bcs::Uleb32;
+///
+/// assert_eq!(bcs::to_bytes(&Uleb32(300)).unwrap(), vec![0xac, 0x02]);
+/// assert_eq!(bcs::from_bytes::<Uleb32>(&[0xac, 0x02]).unwrap(), Uleb32(300));
+/// assert!(bcs::from_bytes::<Uleb32>(&[0x80, 0x00]).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uleb32(pub u32);
+
+/// A `u64` encoded in BCS with ULEB128, in 1 to 10 bytes instead of 8 fixed bytes.
+///
+/// Decoding rejects encodings that are not minimal and values that do not fit in a `u64`.
+/// Other serde formats see a plain `u64`.
+///
+/// ```rust
+/// use bcs::Uleb64;
+///
+/// assert_eq!(bcs::to_bytes(&Uleb64(1)).unwrap(), vec![1]);
+/// assert_eq!(bcs::to_bytes(&Uleb64(u64::MAX)).unwrap().len(), 10);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uleb64(pub u64);
+
+macro_rules! impl_uleb {
+    ($uleb:ident, $ty:ty, $name:expr, $visit:ident) => {
+        impl From<$ty> for $uleb {
+            fn from(value: $ty) -> Self {
+                $uleb(value)
+            }
+        }
+
+        impl From<$uleb> for $ty {
+            fn from(value: $uleb) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $uleb {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl Serialize for $uleb {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_newtype_struct($name, &self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $uleb {
+            fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                struct Visitor;
+
+                impl<'de> de::Visitor<'de> for Visitor {
+                    type Value = $uleb;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        formatter.write_str(concat!("an integer of type ", stringify!($ty)))
+                    }
+
+                    // Called by the BCS deserializer after decoding ULEB128.
+                    fn $visit<E>(self, value: $ty) -> std::result::Result<$uleb, E>
+                    where
+                        E: de::Error,
+                    {
+                        Ok($uleb(value))
+                    }
+
+                    fn visit_newtype_struct<D>(
+                        self,
+                        deserializer: D,
+                    ) -> std::result::Result<$uleb, D::Error>
+                    where
+                        D: Deserializer<'de>,
+                    {
+                        <$ty>::deserialize(deserializer).map($uleb)
+                    }
+                }
+
+                deserializer.deserialize_newtype_struct($name, Visitor)
+            }
+        }
+    };
+}
+
+impl_uleb!(Uleb32, u32, ULEB32_NAME, visit_u32);
+impl_uleb!(Uleb64, u64, ULEB64_NAME, visit_u64);
@@ -0,0 +1,92 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! JavaScript bindings, built with `wasm-bindgen`, to encode and decode BCS in the browser.
+//!
+//! A [`Schema`] is created from a registry serialized in JSON, as produced by
+//! `serde_json::to_string(&registry)`, and the name of one of its containers. It converts
+//! JavaScript values to and from BCS following the conventions of [`crate::json`]: in
+//! particular, 64-bit and 128-bit integers are decimal strings and byte strings are
+//! hexadecimal strings. Failures are thrown as `Error`s.
+//!
+//! ```js
+//! import { Schema, toHex } from "bcs";
+//!
+//! const schema = new Schema(registryJson, "Transfer");
+//! const bytes = schema.encode({ Coins: { amount: "100", to: "abcd" } });
+//! console.log(toHex(bytes), schema.decode(bytes));
+//! ```
+//!
+//! The package is built with `wasm-pack build -- --features wasm` and tested in Node.js
+//! with `wasm-pack test --node -- --features wasm --test wasm`.
+
+use crate::{
+    hex,
+    json::{bcs_to_json, json_to_bcs},
+};
+use serde_reflection::{Format, Registry};
+use std::fmt::Display;
+use wasm_bindgen::prelude::*;
+
+/// A format registry together with the container to encode and decode.
+#[wasm_bindgen]
+pub struct Schema {
+    format: Format,
+    registry: Registry,
+}
+
+#[wasm_bindgen]
+impl Schema {
+    /// Create a schema from a registry serialized in JSON and the name of one of its
+    /// containers.
+    #[wasm_bindgen(constructor)]
+    pub fn new(registry: &str, type_name: &str) -> Result<Schema, JsValue> {
+        let registry: Registry = serde_json::from_str(registry)
+            .map_err(|e| error(format!("invalid registry: {}", e)))?;
+        if !registry.contains_key(type_name) {
+            return Err(error(format!("unknown container {}", type_name)));
+        }
+        Ok(Schema {
+            format: Format::TypeName(type_name.to_string()),
+            registry,
+        })
+    }
+
+    /// Encode a JavaScript value in BCS.
+    pub fn encode(&self, value: &JsValue) -> Result<Vec<u8>, JsValue> {
+        let json = js_sys::JSON::stringify(value)?
+            .as_string()
+            .ok_or_else(|| error("value cannot be converted to JSON"))?;
+        let value = serde_json::from_str(&json).map_err(error)?;
+        json_to_bcs(&value, &self.format, &self.registry).map_err(error)
+    }
+
+    /// Decode BCS bytes to a JavaScript value. The bytes must be canonical and consumed
+    /// entirely.
+    pub fn decode(&self, bytes: &[u8]) -> Result<JsValue, JsValue> {
+        let value = bcs_to_json(bytes, &self.format, &self.registry).map_err(error)?;
+        js_sys::JSON::parse(&value.to_string())
+    }
+
+    /// The number of bytes of the BCS encoding of a JavaScript value.
+    #[wasm_bindgen(js_name = serializedSize)]
+    pub fn serialized_size(&self, value: &JsValue) -> Result<usize, JsValue> {
+        self.encode(value).map(|bytes| bytes.len())
+    }
+}
+
+/// Encode bytes as lowercase hexadecimal digits.
+#[wasm_bindgen(js_name = toHex)]
+pub fn to_hex_string(bytes: &[u8]) -> String {
+    hex::encode(bytes)
+}
+
+/// Decode an even number of hexadecimal digits, in any case.
+#[wasm_bindgen(js_name = fromHex)]
+pub fn from_hex_string(hex: &str) -> Result<Vec<u8>, JsValue> {
+    hex::decode(hex).ok_or_else(|| error(format!("invalid hexadecimal string {:?}", hex)))
+}
+
+fn error(message: impl Display) -> JsValue {
+    JsError::new(&message.to_string()).into()
+}
@@ -0,0 +1,373 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "derive")]
+// The old `proptest-derive` emits its impls inside an anonymous constant.
+#![allow(non_local_definitions)]
+
+use bcs::{
+    codec::{decode, encode, encode_into},
+    from_bytes, serialized_size, to_bytes, BcsDeserialize, BcsSerialize, BcsSize, Error,
+    MAX_CONTAINER_DEPTH,
+};
+use proptest::prelude::*;
+use proptest_derive::Arbitrary;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Debug,
+    marker::PhantomData,
+};
+
+#[derive(
+    Arbitrary, BcsSerialize, BcsDeserialize, Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+struct Point(i32, i64);
+
+#[derive(Arbitrary, BcsSerialize, BcsDeserialize, Debug, PartialEq, Serialize, Deserialize)]
+struct Fixed {
+    id: u128,
+    origin: Point,
+    flags: [bool; 3],
+    pair: (u8, u16, i8),
+    marker: (),
+}
+
+#[derive(BcsSerialize, BcsDeserialize, Debug, PartialEq, Serialize, Deserialize)]
+struct Unit;
+
+#[derive(Arbitrary, BcsSerialize, BcsDeserialize, Debug, PartialEq, Serialize, Deserialize)]
+struct Newtype(Option<u32>);
+
+#[derive(Arbitrary, BcsSerialize, BcsDeserialize, Debug, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Empty,
+    Dot(Point),
+    Polygon { points: Vec<Point> },
+    Named(String),
+    Labels(BTreeMap<String, Option<u64>>),
+    Weights(HashMap<u8, i16>),
+    Tags(BTreeSet<u32>),
+}
+
+#[derive(Arbitrary, BcsSerialize, BcsDeserialize, Debug, PartialEq, Serialize, Deserialize)]
+struct Generic<T> {
+    values: Vec<T>,
+    #[proptest(strategy = "proptest::collection::vec(any::<Shape>(), 0..8)")]
+    shapes: Vec<Shape>,
+    fixed: Box<Fixed>,
+    bytes: Vec<u8>,
+    key: [u8; 32],
+    newtype: Newtype,
+}
+
+#[derive(BcsSerialize, BcsDeserialize, Debug, PartialEq, Serialize, Deserialize)]
+struct Borrowed<'a> {
+    name: &'a str,
+    data: &'a [u8],
+    marker: PhantomData<&'a ()>,
+}
+
+#[derive(BcsSerialize, BcsDeserialize, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "LinkedList")]
+enum List {
+    Nil,
+    Cons(u8, Box<List>),
+}
+
+impl List {
+    fn with_len(len: usize) -> Self {
+        (0..len).fold(List::Nil, |list, i| List::Cons(i as u8, Box::new(list)))
+    }
+}
+
+/// A former version of `Tagged`, encoded through serde.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Untagged {
+    Zero(u8),
+    One,
+    Two(String),
+    Three,
+}
+
+#[derive(BcsSerialize, BcsDeserialize, BcsSize, Debug, PartialEq)]
+enum Tagged {
+    Zero(u8),
+    #[bcs(tag = 3)]
+    Three,
+    #[bcs(tag = 2)]
+    Two(String),
+    #[bcs(tag = 200)]
+    Large {
+        flag: bool,
+    },
+    Next,
+}
+
+#[derive(BcsSerialize, BcsDeserialize, BcsSize, Debug, PartialEq)]
+#[serde(rename = "Renamed")]
+enum TaggedGeneric<'a, T> {
+    #[bcs(tag = 1)]
+    Pair(T, &'a [u8]),
+    #[serde(rename = "Item")]
+    Record {
+        value: Option<T>,
+        #[serde(rename = "n")]
+        name: String,
+    },
+}
+
+/// Check that direct encoding agrees with serde on `value`, as well as decoding on
+/// mutations of its encoding at up to 32 positions.
+fn is_same<T>(value: &T)
+where
+    T: BcsSerialize + Serialize + for<'de> BcsDeserialize<'de> + for<'de> Deserialize<'de>,
+    T: Debug + PartialEq,
+{
+    let bytes = to_bytes(value).unwrap();
+    assert_eq!(encode(value).unwrap(), bytes);
+    assert_eq!(decode::<T>(&bytes).unwrap(), *value);
+
+    let step = (bytes.len() / 32).max(1);
+    for len in (0..bytes.len()).step_by(step) {
+        is_same_decoding::<T>(&bytes[..len]);
+    }
+    let mut bytes = bytes;
+    bytes.push(0);
+    is_same_decoding::<T>(&bytes);
+    bytes.pop();
+    for i in (0..bytes.len()).step_by(step) {
+        for flip in &[0x01, 0x80, 0xff] {
+            bytes[i] ^= flip;
+            is_same_decoding::<T>(&bytes);
+            bytes[i] ^= flip;
+        }
+    }
+}
+
+fn is_same_decoding<'de, T>(bytes: &'de [u8])
+where
+    T: BcsDeserialize<'de> + Deserialize<'de> + Debug + PartialEq,
+{
+    assert_eq!(decode::<T>(bytes), from_bytes::<T>(bytes), "{:?}", bytes);
+}
+
+#[test]
+fn unit_and_borrowed() {
+    is_same(&Unit);
+    is_same(&Newtype(Some(7)));
+
+    let value = Borrowed {
+        name: "çå∞",
+        data: &[1, 2, 3],
+        marker: PhantomData,
+    };
+    let bytes = to_bytes(&value).unwrap();
+    assert_eq!(encode(&value).unwrap(), bytes);
+    assert_eq!(decode::<Borrowed>(&bytes).unwrap(), value);
+    for len in 0..bytes.len() {
+        is_same_decoding::<Borrowed>(&bytes[..len]);
+    }
+    // Invalid UTF-8.
+    is_same_decoding::<Borrowed>(&[1, 0xff, 0]);
+}
+
+#[test]
+fn multi_byte_length() {
+    let points = vec![Point(1, -1); 200];
+    is_same(&Shape::Polygon { points });
+    is_same(&vec![0xabu8; 1 << 14]);
+    is_same(&"x".repeat(300));
+}
+
+#[test]
+fn unknown_variants() {
+    for index in &[7u8, 0x7f] {
+        assert_eq!(
+            decode::<Shape>(&[*index]),
+            Err(Error::Custom(format!(
+                "invalid value: integer `{}`, expected variant index 0 <= i < 7",
+                index
+            )))
+        );
+        is_same_decoding::<Shape>(&[*index]);
+    }
+    is_same_decoding::<Shape>(&[0xff, 0xff, 0xff, 0xff, 0x0f]);
+    is_same_decoding::<Shape>(&[0x80, 0x00]);
+}
+
+#[derive(BcsSerialize, BcsDeserialize, Debug, PartialEq, Serialize, Deserialize)]
+enum Status {
+    Active,
+    Unknown,
+}
+
+#[test]
+fn unknown_variants_without_catch_all() {
+    // `#[serde(other)]` is rejected by the derives: both paths fail on unknown indices.
+    for index in &[2u8, 5] {
+        assert!(decode::<Status>(&[*index]).is_err());
+        is_same_decoding::<Status>(&[*index]);
+    }
+    is_same(&Status::Unknown);
+}
+
+#[test]
+fn non_canonical_maps() {
+    let mut map = BTreeMap::new();
+    map.insert("b".to_string(), None);
+    map.insert("a".to_string(), Some(1u64));
+    map.insert("ab".to_string(), None);
+    is_same(&Shape::Labels(map));
+
+    // Unsorted and duplicate keys.
+    is_same_decoding::<BTreeMap<u8, u8>>(&[2, 2, 0, 1, 0]);
+    is_same_decoding::<BTreeMap<u8, u8>>(&[2, 1, 0, 1, 0]);
+    is_same_decoding::<HashMap<u8, u8>>(&[2, 1, 0, 1, 0]);
+    // Sets are not checked.
+    is_same_decoding::<BTreeSet<u8>>(&[3, 2, 1, 2]);
+}
+
+#[test]
+fn container_depth() {
+    let list = List::with_len(MAX_CONTAINER_DEPTH - 1);
+    is_same(&list);
+
+    let list = List::with_len(MAX_CONTAINER_DEPTH);
+    // Errors name the container as serde does.
+    assert_eq!(
+        encode(&list),
+        Err(Error::ExceededContainerDepthLimit("LinkedList"))
+    );
+    assert_eq!(encode(&list), to_bytes(&list));
+    let bytes = [1, 0].repeat(MAX_CONTAINER_DEPTH);
+    assert_eq!(
+        decode::<List>(&[&bytes[..], &[0]].concat()),
+        Err(Error::ExceededContainerDepthLimit("LinkedList"))
+    );
+    is_same_decoding::<List>(&[&bytes[..], &[0]].concat());
+
+    // Map keys and values are encoded with the depth of the map.
+    let map: BTreeMap<u8, List> = vec![(0, List::with_len(MAX_CONTAINER_DEPTH))]
+        .into_iter()
+        .collect();
+    assert_eq!(encode(&map), to_bytes(&map));
+}
+
+#[test]
+fn pinned_variant_tags() {
+    let cases = vec![
+        (Tagged::Zero(5), vec![0, 5]),
+        (Tagged::Two("a".to_string()), vec![2, 1, b'a']),
+        (Tagged::Three, vec![3]),
+        (Tagged::Large { flag: true }, vec![0xc8, 0x01, 1]),
+        (Tagged::Next, vec![0xc9, 0x01]),
+    ];
+    for (value, bytes) in cases {
+        assert_eq!(encode(&value).unwrap(), bytes);
+        assert_eq!(value.bcs_size(), bytes.len());
+        assert_eq!(decode::<Tagged>(&bytes).unwrap(), value);
+        // Serde sees the same tags.
+        assert_eq!(to_bytes(&value).unwrap(), bytes);
+        assert_eq!(serialized_size(&value).unwrap(), bytes.len());
+        assert_eq!(from_bytes::<Tagged>(&bytes).unwrap(), value);
+        is_same(&value);
+    }
+
+    // Values of the former version decode the same, except for the removed variant.
+    for value in &[
+        Untagged::Zero(1),
+        Untagged::Two("b".to_string()),
+        Untagged::Three,
+    ] {
+        let bytes = to_bytes(value).unwrap();
+        assert_eq!(encode(&decode::<Tagged>(&bytes).unwrap()), Ok(bytes));
+    }
+    assert_eq!(
+        decode::<Tagged>(&to_bytes(&Untagged::One).unwrap()),
+        Err(Error::Custom(
+            "invalid value: integer `1`, expected one of the variant tags 0, 3, 2, 200, 201"
+                .to_string()
+        ))
+    );
+    assert_eq!(
+        from_bytes::<Tagged>(&to_bytes(&Untagged::One).unwrap()),
+        decode::<Tagged>(&to_bytes(&Untagged::One).unwrap()),
+    );
+
+    let pair = TaggedGeneric::Pair(7u16, &b"ab"[..]);
+    let record: TaggedGeneric<u16> = TaggedGeneric::Record {
+        value: Some(8),
+        name: "c".to_string(),
+    };
+    for value in &[pair, record] {
+        let bytes = encode(value).unwrap();
+        assert_eq!(to_bytes(value).unwrap(), bytes);
+        assert_eq!(serialized_size(value).unwrap(), value.bcs_size());
+        assert_eq!(from_bytes::<TaggedGeneric<u16>>(&bytes).unwrap(), *value);
+        assert_eq!(decode::<TaggedGeneric<u16>>(&bytes).unwrap(), *value);
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn pinned_variant_tags_in_other_formats() {
+    let value = Tagged::Large { flag: true };
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#"{"Large":{"flag":true}}"#);
+    assert_eq!(serde_json::from_str::<Tagged>(&json).unwrap(), value);
+    assert_eq!(
+        serde_json::from_str::<Tagged>(r#"{"Two":"a"}"#).unwrap(),
+        Tagged::Two("a".to_string())
+    );
+    assert!(serde_json::from_str::<Tagged>(r#"{"Large":{}}"#).is_err());
+
+    let record: TaggedGeneric<u16> = TaggedGeneric::Record {
+        value: None,
+        name: "c".to_string(),
+    };
+    let json = serde_json::to_string(&record).unwrap();
+    assert_eq!(json, r#"{"Item":{"value":null,"n":"c"}}"#);
+}
+
+#[test]
+fn encode_into_keeps_output_on_error() {
+    let mut output = vec![1, 2];
+    encode_into(&mut output, &Point(3, 4)).unwrap();
+    assert_eq!(
+        output,
+        [&[1, 2][..], &to_bytes(&Point(3, 4)).unwrap()].concat()
+    );
+
+    let len = output.len();
+    encode_into(&mut output, &List::with_len(MAX_CONTAINER_DEPTH)).unwrap_err();
+    assert_eq!(output.len(), len);
+}
+
+proptest! {
+    #[test]
+    fn fixed_is_same(value in any::<Fixed>()) {
+        is_same(&value);
+    }
+
+    #[test]
+    fn generic_is_same(value in any::<Generic<Option<String>>>()) {
+        is_same(&value);
+    }
+
+    #[test]
+    fn tuples_are_same(
+        value in any::<(u64, (bool, String), Option<[i32; 4]>)>(),
+        pairs in proptest::collection::vec(any::<(i128, u16)>(), 0..8),
+    ) {
+        is_same(&(value, pairs));
+    }
+
+    #[test]
+    fn arbitrary_bytes_decode_the_same(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+        is_same_decoding::<Shape>(&bytes);
+        is_same_decoding::<Generic<u32>>(&bytes);
+        is_same_decoding::<Vec<Option<bool>>>(&bytes);
+        is_same_decoding::<(String, BTreeMap<u16, ()>)>(&bytes);
+    }
+}
@@ -0,0 +1,517 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "reflection")]
+
+use bcs::{
+    codegen::{generate, generate_python, generate_typescript, Language},
+    to_bytes, Error, FixedBytes, Uleb32, Uleb64,
+};
+use serde::{Deserialize, Serialize};
+use serde_reflection::{ContainerFormat, Format, Named, Registry, Samples, Tracer, TracerConfig};
+use std::{collections::BTreeMap, path::PathBuf, process::Command};
+
+// The fixtures of `serde_known_vector`.
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Addr([u8; 32]);
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Bar {
+    a: u64,
+    b: Vec<u8>,
+    c: Addr,
+    d: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Foo {
+    a: u64,
+    b: Vec<u8>,
+    c: Bar,
+    d: bool,
+    e: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+// The fixtures of the golden file.
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+enum Message {
+    Ping,
+    Payload { id: u64, data: Vec<u8> },
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Envelope {
+    sender: [u8; 4],
+    message: Message,
+    tags: BTreeMap<String, Option<u16>>,
+}
+
+// Formats not covered above.
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Marker;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+enum Shape {
+    Point,
+    Circle(u32),
+    Pair(i8, i128),
+    Rect { width: u16, height: u16 },
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Misc {
+    #[serde(rename = "from")]
+    sender: String,
+    shapes: Vec<Shape>,
+    weights: BTreeMap<u16, i32>,
+    pair: (u8, i64),
+    memo: Option<String>,
+    marker: Marker,
+    unit: (),
+    points: [u16; 3],
+}
+
+// The types with the encodings of this crate.
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Compact {
+    index: Uleb32,
+    nonce: Uleb64,
+    key: FixedBytes<48>,
+    #[serde(with = "bcs::fixed_bytes")]
+    digest: [u8; 40],
+}
+
+fn foo() -> Foo {
+    let mut map = BTreeMap::new();
+    map.insert(vec![0, 56, 21], vec![22, 10, 5]);
+    map.insert(vec![1], vec![22, 21, 67]);
+    map.insert(vec![20, 21, 89, 105], vec![201, 23, 90]);
+    Foo {
+        a: u64::MAX,
+        b: vec![100, 99, 88, 77, 66, 55],
+        c: Bar {
+            a: 100,
+            b: vec![0, 1, 2, 3, 4, 5, 6, 7, 8],
+            c: Addr([5u8; 32]),
+            d: 99,
+        },
+        d: true,
+        e: map,
+    }
+}
+
+fn envelope() -> Envelope {
+    let mut tags = BTreeMap::new();
+    tags.insert("zeta".to_string(), None);
+    tags.insert("alpha".to_string(), Some(0x1234));
+    Envelope {
+        sender: [192, 168, 1, 1],
+        message: Message::Payload {
+            id: 7,
+            data: vec![0xc0, 0xde],
+        },
+        tags,
+    }
+}
+
+fn misc() -> Misc {
+    Misc {
+        sender: "çå∞".to_string(),
+        shapes: vec![
+            Shape::Point,
+            Shape::Circle(7),
+            Shape::Pair(-1, i128::MIN),
+            Shape::Rect {
+                width: 3,
+                height: 256,
+            },
+        ],
+        weights: vec![(256, -3), (1, 4), (2, i32::MAX)].into_iter().collect(),
+        pair: (255, i64::MIN),
+        memo: Some("memo".to_string()),
+        marker: Marker,
+        unit: (),
+        points: [1, 2, 65535],
+    }
+}
+
+fn compact() -> Compact {
+    Compact {
+        index: Uleb32(u32::MAX),
+        nonce: Uleb64(u64::MAX),
+        key: FixedBytes([7; 48]),
+        digest: [9; 40],
+    }
+}
+
+fn registry() -> Registry {
+    let mut tracer = Tracer::new(TracerConfig::default());
+    let samples = Samples::new();
+    tracer.trace_simple_type::<Message>().unwrap();
+    tracer.trace_simple_type::<Shape>().unwrap();
+    tracer.trace_type::<Foo>(&samples).unwrap();
+    tracer.trace_type::<Envelope>(&samples).unwrap();
+    tracer.trace_type::<Misc>(&samples).unwrap();
+    tracer.trace_type::<Compact>(&samples).unwrap();
+    tracer.registry().unwrap()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("bcs-codegen-{}", std::process::id()))
+        .join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const PYTHON_CHECKS: &str = r#"
+import sys
+from generated import *
+
+foo_bytes, envelope_bytes, misc_bytes, compact_bytes = (
+    bytes.fromhex(arg) for arg in sys.argv[1:]
+)
+
+# Decoding and re-encoding gives back the same bytes.
+for cls, data in [
+    (Foo, foo_bytes),
+    (Envelope, envelope_bytes),
+    (Misc, misc_bytes),
+    (Compact, compact_bytes),
+]:
+    value = cls.bcs_deserialize(data)
+    assert value.bcs_serialize() == data, cls
+
+# Values built in Python are encoded like in Rust, with maps sorted by their keys.
+foo = Foo(
+    a=2**64 - 1,
+    b=bytes([100, 99, 88, 77, 66, 55]),
+    c=Bar(a=100, b=bytes(range(9)), c=Addr(value=bytes([5] * 32)), d=99),
+    d=True,
+    e={
+        bytes([20, 21, 89, 105]): bytes([201, 23, 90]),
+        bytes([1]): bytes([22, 21, 67]),
+        bytes([0, 56, 21]): bytes([22, 10, 5]),
+    },
+)
+assert foo.bcs_serialize() == foo_bytes
+assert Foo.bcs_deserialize(foo_bytes) == foo
+
+envelope = Envelope.bcs_deserialize(envelope_bytes)
+assert envelope.sender == bytes([192, 168, 1, 1])
+assert envelope.message == Message__Payload(id=7, data=bytes([0xC0, 0xDE]))
+assert envelope.tags == {"alpha": 0x1234, "zeta": None}
+assert Message.bcs_deserialize(bytes([0])) == Message__Ping()
+
+misc = Misc.bcs_deserialize(misc_bytes)
+assert misc.from_ == "çå∞"
+assert misc.shapes == [
+    Shape__Point(),
+    Shape__Circle(value=7),
+    Shape__Pair(field0=-1, field1=-(2**127)),
+    Shape__Rect(width=3, height=256),
+]
+assert list(misc.weights) == [256, 1, 2]
+assert misc.pair == (255, -(2**63))
+assert misc.marker == Marker()
+assert misc.unit is None
+assert misc.points == [1, 2, 65535]
+
+compact = Compact(
+    index=2**32 - 1, nonce=2**64 - 1, key=bytes([7] * 48), digest=bytes([9] * 40)
+)
+assert Compact.bcs_deserialize(compact_bytes) == compact
+assert compact.bcs_serialize() == compact_bytes
+assert de_uleb64(BcsDeserializer(bytes([0x80, 0x01]))) == 128
+
+
+def rejects(function, *args):
+    try:
+        function(*args)
+    except BcsError:
+        return
+    raise AssertionError("accepted %r" % (args,))
+
+
+# Non-canonical inputs are rejected.
+rejects(Foo.bcs_deserialize, foo_bytes + b"\x00")
+rejects(Foo.bcs_deserialize, foo_bytes[:-1])
+rejects(Message.bcs_deserialize, bytes([2]))
+rejects(Message__Ping.bcs_deserialize, bytes([1, 0, 0, 0, 0, 0, 0, 0, 0, 0]))
+rejects(de_bool, BcsDeserializer(bytes([2])))
+rejects(BcsDeserializer(bytes([0x80, 0x00])).uleb128)
+rejects(BcsDeserializer(bytes([0x80, 0x80, 0x80, 0x80, 0x10])).uleb128)
+rejects(de_str, BcsDeserializer(bytes([1, 0xFF])))
+rejects(de_map(de_u16, de_bool), BcsDeserializer(bytes([2, 2, 0, 1, 1, 0, 0])))
+rejects(de_map(de_u16, de_bool), BcsDeserializer(bytes([2, 1, 0, 1, 1, 0, 0])))
+rejects(de_option(de_u8), BcsDeserializer(bytes([2, 0])))
+rejects(ser_map(lambda s, key: ser_u8(s, 0), ser_u8), BcsSerializer(), {"a": 1, "b": 2})
+rejects(ser_u8, BcsSerializer(), 256)
+rejects(ser_i8, BcsSerializer(), -129)
+rejects(Addr(value=bytes(31)).bcs_serialize)
+rejects(ser_uleb32, BcsSerializer(), 2**32)
+rejects(ser_uleb64, BcsSerializer(), -1)
+rejects(de_uleb32, BcsDeserializer(bytes([0xFF] * 4 + [0x10])))
+rejects(de_uleb64, BcsDeserializer(bytes([0xFF] * 9 + [0x02])))
+rejects(de_uleb64, BcsDeserializer(bytes([0x80, 0x00])))
+
+# Containers are limited to the same depth as in Rust.
+rejects(Foo.bcs_deserialize_from, BcsDeserializer(foo_bytes, remaining_depth=2))
+assert Foo.bcs_deserialize_from(BcsDeserializer(foo_bytes, remaining_depth=3)) == foo
+
+print("ok")
+"#;
+
+#[test]
+#[ignore = "requires python3, run in CI with --ignored"]
+fn python_round_trip() {
+    let dir = temp_dir("python");
+    let code = generate_python(&registry()).unwrap();
+    std::fs::write(dir.join("generated.py"), code).unwrap();
+    std::fs::write(dir.join("checks.py"), PYTHON_CHECKS).unwrap();
+
+    let output = Command::new("python3")
+        .current_dir(&dir)
+        .arg("checks.py")
+        .arg(hex(&to_bytes(&foo()).unwrap()))
+        .arg(hex(&to_bytes(&envelope()).unwrap()))
+        .arg(hex(&to_bytes(&misc()).unwrap()))
+        .arg(hex(&to_bytes(&compact()).unwrap()))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
+
+const TYPESCRIPT_CHECKS: &str = r#"
+import {
+  BcsClass,
+  BcsDeserializer,
+  BcsError,
+  BcsObject,
+  BcsSerializer,
+  Compact,
+  Envelope,
+  Foo,
+  Message,
+  Message__Payload,
+  Misc,
+  bcsDeserialize,
+  deUleb32,
+  deUleb64,
+  serUleb32,
+  serUleb64,
+} from "./generated";
+
+function fromHex(hex: string): Uint8Array {
+  return Uint8Array.from(hex.match(/../g) ?? [], (byte) => parseInt(byte, 16));
+}
+
+function toHex(bytes: Uint8Array): string {
+  return Array.from(bytes, (byte) => byte.toString(16).padStart(2, "0")).join("");
+}
+
+function check(condition: boolean, message: string): void {
+  if (!condition) {
+    throw new Error("check failed: " + message);
+  }
+}
+
+function rejects(f: () => unknown): void {
+  try {
+    f();
+  } catch (error) {
+    if (error instanceof BcsError) {
+      return;
+    }
+    throw error;
+  }
+  throw new Error("accepted " + f);
+}
+
+// Decoding and re-encoding gives back the same bytes.
+function roundTrip<T extends BcsObject>(cls: BcsClass<T>, hex: string): T {
+  const value = bcsDeserialize(cls, fromHex(hex));
+  check(toHex(value.bcsSerialize()) === hex, cls.name);
+  return value;
+}
+
+roundTrip(Foo, FOO);
+roundTrip(Misc, MISC);
+
+const envelope = roundTrip(Envelope, ENVELOPE);
+check(envelope.message instanceof Message__Payload, "message");
+check(envelope.tags.get("alpha") === 0x1234 && envelope.tags.get("zeta") === null, "tags");
+
+const compact = roundTrip(Compact, COMPACT);
+check(compact.index === 2 ** 32 - 1, "index");
+check(compact.nonce === 2n ** 64n - 1n, "nonce");
+check(toHex(compact.key) === "07".repeat(48), "key");
+check(toHex(compact.digest) === "09".repeat(40), "digest");
+const built = new Compact(2 ** 32 - 1, 2n ** 64n - 1n, compact.key, compact.digest);
+check(toHex(built.bcsSerialize()) === COMPACT, "built");
+
+// Non-canonical inputs are rejected.
+rejects(() => bcsDeserialize(Foo, fromHex(FOO + "00")));
+rejects(() => bcsDeserialize(Message, Uint8Array.of(2)));
+rejects(() => serUleb32(new BcsSerializer(), 2 ** 32));
+rejects(() => serUleb64(new BcsSerializer(), -1n));
+rejects(() => deUleb32(new BcsDeserializer(Uint8Array.of(0xff, 0xff, 0xff, 0xff, 0x10))));
+rejects(() => deUleb64(new BcsDeserializer(Uint8Array.of(0x80, 0x00))));
+rejects(() => deUleb64(new BcsDeserializer(fromHex("ff".repeat(9) + "02"))));
+
+console.log("ok");
+"#;
+#[test]
+#[ignore = "requires tsc and node, run in CI with --ignored"]
+fn typescript_round_trip() {
+    let dir = temp_dir("typescript");
+    let code = generate_typescript(&registry()).unwrap();
+    std::fs::write(dir.join("generated.ts"), code).unwrap();
+    let constants = format!(
+        "const FOO = {:?};\nconst ENVELOPE = {:?};\nconst MISC = {:?};\nconst COMPACT = {:?};\n",
+        hex(&to_bytes(&foo()).unwrap()),
+        hex(&to_bytes(&envelope()).unwrap()),
+        hex(&to_bytes(&misc()).unwrap()),
+        hex(&to_bytes(&compact()).unwrap()),
+    );
+    std::fs::write(dir.join("checks.ts"), constants + TYPESCRIPT_CHECKS).unwrap();
+
+    let output = Command::new("tsc")
+        .current_dir(&dir)
+        .args(["--target", "es2020", "--module", "commonjs"])
+        .args(["generated.ts", "checks.ts"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let output = Command::new("node")
+        .current_dir(&dir)
+        .arg("checks.js")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
+
+#[test]
+fn typescript_definitions() {
+    let code = generate_typescript(&registry()).unwrap();
+    for expected in &[
+        "export class BcsSerializer {",
+        "export class Addr extends BcsObject {",
+        "    public readonly value: Uint8Array,",
+        "    serFixedBytes(32)(serializer, this.value);",
+        "    public readonly e: Map<Uint8Array, Uint8Array>,",
+        "    serMap(serBytes, serBytes)(serializer, this.e);",
+        "      deObject(Bar)(deserializer),",
+        "    public readonly tags: Map<string, number | null>,",
+        "export abstract class Shape extends BcsObject {",
+        "        value = Shape__Rect.deserializePayload(deserializer);",
+        "export class Shape__Pair extends Shape {",
+        "  readonly bcsVariantIndex = 2;",
+        "    public readonly field1: bigint,",
+        "    public readonly points: number[],",
+        "    public readonly pair: [number, bigint],",
+        "    public readonly unit: null,",
+        "    public readonly from: string,",
+        "    public readonly index: number,",
+        "    public readonly nonce: bigint,",
+        "    serUleb32(serializer, this.index);",
+        "      deUleb64(deserializer),",
+        "    public readonly key: Uint8Array,",
+        "      deFixedBytes(40)(deserializer),",
+    ] {
+        assert!(code.contains(expected), "missing {:?}", expected);
+    }
+    assert!(!code.contains("$bcs"));
+    assert_eq!(generate(Language::TypeScript, &registry()).unwrap(), code);
+}
+
+#[test]
+fn python_definitions() {
+    let code = generate_python(&registry()).unwrap();
+    for expected in &[
+        "class Foo(BcsObject):",
+        "    e: typing.Dict[bytes, bytes]",
+        "        ser_map(ser_bytes, ser_bytes)(serializer, self.e)",
+        "class Shape__Rect(Shape):",
+        "    INDEX = 3",
+        "    field1: int",
+        "    from_: str",
+        "    memo: typing.Optional[str]",
+        "    pair: typing.Tuple[int, int]",
+        "    points: typing.List[int]",
+        "            points=de_array(de_u16, 3)(deserializer),",
+        "    index: int",
+        "        ser_uleb64(serializer, self.nonce)",
+        "            index=de_uleb32(deserializer),",
+        "    digest: bytes",
+        "        ser_fixed_bytes(48)(serializer, self.key)",
+    ] {
+        assert!(code.contains(expected), "missing {:?}", expected);
+    }
+    assert!(!code.contains("$bcs"));
+    assert_eq!(generate(Language::Python, &registry()).unwrap(), code);
+}
+
+#[test]
+fn unsupported_formats() {
+    let container = |format: Format| {
+        let mut registry = Registry::new();
+        registry.insert(
+            "Test".to_string(),
+            ContainerFormat::Struct(vec![Named {
+                name: "field".to_string(),
+                value: format,
+            }]),
+        );
+        registry
+    };
+    for language in &[Language::Python, Language::TypeScript] {
+        assert_eq!(
+            generate(*language, &container(Format::F64)),
+            Err(Error::NotSupported("f64"))
+        );
+        assert_eq!(
+            generate(*language, &container(Format::Char)),
+            Err(Error::NotSupported("char"))
+        );
+        let nested = Format::Option(Box::new(Format::Option(Box::new(Format::U8))));
+        assert!(generate(*language, &container(nested))
+            .unwrap_err()
+            .is_unsupported_type());
+        let invalid = Format::TypeName("a-b".to_string());
+        assert_eq!(
+            generate(*language, &container(invalid)),
+            Err(Error::Custom("invalid identifier `a-b`".to_string()))
+        );
+    }
+}
+
+#[test]
+fn languages() {
+    assert_eq!("python".parse::<Language>().unwrap(), Language::Python);
+    assert_eq!("TS".parse::<Language>().unwrap(), Language::TypeScript);
+    assert!("go".parse::<Language>().is_err());
+    assert_eq!(Language::TypeScript.to_string(), "typescript");
+}
@@ -0,0 +1,272 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "reflection")]
+
+use bcs::compat::{check_compatibility, ChangeKind};
+use serde::{Deserialize, Serialize};
+use serde_reflection::{Format, Registry, Tracer, TracerConfig};
+
+fn registry_of<T>() -> Registry
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut tracer = Tracer::new(TracerConfig::default());
+    tracer.trace_simple_type::<T>().unwrap();
+    tracer.registry().unwrap()
+}
+
+fn changes(old: &Registry, new: &Registry) -> Vec<(String, ChangeKind)> {
+    check_compatibility(old, new)
+        .changes()
+        .iter()
+        .map(|c| (c.path.clone(), c.kind.clone()))
+        .collect()
+}
+
+mod v1 {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Account {
+        pub address: [u8; 4],
+        pub balance: u64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum Event {
+        Created(Account),
+        Deposit { amount: u64 },
+        Withdraw { amount: u64 },
+    }
+}
+
+mod v2 {
+    use super::*;
+
+    // Rename a field in place and append another one.
+    #[derive(Serialize, Deserialize)]
+    pub struct Account {
+        pub address: [u8; 4],
+        pub sequence_number: u64,
+        pub amount: u64,
+    }
+
+    // Reorder two variants, change a field type and append a variant.
+    #[derive(Serialize, Deserialize)]
+    pub enum Event {
+        Created(Account),
+        Withdraw { amount: u64 },
+        Deposit { amount: u128 },
+        Frozen,
+    }
+}
+
+#[test]
+fn identical_registries() {
+    let registry = registry_of::<v1::Event>();
+    let report = check_compatibility(&registry, &registry);
+    assert!(report.changes().is_empty());
+    assert!(report.is_compatible());
+}
+
+#[test]
+fn breaking_changes() {
+    let report = check_compatibility(&registry_of::<v1::Event>(), &registry_of::<v2::Event>());
+    assert!(!report.is_compatible());
+    assert_eq!(
+        changes(&registry_of::<v1::Event>(), &registry_of::<v2::Event>()),
+        vec![
+            (
+                "Account.amount".to_string(),
+                ChangeKind::FieldInserted {
+                    name: "amount".to_string()
+                }
+            ),
+            (
+                "Account.balance".to_string(),
+                ChangeKind::FieldRenamed {
+                    old: "balance".to_string(),
+                    new: "sequence_number".to_string(),
+                }
+            ),
+            (
+                "Event::Deposit".to_string(),
+                ChangeKind::VariantMoved {
+                    name: "Deposit".to_string(),
+                    old_index: 1,
+                    new_index: 2,
+                }
+            ),
+            (
+                "Event::Deposit.amount".to_string(),
+                ChangeKind::TypeChanged {
+                    old: Format::U64,
+                    new: Format::U128,
+                }
+            ),
+            (
+                "Event::Frozen".to_string(),
+                ChangeKind::VariantAdded {
+                    index: 3,
+                    name: "Frozen".to_string(),
+                }
+            ),
+            (
+                "Event::Withdraw".to_string(),
+                ChangeKind::VariantMoved {
+                    name: "Withdraw".to_string(),
+                    old_index: 2,
+                    new_index: 1,
+                }
+            ),
+        ]
+    );
+    assert_eq!(report.breaking_changes().count(), 4);
+}
+
+#[test]
+fn removed_variant() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Old {
+        A,
+        B(u8),
+        C,
+    }
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    #[serde(rename = "Old")]
+    enum New {
+        A,
+        C,
+    }
+
+    let report = check_compatibility(&registry_of::<Old>(), &registry_of::<New>());
+    let rendered: Vec<_> = report.changes().iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        rendered,
+        vec![
+            "[breaking] Old::B: variant `B` with index 1 removed",
+            "[breaking] Old::C: variant `C` moved from index 2 to index 1",
+        ]
+    );
+}
+
+#[test]
+fn safe_changes() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Old {
+        A { x: u8 },
+        B(Vec<u8>),
+    }
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    #[serde(rename = "Old")]
+    enum New {
+        A { y: u8 },
+        Renamed(Vec<u8>),
+        Appended(Option<u64>),
+    }
+
+    let report = check_compatibility(&registry_of::<Old>(), &registry_of::<New>());
+    assert!(report.is_compatible());
+    let rendered: Vec<_> = report.changes().iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        rendered,
+        vec![
+            "[safe] Old::A.x: field `x` renamed to `y`",
+            "[safe] Old::Appended: variant `Appended` added with index 2",
+            "[safe] Old::B: variant `B` with index 1 renamed to `Renamed`",
+        ]
+    );
+}
+
+#[test]
+fn swapped_fields_are_breaking() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Old {
+        a: u64,
+        b: u64,
+    }
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    #[serde(rename = "Old")]
+    struct New {
+        b: u64,
+        a: u64,
+    }
+
+    let report = check_compatibility(&registry_of::<Old>(), &registry_of::<New>());
+    assert!(!report.is_compatible());
+    assert_eq!(
+        changes(&registry_of::<Old>(), &registry_of::<New>()),
+        vec![("Old".to_string(), ChangeKind::FieldsReordered)]
+    );
+}
+
+#[test]
+fn type_changes_are_described() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Old(Vec<u8>, Option<[u16; 2]>);
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    #[serde(rename = "Old")]
+    struct New(Vec<u32>, Option<[u16; 2]>, bool);
+
+    let report = check_compatibility(&registry_of::<Old>(), &registry_of::<New>());
+    let rendered: Vec<_> = report.changes().iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        rendered,
+        vec![
+            "[breaking] Old: number of elements changed from 2 to 3",
+            "[breaking] Old.0: type changed from Vec<u8> to Vec<u32>",
+        ]
+    );
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn check_compat_command() {
+    use std::process::Command;
+
+    let dir = std::env::temp_dir().join(format!("bcs-check-compat-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let old = dir.join("old.yaml");
+    let new = dir.join("new.yaml");
+    std::fs::write(
+        &old,
+        serde_yaml::to_string(&registry_of::<v1::Event>()).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(
+        &new,
+        serde_yaml::to_string(&registry_of::<v2::Event>()).unwrap(),
+    )
+    .unwrap();
+
+    let run = |old, new| {
+        Command::new(env!("CARGO_BIN_EXE_bcs"))
+            .arg("check-compat")
+            .arg(old)
+            .arg(new)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&old, &old);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = run(&old, &new);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout
+        .contains("[breaking] Event::Deposit: variant `Deposit` moved from index 1 to index 2"));
+    assert!(stdout.contains("[safe] Event::Frozen: variant `Frozen` added with index 3"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,178 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::{
+    envelope::{from_enveloped_bytes, to_enveloped_bytes, Envelope, EnvelopeRegistry, Enveloped},
+    from_bytes, to_bytes, Error,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct RecordV1 {
+    key: u32,
+}
+
+impl Enveloped for RecordV1 {
+    const TYPE_TAG: &'static str = "Record";
+    const VERSION: u32 = 1;
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    key: u32,
+    value: Option<String>,
+}
+
+impl Enveloped for Record {
+    const TYPE_TAG: &'static str = "Record";
+    const VERSION: u32 = 2;
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Borrowed<'a> {
+    name: &'a str,
+}
+
+impl<'a> Enveloped for Borrowed<'a> {
+    const TYPE_TAG: &'static str = "Borrowed";
+    const VERSION: u32 = 0;
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Label(String);
+
+impl Enveloped for Label {
+    const TYPE_TAG: &'static str = "Label";
+    const VERSION: u32 = 1;
+}
+
+#[derive(Debug, PartialEq)]
+enum Stored {
+    Record(Record),
+    Name(String),
+}
+
+#[test]
+fn envelope_encoding() {
+    let record = RecordV1 { key: 3 };
+    let bytes = to_enveloped_bytes(&record).unwrap();
+    assert_eq!(
+        bytes,
+        vec![6, b'R', b'e', b'c', b'o', b'r', b'd', 1, 0, 0, 0, 4, 3, 0, 0, 0]
+    );
+    let envelope: Envelope = from_bytes(&bytes).unwrap();
+    assert_eq!(envelope, Envelope::seal(&record).unwrap());
+    assert_eq!(envelope.open::<RecordV1>().unwrap(), record);
+    assert_eq!(to_bytes(&envelope).unwrap(), bytes);
+}
+
+#[test]
+fn tags_and_versions_are_checked() {
+    let bytes = to_enveloped_bytes(&RecordV1 { key: 3 }).unwrap();
+    assert_eq!(
+        from_enveloped_bytes::<RecordV1>(&bytes).unwrap(),
+        RecordV1 { key: 3 }
+    );
+    assert_eq!(
+        from_enveloped_bytes::<Record>(&bytes),
+        Err(Error::UnsupportedVersion(1))
+    );
+    assert_eq!(
+        from_enveloped_bytes::<Borrowed>(&bytes),
+        Err(Error::UnexpectedTypeTag("Record".to_string()))
+    );
+    let envelope: Envelope = from_bytes(&bytes).unwrap();
+    assert_eq!(envelope.open::<Record>(), Err(Error::UnsupportedVersion(1)));
+    assert!(Error::UnsupportedVersion(1).is_malformed_input());
+
+    // Trailing bytes, in the envelope or in its payload.
+    let mut extended = bytes.clone();
+    extended.push(0);
+    assert_eq!(
+        from_enveloped_bytes::<RecordV1>(&extended),
+        Err(Error::RemainingInput)
+    );
+    let mut envelope = envelope;
+    envelope.payload.push(0);
+    assert_eq!(envelope.open::<RecordV1>(), Err(Error::RemainingInput));
+}
+
+#[test]
+fn borrowed_values() {
+    let bytes = to_enveloped_bytes(&Borrowed { name: "abc" }).unwrap();
+    let value = from_enveloped_bytes::<Borrowed>(&bytes).unwrap();
+    assert_eq!(value, Borrowed { name: "abc" });
+}
+
+#[test]
+fn registry_dispatch() {
+    let mut registry = EnvelopeRegistry::new();
+    registry
+        .register(|record: RecordV1| {
+            Stored::Record(Record {
+                key: record.key,
+                value: None,
+            })
+        })
+        .register(Stored::Record)
+        .register(|label: Label| Stored::Name(label.0));
+    assert!(registry.contains("Record", 1) && registry.contains("Record", 2));
+    assert!(!registry.contains("Record", 3));
+
+    let record = Record {
+        key: 5,
+        value: Some("x".to_string()),
+    };
+    let bytes = to_enveloped_bytes(&record).unwrap();
+    assert_eq!(registry.decode(&bytes).unwrap(), Stored::Record(record));
+
+    let bytes = to_enveloped_bytes(&Label("y".to_string())).unwrap();
+    assert_eq!(
+        registry.decode(&bytes).unwrap(),
+        Stored::Name("y".to_string())
+    );
+
+    let bytes = to_enveloped_bytes(&RecordV1 { key: 4 }).unwrap();
+    assert_eq!(
+        registry.decode(&bytes).unwrap(),
+        Stored::Record(Record {
+            key: 4,
+            value: None
+        })
+    );
+
+    let envelope = Envelope {
+        type_tag: "Record".to_string(),
+        version: 3,
+        payload: vec![],
+    };
+    assert_eq!(
+        registry.decode_envelope(&envelope),
+        Err(Error::UnsupportedVersion(3))
+    );
+    let envelope = Envelope {
+        type_tag: "Other".to_string(),
+        version: 1,
+        payload: vec![],
+    };
+    assert_eq!(
+        registry.decode(&to_bytes(&envelope).unwrap()),
+        Err(Error::UnexpectedTypeTag("Other".to_string()))
+    );
+    // Payloads are decoded as their registered type.
+    let envelope = Envelope {
+        type_tag: "Record".to_string(),
+        version: 2,
+        payload: vec![1, 0, 0],
+    };
+    assert_eq!(registry.decode_envelope(&envelope), Err(Error::Eof));
+}
+
+#[test]
+#[should_panic(expected = "version 1 of Record is already registered")]
+fn duplicate_registration() {
+    let mut registry = EnvelopeRegistry::new();
+    registry
+        .register(|_: RecordV1| ())
+        .register(|_: RecordV1| ());
+}
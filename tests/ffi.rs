@@ -0,0 +1,320 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "ffi")]
+
+use bcs::{
+    ffi::{BcsBuffer, BCS_OK},
+    test_helpers::UPDATE_GOLDEN_ENV_VAR,
+    to_bytes, ErrorCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serde_reflection::{Tracer, TracerConfig};
+use std::{ffi::CString, os::raw::c_char, ptr};
+
+/// The schema handle, opaque to C programs.
+#[repr(C)]
+struct BcsSchema {
+    _private: [u8; 0],
+}
+
+// Declared like a C program would, and resolved by the linker to the functions of `bcs::ffi`.
+extern "C" {
+    fn bcs_schema_new(
+        registry: *const u8,
+        registry_len: usize,
+        type_name: *const c_char,
+        schema: *mut *mut BcsSchema,
+        error: *mut BcsBuffer,
+    ) -> u16;
+    fn bcs_schema_free(schema: *mut BcsSchema);
+    fn bcs_validate(
+        schema: *const BcsSchema,
+        bytes: *const u8,
+        len: usize,
+        error: *mut BcsBuffer,
+    ) -> u16;
+    fn bcs_decode_to_json(
+        schema: *const BcsSchema,
+        bytes: *const u8,
+        len: usize,
+        json: *mut BcsBuffer,
+        error: *mut BcsBuffer,
+    ) -> u16;
+    fn bcs_encode_from_json(
+        schema: *const BcsSchema,
+        json: *const u8,
+        len: usize,
+        bytes: *mut BcsBuffer,
+        error: *mut BcsBuffer,
+    ) -> u16;
+    fn bcs_buffer_free(buffer: *mut BcsBuffer);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Payload {
+    Transfer { to: Vec<u8>, amount: u64 },
+    Burn(u64),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Transaction {
+    sender: [u8; 4],
+    sequence_number: u64,
+    payload: Payload,
+}
+
+fn transaction() -> (Transaction, Value) {
+    let transaction = Transaction {
+        sender: [1, 2, 3, 4],
+        sequence_number: 7,
+        payload: Payload::Transfer {
+            to: vec![0xab, 0xcd],
+            amount: 1000,
+        },
+    };
+    let value = json!({
+        "sender": "01020304",
+        "sequence_number": "7",
+        "payload": {"Transfer": {"to": "abcd", "amount": "1000"}},
+    });
+    (transaction, value)
+}
+
+fn empty_buffer() -> BcsBuffer {
+    BcsBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+    }
+}
+
+/// Copy the content of a buffer, then release it.
+fn take(mut buffer: BcsBuffer) -> Vec<u8> {
+    let bytes = if buffer.data.is_null() {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(buffer.data, buffer.len) }.to_vec()
+    };
+    unsafe { bcs_buffer_free(&mut buffer) };
+    assert!(buffer.data.is_null());
+    assert_eq!(buffer.len, 0);
+    bytes
+}
+
+fn take_message(buffer: BcsBuffer) -> String {
+    String::from_utf8(take(buffer)).unwrap()
+}
+
+/// A schema handle, released on drop.
+struct Schema(*mut BcsSchema);
+
+impl Schema {
+    fn new(registry: &[u8], type_name: &str) -> Result<Self, (u16, String)> {
+        let type_name = CString::new(type_name).unwrap();
+        let mut schema = ptr::null_mut();
+        let mut error = empty_buffer();
+        let status = unsafe {
+            bcs_schema_new(
+                registry.as_ptr(),
+                registry.len(),
+                type_name.as_ptr(),
+                &mut schema,
+                &mut error,
+            )
+        };
+        let message = take_message(error);
+        if status == BCS_OK {
+            assert!(!schema.is_null());
+            assert_eq!(message, "");
+            Ok(Schema(schema))
+        } else {
+            assert!(schema.is_null());
+            Err((status, message))
+        }
+    }
+
+    fn validate(&self, bytes: &[u8]) -> Result<(), (u16, String)> {
+        let mut error = empty_buffer();
+        let status = unsafe { bcs_validate(self.0, bytes.as_ptr(), bytes.len(), &mut error) };
+        let message = take_message(error);
+        if status == BCS_OK {
+            Ok(())
+        } else {
+            Err((status, message))
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Value, (u16, String)> {
+        let mut json = empty_buffer();
+        let mut error = empty_buffer();
+        let status = unsafe {
+            bcs_decode_to_json(self.0, bytes.as_ptr(), bytes.len(), &mut json, &mut error)
+        };
+        let (json, message) = (take(json), take_message(error));
+        if status == BCS_OK {
+            Ok(serde_json::from_slice(&json).unwrap())
+        } else {
+            assert!(json.is_empty());
+            Err((status, message))
+        }
+    }
+
+    fn encode(&self, json: &[u8]) -> Result<Vec<u8>, (u16, String)> {
+        let mut bytes = empty_buffer();
+        let mut error = empty_buffer();
+        let status = unsafe {
+            bcs_encode_from_json(self.0, json.as_ptr(), json.len(), &mut bytes, &mut error)
+        };
+        let (bytes, message) = (take(bytes), take_message(error));
+        if status == BCS_OK {
+            Ok(bytes)
+        } else {
+            assert!(bytes.is_empty());
+            Err((status, message))
+        }
+    }
+}
+
+impl Drop for Schema {
+    fn drop(&mut self) {
+        unsafe { bcs_schema_free(self.0) };
+    }
+}
+
+fn registry_json() -> Vec<u8> {
+    let mut tracer = Tracer::new(TracerConfig::default());
+    tracer.trace_simple_type::<Payload>().unwrap();
+    tracer.trace_simple_type::<Transaction>().unwrap();
+    serde_json::to_vec(&tracer.registry().unwrap()).unwrap()
+}
+
+fn code(code: ErrorCode) -> u16 {
+    code.as_u16()
+}
+
+#[test]
+fn transcoding() {
+    let schema = Schema::new(&registry_json(), "Transaction").unwrap();
+    let (transaction, value) = transaction();
+    let bytes = to_bytes(&transaction).unwrap();
+
+    assert_eq!(schema.validate(&bytes), Ok(()));
+    assert_eq!(schema.decode(&bytes), Ok(value.clone()));
+    assert_eq!(
+        schema.encode(&serde_json::to_vec(&value).unwrap()),
+        Ok(bytes)
+    );
+}
+
+#[test]
+fn errors() {
+    let schema = Schema::new(&registry_json(), "Transaction").unwrap();
+    let (transaction, _) = transaction();
+    let bytes = to_bytes(&transaction).unwrap();
+
+    assert_eq!(
+        schema.validate(&bytes[..bytes.len() - 1]),
+        Err((code(ErrorCode::Eof), "unexpected end of input".to_string()))
+    );
+    assert_eq!(
+        schema.decode(&[&bytes[..], &[0]].concat()),
+        Err((
+            code(ErrorCode::RemainingInput),
+            "remaining input".to_string()
+        ))
+    );
+    // An empty input may be passed as a null pointer.
+    let mut error = empty_buffer();
+    let status = unsafe { bcs_validate(schema.0, ptr::null(), 0, &mut error) };
+    assert_eq!(status, code(ErrorCode::Eof));
+    assert_eq!(take_message(error), "unexpected end of input");
+
+    let (status, message) = schema.encode(b"{").unwrap_err();
+    assert_eq!(status, code(ErrorCode::Custom));
+    assert!(message.starts_with("invalid JSON"), "{}", message);
+    assert_eq!(
+        schema.encode(br#"{"sender": "01020304"}"#),
+        Err((
+            code(ErrorCode::Custom),
+            "missing field `sequence_number`".to_string()
+        ))
+    );
+
+    // The error buffer is optional.
+    let status = unsafe { bcs_validate(schema.0, [1].as_ptr(), 1, ptr::null_mut()) };
+    assert_eq!(status, code(ErrorCode::Eof));
+}
+
+#[test]
+fn invalid_arguments() {
+    let registry = registry_json();
+    assert_eq!(
+        Schema::new(&registry, "Missing").err(),
+        Some((
+            code(ErrorCode::Custom),
+            "unknown container Missing".to_string()
+        ))
+    );
+    let (status, message) = Schema::new(b"[]", "Transaction").err().unwrap();
+    assert_eq!(status, code(ErrorCode::Custom));
+    assert!(message.starts_with("invalid registry"), "{}", message);
+
+    let mut error = empty_buffer();
+    let status = unsafe { bcs_validate(ptr::null(), [0].as_ptr(), 1, &mut error) };
+    assert_eq!(status, code(ErrorCode::Custom));
+    assert_eq!(take_message(error), "null pointer passed as schema");
+
+    let schema = Schema::new(&registry, "Transaction").unwrap();
+    let mut error = empty_buffer();
+    let status = unsafe { bcs_validate(schema.0, ptr::null(), 1, &mut error) };
+    assert_eq!(status, code(ErrorCode::Custom));
+    assert_eq!(take_message(error), "null pointer passed as bytes");
+
+    let mut error = empty_buffer();
+    let status =
+        unsafe { bcs_decode_to_json(schema.0, [0].as_ptr(), 1, ptr::null_mut(), &mut error) };
+    assert_eq!(status, code(ErrorCode::Custom));
+    assert_eq!(take_message(error), "null pointer passed as json");
+
+    // Releasing null pointers and empty buffers does nothing.
+    unsafe {
+        bcs_schema_free(ptr::null_mut());
+        bcs_buffer_free(ptr::null_mut());
+        bcs_buffer_free(&mut empty_buffer());
+    }
+}
+
+#[test]
+fn header_is_up_to_date() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/include/bcs.h");
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        include_guard: Some("BCS_H".to_string()),
+        autogen_warning: Some(
+            "/* Generated with cbindgen from src/ffi.rs. Do not edit. */".to_string(),
+        ),
+        cpp_compat: true,
+        usize_is_size_t: true,
+        documentation_style: cbindgen::DocumentationStyle::Doxy,
+        ..Default::default()
+    };
+    let bindings = cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(concat!(env!("CARGO_MANIFEST_DIR"), "/src/ffi.rs"))
+        .generate()
+        .unwrap();
+    let mut header = Vec::new();
+    bindings.write(&mut header);
+    let header = String::from_utf8(header).unwrap();
+
+    if std::env::var_os(UPDATE_GOLDEN_ENV_VAR).is_some() {
+        std::fs::write(path, &header).unwrap();
+    }
+    let expected = std::fs::read_to_string(path).unwrap_or_default();
+    assert!(
+        header == expected,
+        "include/bcs.h is out of date, run the tests with {}=1 to regenerate it",
+        UPDATE_GOLDEN_ENV_VAR
+    );
+}
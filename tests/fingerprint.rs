@@ -0,0 +1,274 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "reflection")]
+// The types below are only traced, never constructed.
+#![allow(dead_code)]
+
+use bcs::{
+    trace::{trace_type, FormatTracer},
+    Error, Fingerprint, Uleb32,
+};
+use serde::Deserialize;
+use serde_reflection::{ContainerFormat, Format, Registry, Samples, Tracer, TracerConfig};
+use std::collections::BTreeMap;
+
+// Successive versions of the same message.
+mod v1 {
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub struct Message {
+        pub id: u64,
+        pub payload: Vec<u8>,
+        pub kind: Kind,
+    }
+
+    #[derive(Deserialize)]
+    pub enum Kind {
+        Request,
+        Response { status: u16 },
+    }
+}
+
+mod reordered {
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub struct Message {
+        pub payload: Vec<u8>,
+        pub id: u64,
+        pub kind: v1::Kind,
+    }
+}
+
+mod renamed {
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub struct Message {
+        pub id: u64,
+        pub data: Vec<u8>,
+        pub kind: v1::Kind,
+    }
+}
+
+mod extended {
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub struct Message {
+        pub id: u64,
+        pub payload: Vec<u8>,
+        pub kind: Kind,
+    }
+
+    #[derive(Deserialize)]
+    pub enum Kind {
+        Request,
+        Response { status: u16 },
+        Notification,
+    }
+}
+
+#[derive(Deserialize)]
+enum Chain {
+    End,
+    Link(u8, Box<Chain>),
+}
+
+#[derive(Deserialize)]
+struct Tree {
+    label: Option<String>,
+    children: Vec<Tree>,
+    index: BTreeMap<String, (Tree, Tree)>,
+}
+
+#[derive(Deserialize)]
+struct Outer {
+    inner: Inner,
+    chain: Chain,
+    pair: (u32, u32),
+    triple: (bool, u8, bool),
+}
+
+#[derive(Deserialize)]
+enum Inner {
+    A(u8),
+    B { flag: bool, chain: Box<Chain> },
+    C,
+    D(Option<Box<Inner>>, i128),
+}
+
+#[derive(Deserialize)]
+struct Measure {
+    value: f64,
+}
+
+/// The fingerprint of `Message`, after tracing the enum `Kind` that it contains.
+fn fingerprint<Message, Kind>() -> Fingerprint
+where
+    Message: Deserialize<'static>,
+    Kind: Deserialize<'static>,
+{
+    let mut tracer = FormatTracer::new();
+    tracer.trace_type::<Kind>().unwrap();
+    let format = tracer.trace_type::<Message>().unwrap();
+    Fingerprint::of_format(&format, &tracer.registry().unwrap()).unwrap()
+}
+
+#[test]
+fn fingerprints_are_deterministic() {
+    let expected = fingerprint::<v1::Message, v1::Kind>();
+    assert_eq!(expected, fingerprint::<v1::Message, v1::Kind>());
+    assert_eq!(expected.to_string().len(), 64);
+    assert_eq!(
+        expected.to_string(),
+        expected
+            .as_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()
+    );
+}
+
+#[test]
+fn layout_changes_change_the_fingerprint() {
+    let expected = fingerprint::<v1::Message, v1::Kind>();
+    assert_ne!(expected, fingerprint::<reordered::Message, v1::Kind>());
+    assert_ne!(expected, fingerprint::<extended::Message, extended::Kind>());
+    // Names are part of the fingerprint.
+    assert_ne!(expected, fingerprint::<renamed::Message, v1::Kind>());
+}
+
+#[test]
+fn nested_enums_are_traced_first() {
+    let mut tracer = FormatTracer::new();
+    tracer.trace_type::<Chain>().unwrap();
+    tracer.trace_type::<Inner>().unwrap();
+    let format = tracer.trace_type::<Outer>().unwrap();
+    assert_eq!(format, Format::TypeName("Outer".to_string()));
+    let registry = tracer.registry().unwrap();
+
+    let mut tracer = Tracer::new(TracerConfig::default());
+    let samples = Samples::new();
+    tracer.trace_type::<Chain>(&samples).unwrap();
+    tracer.trace_type::<Inner>(&samples).unwrap();
+    tracer.trace_type::<Outer>(&samples).unwrap();
+    assert_eq!(registry, tracer.registry().unwrap());
+
+    assert_eq!(
+        trace_type::<Outer>(),
+        Err(Error::Custom(
+            "missing variants of enums Chain, Inner: nested enums must be traced first".to_string()
+        ))
+    );
+}
+
+#[test]
+fn recursive_types_are_traced() {
+    let (_, registry) = trace_type::<Chain>().unwrap();
+    match &registry["Chain"] {
+        ContainerFormat::Enum(variants) => assert_eq!(variants.len(), 2),
+        format => panic!("unexpected format {:?}", format),
+    }
+
+    let (_, registry) = trace_type::<Tree>().unwrap();
+    let mut tracer = Tracer::new(TracerConfig::default());
+    tracer.trace_simple_type::<Tree>().unwrap();
+    assert_eq!(registry, tracer.registry().unwrap());
+}
+
+#[test]
+fn uleb128_integers_change_the_fingerprint() {
+    mod fixed {
+        #[derive(serde::Deserialize)]
+        pub struct Counter {
+            pub value: u32,
+        }
+    }
+    mod uleb128 {
+        #[derive(serde::Deserialize)]
+        pub struct Counter {
+            pub value: bcs::Uleb32,
+        }
+    }
+
+    let (_, registry) = trace_type::<uleb128::Counter>().unwrap();
+    assert!(registry.contains_key("$bcs::Uleb32"));
+    assert_ne!(
+        bcs::fingerprint::<fixed::Counter>().unwrap(),
+        bcs::fingerprint::<uleb128::Counter>().unwrap()
+    );
+    assert_ne!(
+        bcs::fingerprint::<u32>().unwrap(),
+        bcs::fingerprint::<Uleb32>().unwrap()
+    );
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn pinned_tags_are_traced() {
+    use bcs::{BcsDeserialize, BcsSerialize};
+    use serde_reflection::{Named, VariantFormat};
+
+    #[derive(BcsSerialize, BcsDeserialize)]
+    enum Command {
+        Get(u64),
+        #[bcs(tag = 5)]
+        Put(u64, Vec<u8>),
+        Delete(u64),
+    }
+
+    let (_, registry) = trace_type::<Command>().unwrap();
+    let indices: Vec<_> = match &registry["Command"] {
+        ContainerFormat::Enum(variants) => variants
+            .iter()
+            .map(|(index, Named { name, .. })| (*index, name.as_str()))
+            .collect(),
+        format => panic!("unexpected format {:?}", format),
+    };
+    assert_eq!(indices, vec![(0, "Get"), (5, "Put"), (6, "Delete")]);
+
+    let mut tracer = FormatTracer::new();
+    tracer.trace_type::<Command>().unwrap();
+    tracer.trace_type::<Vec<Command>>().unwrap();
+    assert_eq!(tracer.registry().unwrap(), registry);
+    assert!(matches!(
+        &registry["Command"],
+        ContainerFormat::Enum(variants)
+            if variants[&6].value == VariantFormat::NewType(Box::new(Format::U64))
+    ));
+}
+
+#[test]
+fn unsupported_types() {
+    assert_eq!(
+        bcs::fingerprint::<Measure>(),
+        Err(Error::NotSupported("deserialize_f64"))
+    );
+    assert_eq!(
+        bcs::fingerprint::<Vec<char>>(),
+        Err(Error::NotSupported("deserialize_char"))
+    );
+}
+
+#[test]
+fn fingerprints_hash_the_serialized_format() {
+    // SHA3-256 of the BCS bytes of the format and its registry, computed independently.
+    let expected = Fingerprint::of_format(&Format::U8, &Registry::new()).unwrap();
+    assert_eq!(
+        expected.to_string(),
+        "f778c16419b164b6d58c9aa7365c4709f75994d6a10c5c3e09148de0e6135026"
+    );
+    assert_eq!(expected, bcs::fingerprint::<u8>().unwrap());
+
+    // More than one block of input.
+    let format = Format::TypeName("x".repeat(200));
+    assert_eq!(
+        Fingerprint::of_format(&format, &Registry::new())
+            .unwrap()
+            .to_string(),
+        "13096fc361a473efdbef19891dcc02438ad3f44b9a91d9cf55c5021fd3b209c7"
+    );
+}
@@ -0,0 +1,133 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::{from_bytes, serialized_size, to_bytes, BcsSize, Error, FixedBytes};
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Signed<'a> {
+    #[serde(with = "bcs::fixed_bytes")]
+    public_key: [u8; 96],
+    #[serde(borrow, with = "bcs::fixed_bytes::borrowed")]
+    signature: &'a [u8; 64],
+    nonce: FixedBytes<40>,
+    payload: Vec<u8>,
+}
+
+#[test]
+fn no_length_prefix() {
+    let signature = [2; 64];
+    let signed = Signed {
+        public_key: [1; 96],
+        signature: &signature,
+        nonce: FixedBytes([3; 40]),
+        payload: vec![4, 5],
+    };
+    let bytes = to_bytes(&signed).unwrap();
+    let mut expected = vec![1; 96];
+    expected.extend_from_slice(&[2; 64]);
+    expected.extend_from_slice(&[3; 40]);
+    expected.extend_from_slice(&[2, 4, 5]);
+    assert_eq!(bytes, expected);
+    assert_eq!(from_bytes::<Signed>(&bytes).unwrap(), signed);
+    assert_eq!(serialized_size(&signed).unwrap(), bytes.len());
+
+    assert_eq!(to_bytes(&FixedBytes([])).unwrap(), Vec::<u8>::new());
+    assert_eq!(from_bytes::<FixedBytes<0>>(&[]).unwrap(), FixedBytes([]));
+}
+
+#[test]
+fn borrowed_from_input() {
+    let bytes = to_bytes(&Signed {
+        public_key: [0; 96],
+        signature: &[9; 64],
+        nonce: FixedBytes::default(),
+        payload: Vec::new(),
+    })
+    .unwrap();
+    let signed = from_bytes::<Signed>(&bytes).unwrap();
+    assert!(std::ptr::eq(
+        signed.signature.as_ptr(),
+        bytes[96..].as_ptr()
+    ));
+}
+
+#[test]
+fn truncated_and_trailing_input() {
+    assert_eq!(from_bytes::<FixedBytes<64>>(&[0; 63]), Err(Error::Eof));
+    assert_eq!(
+        from_bytes::<FixedBytes<64>>(&[0; 65]),
+        Err(Error::RemainingInput)
+    );
+    assert_eq!(from_bytes::<Signed>(&[0; 96 + 63]), Err(Error::Eof));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn other_formats() {
+    let nonce = FixedBytes([7; 40]);
+    let json = serde_json::to_string(&nonce).unwrap();
+    assert_eq!(json, serde_json::to_string(&vec![7; 40]).unwrap());
+    assert_eq!(
+        serde_json::from_str::<FixedBytes<40>>(&json).unwrap(),
+        nonce
+    );
+    assert!(serde_json::from_str::<FixedBytes<41>>(&json).is_err());
+}
+
+proptest! {
+    #[test]
+    fn proptest_fixed_bytes(bytes in proptest::collection::vec(any::<u8>(), 100)) {
+        let mut array = [0; 100];
+        array.copy_from_slice(&bytes);
+        let value = FixedBytes(array);
+        prop_assert_eq!(to_bytes(&value)?, bytes.clone());
+        prop_assert_eq!(from_bytes::<FixedBytes<100>>(&bytes)?, value);
+        prop_assert_eq!(bcs::codec::encode(&value)?, bytes.clone());
+        prop_assert_eq!(bcs::codec::decode::<FixedBytes<100>>(&bytes)?, value);
+        prop_assert_eq!(serialized_size(&value)?, 100);
+        prop_assert_eq!(value.bcs_size(), 100);
+        prop_assert_eq!(FixedBytes::<100>::FIXED_SIZE, Some(100));
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn registries() {
+    use bcs::json::{bcs_to_json, json_to_bcs};
+    use serde_json::json;
+    use serde_reflection::{ContainerFormat, Format, Samples, Tracer, TracerConfig};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Keys {
+        public_key: FixedBytes<48>,
+        signatures: Vec<FixedBytes<96>>,
+    }
+
+    let mut tracer = Tracer::new(TracerConfig::default());
+    tracer.trace_type::<Keys>(&Samples::new()).unwrap();
+    let registry = tracer.registry().unwrap();
+    assert_eq!(
+        registry.get("$bcs::FixedBytes<96>"),
+        Some(&ContainerFormat::NewTypeStruct(Box::new(
+            Format::TupleArray {
+                content: Box::new(Format::U8),
+                size: 96,
+            }
+        )))
+    );
+
+    let keys = Keys {
+        public_key: FixedBytes([0xab; 48]),
+        signatures: vec![FixedBytes([0xcd; 96])],
+    };
+    let bytes = to_bytes(&keys).unwrap();
+    let format = Format::TypeName("Keys".to_string());
+    let value = json!({
+        "public_key": "ab".repeat(48),
+        "signatures": ["cd".repeat(96)],
+    });
+    assert_eq!(bcs_to_json(&bytes, &format, &registry).unwrap(), value);
+    assert_eq!(json_to_bcs(&value, &format, &registry).unwrap(), bytes);
+}
@@ -0,0 +1,125 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "fuzzing")]
+
+use arbitrary::Unstructured;
+use bcs::fuzzing::{
+    arbitrary_malformed_bytes, arbitrary_valid_bytes, malformed_bytes, valid_bytes, Defect,
+};
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_reflection::{Format, Registry, Tracer, TracerConfig};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum List<T> {
+    Empty,
+    Node(T, Box<List<T>>),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Message {
+    Ping,
+    Data(Vec<u8>),
+    Close { reason: Option<String>, fatal: bool },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Batch {
+    id: u64,
+    messages: List<Message>,
+    tags: BTreeMap<String, (i16, [u8; 2])>,
+    peers: Vec<u32>,
+}
+
+fn trace<T>() -> (Format, Registry)
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut tracer = Tracer::new(TracerConfig::default());
+    // Enums nested in `T` must be traced first.
+    tracer.trace_simple_type::<Message>().unwrap();
+    tracer.trace_simple_type::<List<Message>>().unwrap();
+    let (format, _) = tracer.trace_simple_type::<T>().unwrap();
+    (format, tracer.registry().unwrap())
+}
+
+proptest! {
+    #[test]
+    fn valid_bytes_are_canonical(bytes in valid_bytes(trace::<Batch>().0, trace::<Batch>().1)) {
+        let value: Batch = bcs::from_bytes(&bytes).unwrap();
+        prop_assert_eq!(bcs::to_bytes(&value).unwrap(), bytes);
+    }
+
+    #[test]
+    fn malformed_bytes_are_rejected(input in malformed_bytes(trace::<Batch>().0, trace::<Batch>().1)) {
+        prop_assert_eq!(bcs::from_bytes::<Batch>(&input.bytes), Err(input.expected_error));
+    }
+}
+
+#[test]
+fn every_defect_is_generated() {
+    let (format, registry) = trace::<Batch>();
+    let mut defects = BTreeSet::new();
+    for seed in 0..2000u32 {
+        // A xorshift generator is enough to explore the choices of the generator.
+        let mut state = seed.wrapping_mul(2_654_435_761) | 1;
+        let entropy: Vec<u8> = (0..512)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let input = arbitrary_malformed_bytes(&mut Unstructured::new(&entropy), &format, &registry)
+            .unwrap();
+        assert_eq!(
+            bcs::from_bytes::<Batch>(&input.bytes),
+            Err(input.expected_error.clone()),
+            "{:?}",
+            input
+        );
+        defects.insert(input.defect);
+    }
+    for defect in &[
+        Defect::NonMinimalUleb128,
+        Defect::Uleb128Overflow,
+        Defect::UnsortedMapKeys,
+        Defect::DuplicateMapKeys,
+        Defect::InvalidBool,
+        Defect::InvalidOptionTag,
+        Defect::InvalidVariantIndex,
+        Defect::InvalidUtf8,
+        Defect::Truncated,
+        Defect::TrailingBytes,
+    ] {
+        assert!(defects.contains(defect), "{:?} was never generated", defect);
+    }
+}
+
+#[test]
+fn empty_entropy_gives_smallest_value() {
+    let (format, registry) = trace::<Batch>();
+    let bytes = arbitrary_valid_bytes(&mut Unstructured::new(&[]), &format, &registry).unwrap();
+    let value: Batch = bcs::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        value,
+        Batch {
+            id: 0,
+            messages: List::Empty,
+            tags: BTreeMap::new(),
+            peers: Vec::new(),
+        }
+    );
+}
+
+#[test]
+fn unsupported_formats() {
+    let (format, registry) = trace::<(u8, f32)>();
+    assert_eq!(
+        arbitrary_valid_bytes(&mut Unstructured::new(&[1, 2, 3]), &format, &registry),
+        Err(arbitrary::Error::IncorrectFormat)
+    );
+}
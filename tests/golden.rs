@@ -0,0 +1,112 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::test_helpers::{GoldenFile, UPDATE_GOLDEN_ENV_VAR};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+};
+
+#[derive(Debug, Serialize)]
+enum Message {
+    Ping,
+    Payload { id: u64, data: Vec<u8> },
+}
+
+#[derive(Debug, Serialize)]
+struct Envelope {
+    sender: [u8; 4],
+    message: Message,
+    tags: BTreeMap<String, Option<u16>>,
+}
+
+fn known_vectors() -> GoldenFile {
+    let mut tags = BTreeMap::new();
+    tags.insert("zeta".to_string(), None);
+    tags.insert("alpha".to_string(), Some(0x1234));
+
+    let mut golden = GoldenFile::new("tests/golden/known_vectors.hex");
+    golden
+        .add("bool", &true)
+        .add("i16", &-4660i16)
+        .add("u64", &0x1234_5678_abcd_ef00u64)
+        .add("uleb128_length", &vec![(); 9487])
+        .add("string", "çå∞≠¢õß∂ƒ∫")
+        .add("unit_variant", &Message::Ping)
+        .add(
+            "envelope",
+            &Envelope {
+                sender: [192, 168, 1, 1],
+                message: Message::Payload {
+                    id: 7,
+                    data: vec![0xc0, 0xde],
+                },
+                tags,
+            },
+        );
+    golden
+}
+
+#[test]
+fn known_vectors_are_stable() {
+    known_vectors().check();
+}
+
+fn temp_golden_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("bcs-golden-{}", std::process::id()))
+        .join(name)
+}
+
+#[test]
+fn update_then_verify() {
+    let path = temp_golden_path("update_then_verify.hex");
+    let mut golden = GoldenFile::new(&path);
+    golden.add("pair", &(1u8, 2u16));
+    golden.update();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "# BCS golden file. Regenerate with BCS_UPDATE_GOLDEN=1.\npair: 010200\n"
+    );
+    golden.verify();
+}
+
+#[test]
+#[should_panic(expected = "  entry `value` changed:
+    value:    (1, 3)
+    expected: 010200
+    actual:   010300
+                ^^ first difference at byte 1 (expected 3 bytes, got 3)
+  entry `removed` is no longer recorded:
+    expected: 00
+")]
+fn drift_is_reported() {
+    let path = temp_golden_path("drift_is_reported.hex");
+    let mut golden = GoldenFile::new(&path);
+    golden.add("value", &(1u8, 2u16)).add("removed", &false);
+    golden.update();
+
+    let mut golden = GoldenFile::new(&path);
+    golden.add("value", &(1u8, 3u16));
+    golden.verify();
+}
+
+#[test]
+fn zero_or_empty_does_not_update() {
+    // Leave the variable alone while golden files are being regenerated.
+    if std::env::var_os(UPDATE_GOLDEN_ENV_VAR).is_some() {
+        return;
+    }
+    let path = temp_golden_path("zero_or_empty_does_not_update.hex");
+    let mut golden = GoldenFile::new(&path);
+    golden.add("value", &1u8);
+    for value in &["0", ""] {
+        std::env::set_var(UPDATE_GOLDEN_ENV_VAR, value);
+        let result = catch_unwind(AssertUnwindSafe(|| golden.check()));
+        std::env::remove_var(UPDATE_GOLDEN_ENV_VAR);
+        assert!(result.is_err(), "{:?}", value);
+        assert!(!path.exists(), "{:?}", value);
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "json")]
+
+use bcs::{
+    json::{bcs_to_json, json_to_bcs},
+    to_bytes, Error,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serde_reflection::{Format, Registry, Samples, Tracer, TracerConfig};
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Account(Vec<u8>);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Marker;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Pair(i8, u16);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Action {
+    Noop,
+    Pay(u64),
+    Swap(Pair, bool),
+    Send { to: Account, amount: u128 },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Request {
+    id: i64,
+    sender: [u8; 4],
+    key: Vec<u8>,
+    actions: Vec<Action>,
+    labels: BTreeMap<String, u32>,
+    weights: BTreeMap<u16, i32>,
+    memo: Option<String>,
+    marker: Marker,
+    pair: (u8, i128),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum List {
+    Nil,
+    Cons(Box<List>),
+}
+
+fn registry() -> Registry {
+    let mut tracer = Tracer::new(TracerConfig::default());
+    let samples = Samples::new();
+    tracer.trace_type::<Action>(&samples).unwrap();
+    tracer.trace_type::<Request>(&samples).unwrap();
+    tracer.trace_type::<List>(&samples).unwrap();
+    tracer.registry().unwrap()
+}
+
+fn request_format() -> Format {
+    Format::TypeName("Request".to_string())
+}
+
+fn sample() -> (Request, Value) {
+    let request = Request {
+        id: -5,
+        sender: [0xde, 0xad, 0xbe, 0xef],
+        key: vec![0, 1, 0xff],
+        actions: vec![
+            Action::Noop,
+            Action::Pay(u64::MAX),
+            Action::Swap(Pair(-1, 300), true),
+            Action::Send {
+                to: Account(vec![]),
+                amount: 1 << 100,
+            },
+        ],
+        labels: vec![("b".to_string(), 2), ("ab".to_string(), 1)]
+            .into_iter()
+            .collect(),
+        weights: vec![(256, -3), (1, 4)].into_iter().collect(),
+        memo: None,
+        marker: Marker,
+        pair: (7, i128::MIN),
+    };
+    let value = json!({
+        "id": "-5",
+        "sender": "deadbeef",
+        "key": "0001ff",
+        "actions": [
+            {"Noop": null},
+            {"Pay": "18446744073709551615"},
+            {"Swap": [[-1, 300], true]},
+            {"Send": {"to": "", "amount": "1267650600228229401496703205376"}},
+        ],
+        "labels": {"ab": 1, "b": 2},
+        "weights": [[256, -3], [1, 4]],
+        "memo": null,
+        "marker": null,
+        "pair": [7, "-170141183460469231731687303715884105728"],
+    });
+    (request, value)
+}
+
+#[test]
+fn transcoding() {
+    let registry = registry();
+    let (request, value) = sample();
+    let bytes = to_bytes(&request).unwrap();
+    assert_eq!(
+        json_to_bcs(&value, &request_format(), &registry).unwrap(),
+        bytes
+    );
+    assert_eq!(
+        bcs_to_json(&bytes, &request_format(), &registry).unwrap(),
+        value
+    );
+
+    let format = Format::Option(Box::new(Format::Str));
+    assert_eq!(
+        json_to_bcs(&json!("x"), &format, &registry),
+        Ok(vec![1, 1, b'x'])
+    );
+    assert_eq!(bcs_to_json(&[0], &format, &registry), Ok(Value::Null));
+}
+
+#[test]
+fn maps_are_sorted() {
+    let registry = registry();
+    let format = Format::Map {
+        key: Box::new(Format::U16),
+        value: Box::new(Format::Bool),
+    };
+    // Entries are sorted by the bytes of their keys.
+    let map: BTreeMap<u16, bool> = vec![(256, true), (1, false), (2, true)]
+        .into_iter()
+        .collect();
+    let value = json!([[2, true], [1, false], [256, true]]);
+    let bytes = json_to_bcs(&value, &format, &registry).unwrap();
+    assert_eq!(bytes, to_bytes(&map).unwrap());
+    assert_eq!(
+        bcs_to_json(&bytes, &format, &registry).unwrap(),
+        json!([[256, true], [1, false], [2, true]])
+    );
+
+    assert_eq!(
+        json_to_bcs(&json!([[1, true], [1, false]]), &format, &registry),
+        Err(Error::NonCanonicalMap)
+    );
+    assert_eq!(
+        bcs_to_json(&[2, 2, 0, 1, 1, 0, 0], &format, &registry),
+        Err(Error::NonCanonicalMap)
+    );
+}
+
+#[test]
+fn canonical_conventions() {
+    let registry = registry();
+    let invalid = vec![
+        (json!(1), Format::U64),
+        (json!("+1"), Format::U64),
+        (json!("01"), Format::U64),
+        (json!("-0"), Format::I64),
+        (json!("18446744073709551616"), Format::U64),
+        (json!("1"), Format::U32),
+        (json!(256), Format::U8),
+        (json!(-1), Format::U16),
+        (json!(1.5), Format::I32),
+        (json!("abc"), Format::Bytes),
+        (json!("+f"), Format::Bytes),
+        (json!("\u{e9}0"), Format::Bytes),
+        (json!("0x00"), Format::Bytes),
+        (
+            json!("00"),
+            Format::TupleArray {
+                content: Box::new(Format::U8),
+                size: 2,
+            },
+        ),
+        (json!(null), Format::Bool),
+        (json!([1]), Format::Tuple(vec![Format::U8, Format::U8])),
+    ];
+    for (value, format) in invalid {
+        let error = json_to_bcs(&value, &format, &registry).unwrap_err();
+        assert!(matches!(error, Error::Custom(_)), "{} {:?}", value, format);
+    }
+    assert_eq!(
+        json_to_bcs(&json!("00FF"), &Format::Bytes, &registry),
+        Ok(vec![2, 0, 0xff])
+    );
+    assert_eq!(
+        json_to_bcs(&json!("0"), &Format::I128, &registry),
+        Ok(vec![0; 16])
+    );
+
+    assert_eq!(
+        json_to_bcs(&json!(1.0), &Format::F64, &registry),
+        Err(Error::NotSupported("f64"))
+    );
+    let nested = Format::Option(Box::new(Format::Option(Box::new(Format::U8))));
+    assert!(json_to_bcs(&json!(null), &nested, &registry)
+        .unwrap_err()
+        .is_unsupported_type());
+}
+
+#[test]
+fn containers() {
+    let registry = registry();
+    let (_, value) = sample();
+    let mut missing = value.clone();
+    missing.as_object_mut().unwrap().remove("memo");
+    assert_eq!(
+        json_to_bcs(&missing, &request_format(), &registry),
+        Err(Error::Custom("missing field `memo`".to_string()))
+    );
+    let mut unknown = value;
+    unknown["extra"] = json!(1);
+    assert_eq!(
+        json_to_bcs(&unknown, &request_format(), &registry),
+        Err(Error::Custom("unknown field `extra`".to_string()))
+    );
+
+    let action = Format::TypeName("Action".to_string());
+    assert_eq!(
+        json_to_bcs(&json!({"Burn": "1"}), &action, &registry),
+        Err(Error::Custom(
+            "unknown variant `Burn` of Action".to_string()
+        ))
+    );
+    assert!(json_to_bcs(&json!({"Noop": null, "Pay": "1"}), &action, &registry).is_err());
+    assert!(json_to_bcs(&json!("Noop"), &action, &registry).is_err());
+    assert_eq!(
+        bcs_to_json(&[4], &action, &registry),
+        Err(Error::Custom(
+            "invalid value: integer `4`, expected variant index 0 <= i < 4".to_string()
+        ))
+    );
+    assert_eq!(
+        bcs_to_json(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0], &action, &registry),
+        Err(Error::RemainingInput)
+    );
+    assert!(json_to_bcs(
+        &json!(null),
+        &Format::TypeName("Missing".to_string()),
+        &registry
+    )
+    .is_err());
+}
+
+#[test]
+fn container_depth() {
+    let registry = registry();
+    let list = Format::TypeName("List".to_string());
+    let mut value = json!({"Nil": null});
+    for _ in 1..bcs::MAX_CONTAINER_DEPTH {
+        value = json!({ "Cons": value });
+    }
+    let bytes = json_to_bcs(&value, &list, &registry).unwrap();
+    assert_eq!(bcs_to_json(&bytes, &list, &registry).unwrap(), value);
+
+    let value = json!({ "Cons": value });
+    let error = json_to_bcs(&value, &list, &registry).unwrap_err();
+    assert_eq!(error, Error::ExceededContainerDepthLimit("List"));
+    assert!(error.is_limit_exceeded());
+    let bytes = [&[1; bcs::MAX_CONTAINER_DEPTH][..], &[0]].concat();
+    assert_eq!(
+        bcs_to_json(&bytes, &list, &registry),
+        Err(Error::ExceededContainerDepthLimit("List"))
+    );
+}
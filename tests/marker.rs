@@ -0,0 +1,87 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "derive")]
+
+use bcs::{assert_bcs_compatible, to_bytes, BcsCompatible};
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BinaryHeap, HashSet, LinkedList, VecDeque},
+    marker::PhantomData,
+    rc::Rc,
+    sync::Arc,
+};
+
+#[derive(BcsCompatible, Serialize)]
+struct Unit;
+
+#[derive(BcsCompatible, Serialize)]
+struct Point(i32, i32);
+
+#[derive(BcsCompatible, Serialize)]
+#[serde(rename = "Figure")]
+enum Shape {
+    Empty,
+    Dot(Point),
+    Polygon { points: Vec<Point>, closed: bool },
+    Labels(BTreeMap<String, Option<u64>>),
+    Nested(Box<Shape>),
+}
+
+#[derive(BcsCompatible, Serialize)]
+struct Generic<'a, T> {
+    values: [T; 2],
+    name: &'a str,
+    tags: HashSet<(u8, u128)>,
+    marker: PhantomData<T>,
+}
+
+assert_bcs_compatible!(
+    Unit,
+    Shape,
+    Generic<'static, Shape>,
+    Vec<Option<(String, [u8; 32])>>,
+    Box<[i64]>,
+    VecDeque<Point>,
+    LinkedList<Shape>,
+    BinaryHeap<u32>,
+    Cow<'static, str>,
+    Cow<'static, [u64]>,
+    Rc<Shape>,
+    Arc<[u8]>,
+);
+
+fn serialize<T: BcsCompatible + Serialize>(value: &T) -> Vec<u8> {
+    to_bytes(value).unwrap()
+}
+
+#[test]
+fn compatible_types_serialize() {
+    let shape = Shape::Polygon {
+        points: vec![Point(1, 2), Point(3, 4)],
+        closed: true,
+    };
+    assert_eq!(serialize(&shape), to_bytes(&shape).unwrap());
+
+    let shapes = vec![Shape::Dot(Point(0, 0)), Shape::Labels(BTreeMap::new())];
+    assert_eq!(serialize(&shapes), to_bytes(&shapes).unwrap());
+
+    let generic = Generic {
+        values: [Shape::Empty, Shape::Nested(Box::new(shape))],
+        name: "a",
+        tags: vec![(1, 2)].into_iter().collect(),
+        marker: PhantomData,
+    };
+    assert_eq!(serialize(&generic), to_bytes(&generic).unwrap());
+    assert_eq!(serialize(&Unit), Vec::<u8>::new());
+
+    let points: VecDeque<_> = vec![Point(1, 2), Point(3, 4)].into_iter().collect();
+    assert_eq!(serialize(&points), to_bytes(&points).unwrap());
+    let shapes: LinkedList<_> = vec![Shape::Empty].into_iter().collect();
+    assert_eq!(serialize(&shapes), to_bytes(&shapes).unwrap());
+    let heap: BinaryHeap<_> = vec![3u32, 1, 2].into_iter().collect();
+    assert_eq!(serialize(&heap), to_bytes(&heap).unwrap());
+    let name: Cow<str> = Cow::Borrowed("a");
+    assert_eq!(serialize(&name), to_bytes("a").unwrap());
+}
@@ -0,0 +1,158 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::{
+    migration::{
+        from_bytes_with_fallback, from_versioned_bytes, to_versioned_bytes, Initial, Versioned,
+    },
+    to_bytes, Error,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct UserV1 {
+    name: String,
+}
+
+impl Versioned for UserV1 {
+    const VERSION: u32 = 1;
+    type Previous = Initial;
+
+    fn migrate(previous: Initial) -> Self {
+        match previous {}
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct UserV2 {
+    name: String,
+    age: u8,
+}
+
+impl Versioned for UserV2 {
+    const VERSION: u32 = 2;
+    type Previous = UserV1;
+
+    fn migrate(previous: UserV1) -> Self {
+        UserV2 {
+            name: previous.name,
+            age: 0,
+        }
+    }
+}
+
+// Version 3 was never released.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+    age: u8,
+    emails: Vec<String>,
+}
+
+impl Versioned for User {
+    const VERSION: u32 = 4;
+    type Previous = UserV2;
+
+    fn migrate(previous: UserV2) -> Self {
+        User {
+            name: previous.name,
+            age: previous.age,
+            emails: Vec::new(),
+        }
+    }
+}
+
+fn user(name: &str, age: u8) -> User {
+    User {
+        name: name.to_string(),
+        age,
+        emails: Vec::new(),
+    }
+}
+
+#[test]
+fn fallback_through_previous_layouts() {
+    let current = User {
+        name: "a".to_string(),
+        age: 30,
+        emails: vec!["a@b".to_string()],
+    };
+    let bytes = to_bytes(&current).unwrap();
+    assert_eq!(from_bytes_with_fallback::<User>(&bytes), Ok((current, 4)));
+
+    let bytes = to_bytes(&UserV2 {
+        name: "b".to_string(),
+        age: 40,
+    })
+    .unwrap();
+    assert_eq!(
+        from_bytes_with_fallback::<User>(&bytes),
+        Ok((user("b", 40), 2))
+    );
+
+    let bytes = to_bytes(&UserV1 {
+        name: "c".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+        from_bytes_with_fallback::<User>(&bytes),
+        Ok((user("c", 0), 1))
+    );
+    assert_eq!(
+        from_bytes_with_fallback::<UserV2>(&bytes).map(|(value, version)| (value.age, version)),
+        Ok((0, 1))
+    );
+
+    // The error of the current layout is reported.
+    assert_eq!(
+        from_bytes_with_fallback::<User>(&[1, 0xff]),
+        Err(Error::Utf8)
+    );
+    assert_eq!(from_bytes_with_fallback::<User>(&[]), Err(Error::Eof));
+}
+
+#[test]
+fn version_prefixes() {
+    let bytes = to_versioned_bytes(&UserV1 {
+        name: "d".to_string(),
+    })
+    .unwrap();
+    assert_eq!(bytes, vec![1, 1, b'd']);
+    assert_eq!(from_versioned_bytes::<User>(&bytes), Ok((user("d", 0), 1)));
+    assert_eq!(
+        from_versioned_bytes::<UserV1>(&bytes),
+        Ok((
+            UserV1 {
+                name: "d".to_string()
+            },
+            1
+        ))
+    );
+
+    let current = user("e", 5);
+    let bytes = to_versioned_bytes(&current).unwrap();
+    assert_eq!(bytes[0], 4);
+    assert_eq!(from_versioned_bytes::<User>(&bytes), Ok((current, 4)));
+    // Newer versions are unknown to older types.
+    assert_eq!(
+        from_versioned_bytes::<UserV2>(&bytes),
+        Err(Error::UnsupportedVersion(4))
+    );
+
+    // Versions outside of the chain.
+    for version in &[0, 3, 5, 0x7f] {
+        assert_eq!(
+            from_versioned_bytes::<User>(&[*version, 1, b'x']),
+            Err(Error::UnsupportedVersion(u32::from(*version)))
+        );
+    }
+    assert_eq!(
+        from_versioned_bytes::<User>(&[0x80, 0x01]),
+        Err(Error::UnsupportedVersion(128))
+    );
+    assert_eq!(from_versioned_bytes::<User>(&[]), Err(Error::Eof));
+    assert_eq!(
+        from_versioned_bytes::<User>(&[2, 1, b'f', 7, 0]),
+        Err(Error::RemainingInput)
+    );
+}
@@ -3,6 +3,10 @@
 
 // For some reason deriving `Arbitrary` results in clippy firing a `unit_arg` violation
 #![allow(clippy::unit_arg)]
+// The old `proptest-derive` emits its impls inside an anonymous constant.
+#![allow(non_local_definitions)]
+// `Cow<'_, Vec<_>>` and `Cow<'_, String>` are exercised on purpose below.
+#![allow(clippy::owned_cow)]
 
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -13,7 +17,10 @@ use proptest::prelude::*;
 use proptest_derive::Arbitrary;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use bcs::{from_bytes, serialized_size, to_bytes, Error, MAX_CONTAINER_DEPTH, MAX_SEQUENCE_LENGTH};
+use bcs::{
+    from_bytes, serialize_into_slice, serialized_size, to_bytes, to_bytes_into, to_bytes_presized,
+    to_bytes_with_capacity, Error, MAX_CONTAINER_DEPTH, MAX_SEQUENCE_LENGTH,
+};
 
 fn is_same<T>(t: T)
 where
@@ -23,6 +30,17 @@ where
     let s: T = from_bytes(&bytes).unwrap();
     assert_eq!(t, s);
     assert_eq!(bytes.len(), serialized_size(&t).unwrap());
+    assert_eq!(bytes, to_bytes_presized(&t).unwrap());
+
+    let mut buffer = vec![0; bytes.len()];
+    assert_eq!(serialize_into_slice(&mut buffer, &t), Ok(bytes.len()));
+    assert_eq!(buffer, bytes);
+    if !bytes.is_empty() {
+        assert_eq!(
+            serialize_into_slice(&mut buffer[1..], &t),
+            Err(Error::BufferTooSmall)
+        );
+    }
 }
 
 // TODO deriving `Arbitrary` is currently broken for enum types
@@ -507,6 +525,125 @@ fn path_buf() {
     assert!(path.to_str() == decoded.to_str());
 }
 
+#[test]
+fn preallocated_output() {
+    let value = (vec![7u8; 1000], "hello".to_string());
+    let bytes = to_bytes(&value).unwrap();
+
+    let presized = to_bytes_presized(&value).unwrap();
+    assert_eq!(presized, bytes);
+    assert_eq!(presized.capacity(), bytes.len());
+
+    let with_capacity = to_bytes_with_capacity(&value, 4096).unwrap();
+    assert_eq!(with_capacity, bytes);
+    assert!(with_capacity.capacity() >= 4096);
+
+    // Appending to a buffer with enough capacity does not reallocate.
+    let mut buffer = Vec::with_capacity(2 * bytes.len() + 1);
+    buffer.push(0xff);
+    let ptr = buffer.as_ptr();
+    to_bytes_into(&mut buffer, &value).unwrap();
+    to_bytes_into(&mut buffer, &value).unwrap();
+    assert_eq!(buffer.as_ptr(), ptr);
+    assert_eq!(buffer[0], 0xff);
+    assert_eq!(&buffer[1..], [&bytes[..], &bytes[..]].concat().as_slice());
+
+    // Partial output is discarded on errors.
+    assert_eq!(
+        to_bytes_into(&mut buffer, &(1u8, 1.0f64)),
+        Err(Error::NotSupported("serialize_f64"))
+    );
+    assert_eq!(buffer.len(), 2 * bytes.len() + 1);
+}
+
+#[test]
+fn io_errors_keep_their_kind() {
+    use std::io::{self, ErrorKind, Write};
+
+    /// A non-blocking socket accepting a few bytes at a time.
+    struct Socket(usize);
+
+    impl Write for Socket {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::new(ErrorKind::WouldBlock, "try again"));
+            }
+            let len = buf.len().min(self.0);
+            self.0 -= len;
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let error = bcs::serialize_into(&mut Socket(3), &[1u32, 2u32]).unwrap_err();
+    assert_eq!(error.io_kind(), Some(ErrorKind::WouldBlock));
+    assert_eq!(error.to_string(), "I/O error: try again");
+    assert_eq!(
+        error.clone(),
+        Error::Io(bcs::IoError::new(ErrorKind::WouldBlock, "try again"))
+    );
+    assert_ne!(
+        error,
+        Error::Io(bcs::IoError::new(ErrorKind::BrokenPipe, "try again"))
+    );
+    match &error {
+        Error::Io(io_error) => {
+            assert_eq!(io_error.get_ref().unwrap().kind(), ErrorKind::WouldBlock)
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(Error::Eof.io_kind(), None);
+}
+
+#[test]
+fn error_classification() {
+    use bcs::ErrorCode;
+
+    let malformed = vec![
+        from_bytes::<u32>(&[1, 2]).unwrap_err(),
+        from_bytes::<bool>(&[2]).unwrap_err(),
+        from_bytes::<Option<u8>>(&[2]).unwrap_err(),
+        from_bytes::<String>(&[1, 0xff]).unwrap_err(),
+        from_bytes::<Vec<u8>>(&[0x80, 0]).unwrap_err(),
+        from_bytes::<Vec<u8>>(&[0x80, 0x80, 0x80, 0x80, 0x10]).unwrap_err(),
+        from_bytes::<BTreeMap<u8, u8>>(&[2, 1, 0, 1, 0]).unwrap_err(),
+        from_bytes::<E>(&[4]).unwrap_err(),
+        from_bytes::<u8>(&[1, 2]).unwrap_err(),
+    ];
+    for error in &malformed {
+        assert!(error.is_malformed_input(), "{:?}", error);
+        assert!(!error.is_limit_exceeded() && !error.is_unsupported_type() && !error.is_io());
+    }
+    assert_eq!(malformed[7].code(), ErrorCode::Custom);
+
+    let limits = vec![
+        from_bytes::<Vec<u8>>(&[0x80, 0x80, 0x80, 0x80, 0x08]).unwrap_err(),
+        from_bytes::<List<()>>(&[1; MAX_CONTAINER_DEPTH + 1]).unwrap_err(),
+        bcs::serialize_into_slice(&mut [0; 1], &1u16).unwrap_err(),
+    ];
+    for error in &limits {
+        assert!(error.is_limit_exceeded(), "{:?}", error);
+        assert!(!error.is_malformed_input() && !error.is_unsupported_type() && !error.is_io());
+    }
+
+    let unsupported = to_bytes(&1.0f32).unwrap_err();
+    assert!(unsupported.is_unsupported_type());
+    assert!(!unsupported.is_malformed_input() && !unsupported.is_limit_exceeded());
+    assert_eq!(unsupported.code(), ErrorCode::NotSupported);
+
+    let io = bcs::serialize_into(&mut &mut [0; 1][..], &1u16).unwrap_err();
+    assert!(io.is_io() && !io.is_malformed_input() && !io.is_limit_exceeded());
+    assert_eq!(io.io_kind(), Some(std::io::ErrorKind::WriteZero));
+
+    // Codes are stable.
+    assert_eq!(ErrorCode::Eof.as_u16(), 1);
+    assert_eq!(ErrorCode::NonCanonicalMap.as_u16(), 8);
+    assert_eq!(ErrorCode::BufferTooSmall.as_u16(), 17);
+}
+
 #[derive(Arbitrary, Debug, Deserialize, Serialize, PartialEq)]
 struct Addr([u8; 32]);
 
@@ -542,7 +679,7 @@ fn serde_known_vector() {
     map.insert(vec![20, 21, 89, 105], vec![201, 23, 90]);
 
     let f = Foo {
-        a: u64::max_value(),
+        a: u64::MAX,
         b: vec![100, 99, 88, 77, 66, 55],
         c: b,
         d: true,
@@ -0,0 +1,135 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// The old `proptest-derive` emits its impls inside an anonymous constant.
+#![allow(non_local_definitions)]
+
+use bcs::{serialized_size, to_bytes, Error, MAX_CONTAINER_DEPTH};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// A map serialized with the given entries, duplicates included.
+struct RawMap<K, V>(Vec<(K, V)>);
+
+impl<K: Serialize, V: Serialize> Serialize for RawMap<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+#[test]
+fn map_with_duplicate_keys() {
+    // The first entry of each key is kept.
+    let map = RawMap(vec![
+        ("b", vec![1u8; 300]),
+        ("a", vec![]),
+        ("b", vec![]),
+        ("a", vec![2u8; 5]),
+    ]);
+    assert_eq!(to_bytes(&map).unwrap().len(), 1 + 304 + 3);
+    assert_eq!(serialized_size(&map).unwrap(), 1 + 304 + 3);
+}
+
+#[test]
+fn errors_are_the_same_as_serialization() {
+    let map = RawMap(vec![(1u8, 0.5f32)]);
+    assert_eq!(
+        serialized_size(&map),
+        Err(Error::NotSupported("serialize_f32"))
+    );
+    assert_eq!(serialized_size(&map), to_bytes(&map).map(|b| b.len()));
+
+    #[derive(Serialize)]
+    struct Nested(Option<Box<Nested>>);
+    let mut value = Nested(None);
+    for _ in 0..MAX_CONTAINER_DEPTH {
+        value = Nested(Some(Box::new(value)));
+    }
+    assert_eq!(
+        serialized_size(&value),
+        Err(Error::ExceededContainerDepthLimit("Nested"))
+    );
+    assert_eq!(serialized_size(&value), to_bytes(&value).map(|b| b.len()));
+
+    let mut map = BTreeMap::new();
+    map.insert(vec![0u8; 200], Some(()));
+    assert_eq!(serialized_size(&map), to_bytes(&map).map(|b| b.len()));
+}
+
+#[cfg(feature = "derive")]
+mod derive {
+    use bcs::{serialized_size, BcsSize};
+    use proptest::prelude::*;
+    use proptest_derive::Arbitrary;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Arbitrary, BcsSize, Clone, Debug, Serialize)]
+    struct Point(i32, i32);
+
+    #[derive(Arbitrary, BcsSize, Debug, Serialize)]
+    struct Fixed {
+        id: u128,
+        origin: Point,
+        flags: [bool; 3],
+        pair: (u8, u16),
+        marker: (),
+    }
+
+    #[derive(BcsSize, Debug, Serialize)]
+    struct Unit;
+
+    #[derive(Arbitrary, BcsSize, Debug, Serialize)]
+    enum Shape {
+        Empty,
+        Dot(Point),
+        Polygon { points: Vec<Point> },
+        Named(String),
+        Labels(BTreeMap<String, Option<u64>>),
+    }
+
+    #[derive(Arbitrary, BcsSize, Debug, Serialize)]
+    struct Generic<T> {
+        values: Vec<T>,
+        #[proptest(strategy = "proptest::collection::vec(any::<Shape>(), 0..8)")]
+        shapes: Vec<Shape>,
+        fixed: Box<Fixed>,
+    }
+
+    #[test]
+    fn fixed_sizes() {
+        assert_eq!(Point::FIXED_SIZE, Some(8));
+        assert_eq!(Fixed::FIXED_SIZE, Some(16 + 8 + 3 + 3));
+        assert_eq!(Unit::FIXED_SIZE, Some(0));
+        assert_eq!(Unit.bcs_size(), serialized_size(&Unit).unwrap());
+        assert_eq!(Shape::FIXED_SIZE, None);
+        assert_eq!(<Generic<u8>>::FIXED_SIZE, None);
+        assert_eq!(<[Point; 4]>::FIXED_SIZE, Some(32));
+        assert_eq!(<Vec<Point>>::FIXED_SIZE, None);
+    }
+
+    #[test]
+    fn multi_byte_length() {
+        // The length of a sequence of 200 elements takes two bytes.
+        let points = vec![Point(0, 0); 200];
+        let shape = Shape::Polygon { points };
+        assert_eq!(shape.bcs_size(), 1 + 2 + 1600);
+        assert_eq!(shape.bcs_size(), serialized_size(&shape).unwrap());
+    }
+
+    proptest! {
+        #[test]
+        fn derived_size_agrees_with_serialized_size(value in any::<Generic<Option<String>>>()) {
+            prop_assert_eq!(value.bcs_size(), serialized_size(&value).unwrap());
+        }
+
+        #[test]
+        fn fixed_size_agrees_with_serialized_size(value in any::<Fixed>()) {
+            prop_assert_eq!(Some(serialized_size(&value).unwrap()), Fixed::FIXED_SIZE);
+        }
+    }
+}
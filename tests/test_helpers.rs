@@ -0,0 +1,108 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::test_helpers::{
+    assert_canonical_bytes, assert_canonical_properties, assert_length_prefix_mutations_rejected,
+    assert_serialized_size, assert_trailing_bytes_rejected, assert_truncations_rejected,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+enum Message {
+    Ping,
+    Payload { id: u64, data: Vec<u8> },
+    Batch(Vec<Message>),
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Envelope {
+    sender: [u8; 4],
+    label: String,
+    message: Message,
+    tags: BTreeMap<String, Option<u16>>,
+}
+
+fn envelope() -> Envelope {
+    let mut tags = BTreeMap::new();
+    tags.insert("zeta".to_string(), None);
+    tags.insert("alpha".to_string(), Some(0x1234));
+    Envelope {
+        sender: [192, 168, 1, 1],
+        label: "çå∞".to_string(),
+        message: Message::Batch(vec![
+            Message::Ping,
+            Message::Payload {
+                id: 7,
+                data: vec![0; 200],
+            },
+        ]),
+        tags,
+    }
+}
+
+#[test]
+fn canonical_properties() {
+    assert_canonical_properties(&envelope());
+    assert_canonical_properties(&(vec![7u8, 1], Vec::<u8>::new()));
+    assert_canonical_properties(&vec![vec![(); 300]; 2]);
+    assert_canonical_properties(&Some(String::new()));
+    assert_canonical_properties(&());
+    assert_canonical_properties(&(
+        BTreeMap::from([
+            (vec![1u8], "a".to_string()),
+            (vec![2u8; 130], String::new()),
+        ]),
+        bcs::Uleb32(300),
+        bcs::FixedBytes([1; 40]),
+        "last".to_string(),
+    ));
+}
+
+#[test]
+fn individual_assertions() {
+    let value = envelope();
+    let bytes = bcs::to_bytes(&value).unwrap();
+    assert_canonical_bytes::<Envelope>(&bytes);
+    assert_serialized_size(&value);
+    assert_truncations_rejected(&value);
+    assert_trailing_bytes_rejected(&value);
+    assert_length_prefix_mutations_rejected(&value);
+}
+
+#[test]
+#[should_panic(expected = "re-serializing {4, 5} does not give back the decoded bytes")]
+fn non_canonical_bytes_are_detected() {
+    // Sets are serialized as sequences, which are not checked for order during decoding.
+    assert_canonical_bytes::<BTreeSet<u8>>(&[2, 5, 4]);
+}
+
+/// A type with a lenient deserializer: only the first element of the sequence is used.
+#[derive(Debug, PartialEq)]
+struct FirstByte(u8);
+
+impl Serialize for FirstByte {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        vec![self.0].serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FirstByte {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        match bytes.first() {
+            Some(byte) => Ok(FirstByte(*byte)),
+            None => Err(serde::de::Error::custom("empty sequence")),
+        }
+    }
+}
+
+#[test]
+#[should_panic(
+    expected = "changing the length prefix at byte 0 of (FirstByte(5), Some(0)) from 1 to 2"
+)]
+fn lenient_decoding_is_detected() {
+    // `01 05 01 00` is the encoding of `(FirstByte(5), Some(0))` but `02 05 01 00` decodes into
+    // `(FirstByte(5), None)` as well, whose canonical encoding is `01 05 00`.
+    assert_length_prefix_mutations_rejected(&(FirstByte(5), Some(0u8)));
+}
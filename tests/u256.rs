@@ -0,0 +1,165 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::{from_bytes, serialized_size, to_bytes, BcsSize, Error, U256};
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+fn u256() -> impl Strategy<Value = U256> {
+    any::<[u8; 32]>().prop_map(U256::from_le_bytes)
+}
+
+#[test]
+fn move_encoding() {
+    // Move serializes `u256` values as 32 little-endian bytes.
+    let mut expected = [0; 32];
+    assert_eq!(to_bytes(&U256::ZERO).unwrap(), expected);
+    expected[0] = 1;
+    assert_eq!(to_bytes(&U256::ONE).unwrap(), expected);
+    assert_eq!(to_bytes(&U256::MAX).unwrap(), [0xff; 32]);
+
+    let n: U256 = "452312848583266388373324160190187140051835877600158453279131187530910662656"
+        .parse()
+        .unwrap();
+    let mut expected = [0; 32];
+    expected[31] = 1;
+    assert_eq!(to_bytes(&n).unwrap(), expected);
+
+    let n = U256::from(0x0102_0304_0506_0708u64);
+    let mut expected = vec![8, 7, 6, 5, 4, 3, 2, 1];
+    expected.resize(32, 0);
+    assert_eq!(to_bytes(&n).unwrap(), expected);
+}
+
+#[test]
+fn no_length_prefix() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Balance {
+        owner: u8,
+        amounts: Vec<U256>,
+    }
+
+    let balance = Balance {
+        owner: 1,
+        amounts: vec![U256::ONE, U256::MAX],
+    };
+    let bytes = to_bytes(&balance).unwrap();
+    assert_eq!(bytes.len(), 2 + 2 * 32);
+    assert_eq!(&bytes[..3], &[1, 2, 1]);
+    assert_eq!(from_bytes::<Balance>(&bytes).unwrap(), balance);
+
+    assert_eq!(from_bytes::<U256>(&[0; 31]), Err(Error::Eof));
+    assert_eq!(from_bytes::<U256>(&[0; 33]), Err(Error::RemainingInput));
+}
+
+#[test]
+fn decimal_strings() {
+    assert_eq!(U256::ZERO.to_string(), "0");
+    assert_eq!(
+        U256::MAX.to_string(),
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+    );
+    assert_eq!(
+        format!("{:>5}|{:<4}|{:03}", U256::from(42u8), U256::ONE, U256::ONE),
+        "   42|1   |001"
+    );
+    assert_eq!(format!("{:?}", U256::from(7u8)), "7");
+
+    assert_eq!("007".parse::<U256>().unwrap(), U256::from(7u8));
+    for invalid in &[
+        "",
+        "-1",
+        "+1",
+        "0x10",
+        "1 ",
+        "115792089237316195423570985008687907853269984665640564039457584007913129639936",
+    ] {
+        assert_eq!(
+            invalid.parse::<U256>(),
+            Err(Error::Custom(format!(
+                "invalid 256-bit integer {:?}",
+                invalid
+            )))
+        );
+    }
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(u128::try_from(U256::from(u128::MAX)).unwrap(), u128::MAX);
+    assert_eq!(u8::try_from(U256::from(255u64)).unwrap(), 255);
+    assert_eq!(
+        u8::try_from(U256::from(256u16)),
+        Err(Error::Custom(
+            "integer `256` does not fit in u8".to_string()
+        ))
+    );
+    assert!(u128::try_from(U256::from(u128::MAX).checked_add(U256::ONE).unwrap()).is_err());
+
+    assert_eq!(U256::MAX.checked_add(U256::ONE), None);
+    assert_eq!(U256::ZERO.checked_sub(U256::ONE), None);
+    assert_eq!(U256::MAX.checked_mul(U256::from(2u8)), None);
+    assert_eq!(
+        U256::MAX.checked_sub(U256::MAX.checked_sub(U256::ONE).unwrap()),
+        Some(U256::ONE)
+    );
+    assert!(U256::from(u128::MAX) < U256::from(u128::MAX).checked_add(U256::ONE).unwrap());
+}
+
+proptest! {
+    #[test]
+    fn proptest_encoding(n in u256()) {
+        let bytes = to_bytes(&n)?;
+        prop_assert_eq!(&bytes[..], &n.to_le_bytes()[..]);
+        prop_assert_eq!(from_bytes::<U256>(&bytes)?, n);
+        prop_assert_eq!(bcs::codec::encode(&n)?, bytes.clone());
+        prop_assert_eq!(bcs::codec::decode::<U256>(&bytes)?, n);
+        prop_assert_eq!(serialized_size(&n)?, 32);
+        prop_assert_eq!(n.bcs_size(), 32);
+    }
+
+    #[test]
+    fn proptest_decimal(n in u256()) {
+        prop_assert_eq!(n.to_string().parse::<U256>()?, n);
+    }
+
+    #[test]
+    fn proptest_u128(a in any::<u128>(), b in any::<u128>()) {
+        let (x, y) = (U256::from(a), U256::from(b));
+        prop_assert_eq!(to_bytes(&x)?[..16].to_vec(), to_bytes(&a)?);
+        prop_assert_eq!(x.to_string(), a.to_string());
+        prop_assert_eq!(x.cmp(&y), a.cmp(&b));
+        prop_assert_eq!(u128::try_from(x)?, a);
+        if let Some(sum) = a.checked_add(b) {
+            prop_assert_eq!(x.checked_add(y), Some(U256::from(sum)));
+        }
+        if let Some(product) = a.checked_mul(b) {
+            prop_assert_eq!(x.checked_mul(y), Some(U256::from(product)));
+        }
+        prop_assert_eq!(x.checked_sub(y).map(u128::try_from).transpose()?, a.checked_sub(b));
+    }
+
+    #[test]
+    fn proptest_arithmetic(a in u256(), b in u256()) {
+        if let Some(sum) = a.checked_add(b) {
+            prop_assert_eq!(sum.checked_sub(b), Some(a));
+            prop_assert!(sum >= a && sum >= b);
+        } else {
+            prop_assert!(U256::MAX.checked_sub(a).unwrap() < b);
+        }
+        if let (Some(product), false) = (a.checked_mul(b), b == U256::ZERO) {
+            prop_assert!(product >= a);
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn human_readable() {
+    let n = U256::MAX.checked_sub(U256::ONE).unwrap();
+    let json = serde_json::to_string(&n).unwrap();
+    assert_eq!(json, format!("\"{}\"", n));
+    assert_eq!(serde_json::from_str::<U256>(&json).unwrap(), n);
+    assert!(serde_json::from_str::<U256>("\"-1\"").is_err());
+}
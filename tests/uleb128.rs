@@ -0,0 +1,147 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bcs::{from_bytes, serialized_size, to_bytes, BcsSize, Error, Uleb32, Uleb64};
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+#[test]
+fn encoding() {
+    assert_eq!(to_bytes(&Uleb32(0)).unwrap(), vec![0]);
+    assert_eq!(to_bytes(&Uleb32(127)).unwrap(), vec![0x7f]);
+    assert_eq!(to_bytes(&Uleb32(128)).unwrap(), vec![0x80, 0x01]);
+    assert_eq!(to_bytes(&Uleb32(16384)).unwrap(), vec![0x80, 0x80, 0x01]);
+    assert_eq!(
+        to_bytes(&Uleb32(u32::MAX)).unwrap(),
+        vec![0xff, 0xff, 0xff, 0xff, 0x0f]
+    );
+
+    assert_eq!(to_bytes(&Uleb64(0)).unwrap(), vec![0]);
+    assert_eq!(
+        to_bytes(&Uleb64(1 << 35)).unwrap(),
+        vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x01]
+    );
+    let mut expected = vec![0xff; 9];
+    expected.push(0x01);
+    assert_eq!(to_bytes(&Uleb64(u64::MAX)).unwrap(), expected);
+    assert_eq!(from_bytes::<Uleb64>(&expected).unwrap(), Uleb64(u64::MAX));
+}
+
+#[test]
+fn same_encoding_as_lengths() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        index: Uleb32,
+        values: Vec<u8>,
+    }
+
+    let entry = Entry {
+        index: Uleb32(200),
+        values: vec![0; 200],
+    };
+    let bytes = to_bytes(&entry).unwrap();
+    assert_eq!(&bytes[..2], &bytes[2..4]);
+    assert_eq!(bytes.len(), 204);
+    assert_eq!(from_bytes::<Entry>(&bytes).unwrap(), entry);
+}
+
+#[test]
+fn non_canonical_encodings() {
+    assert_eq!(
+        from_bytes::<Uleb32>(&[0x80, 0x00]),
+        Err(Error::NonCanonicalUleb128Encoding)
+    );
+    assert_eq!(
+        from_bytes::<Uleb64>(&[0xff, 0x80, 0x00]),
+        Err(Error::NonCanonicalUleb128Encoding)
+    );
+}
+
+#[test]
+fn overflows() {
+    assert_eq!(
+        from_bytes::<Uleb32>(&[0x80, 0x80, 0x80, 0x80, 0x10]),
+        Err(Error::IntegerOverflowDuringUleb128Decoding)
+    );
+    assert_eq!(
+        from_bytes::<Uleb32>(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]),
+        Err(Error::IntegerOverflowDuringUleb128Decoding)
+    );
+    let mut bytes = vec![0xff; 9];
+    bytes.push(0x02);
+    assert_eq!(
+        from_bytes::<Uleb64>(&bytes),
+        Err(Error::IntegerOverflowDuringUleb128Decoding)
+    );
+    bytes[9] = 0x81;
+    bytes.push(0x01);
+    assert_eq!(
+        from_bytes::<Uleb64>(&bytes),
+        Err(Error::IntegerOverflowDuringUleb128Decoding)
+    );
+    assert_eq!(from_bytes::<Uleb32>(&[0x80]), Err(Error::Eof));
+}
+
+proptest! {
+    #[test]
+    fn proptest_uleb32(n in any::<u32>()) {
+        let bytes = to_bytes(&Uleb32(n))?;
+        prop_assert_eq!(from_bytes::<Uleb32>(&bytes)?, Uleb32(n));
+        prop_assert_eq!(bcs::codec::encode(&Uleb32(n))?, bytes.clone());
+        prop_assert_eq!(bcs::codec::decode::<Uleb32>(&bytes)?, Uleb32(n));
+        prop_assert_eq!(serialized_size(&Uleb32(n))?, bytes.len());
+        prop_assert_eq!(Uleb32(n).bcs_size(), bytes.len());
+    }
+
+    #[test]
+    fn proptest_uleb64(n in any::<u64>()) {
+        let bytes = to_bytes(&Uleb64(n))?;
+        if let Ok(n) = u32::try_from(n) {
+            prop_assert_eq!(&bytes, &to_bytes(&Uleb32(n))?);
+        }
+        prop_assert_eq!(from_bytes::<Uleb64>(&bytes)?, Uleb64(n));
+        prop_assert_eq!(bcs::codec::encode(&Uleb64(n))?, bytes.clone());
+        prop_assert_eq!(bcs::codec::decode::<Uleb64>(&bytes)?, Uleb64(n));
+        prop_assert_eq!(serialized_size(&Uleb64(n))?, bytes.len());
+        prop_assert_eq!(Uleb64(n).bcs_size(), bytes.len());
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json() {
+    use bcs::json::{bcs_to_json, json_to_bcs};
+    use serde_json::json;
+    use serde_reflection::{Format, Samples, Tracer, TracerConfig};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Counters {
+        small: Uleb32,
+        large: Uleb64,
+    }
+
+    let mut tracer = Tracer::new(TracerConfig::default());
+    tracer.trace_type::<Counters>(&Samples::new()).unwrap();
+    let registry = tracer.registry().unwrap();
+    let format = Format::TypeName("Counters".to_string());
+
+    let counters = Counters {
+        small: Uleb32(300),
+        large: Uleb64(u64::MAX),
+    };
+    let bytes = to_bytes(&counters).unwrap();
+    let value = json!({"small": 300, "large": "18446744073709551615"});
+    assert_eq!(bcs_to_json(&bytes, &format, &registry).unwrap(), value);
+    assert_eq!(json_to_bcs(&value, &format, &registry).unwrap(), bytes);
+    assert_eq!(
+        bcs_to_json(&[0x80, 0x00, 0x00], &format, &registry),
+        Err(Error::NonCanonicalUleb128Encoding)
+    );
+
+    // Other serde formats see plain integers.
+    assert_eq!(
+        serde_json::to_value(&counters).unwrap(),
+        json!({"small": 300, "large": u64::MAX})
+    );
+}
@@ -0,0 +1,111 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Run with `wasm-pack test --node -- --features wasm --test wasm`.
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use bcs::wasm::{from_hex_string, to_hex_string, Schema};
+use serde::{Deserialize, Serialize};
+use serde_reflection::{Tracer, TracerConfig};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Payload {
+    Transfer { to: Vec<u8>, amount: u64 },
+    Burn(u64),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Transaction {
+    sender: [u8; 4],
+    sequence_number: u64,
+    tags: Vec<String>,
+    payload: Payload,
+}
+
+fn schema() -> Schema {
+    let mut tracer = Tracer::new(TracerConfig::default());
+    tracer.trace_simple_type::<Payload>().unwrap();
+    tracer.trace_simple_type::<Transaction>().unwrap();
+    let registry = serde_json::to_string(&tracer.registry().unwrap()).unwrap();
+    Schema::new(&registry, "Transaction").unwrap()
+}
+
+fn js(json: &str) -> JsValue {
+    js_sys::JSON::parse(json).unwrap()
+}
+
+fn message(error: JsValue) -> String {
+    js_sys::Error::from(error).message().into()
+}
+
+const TRANSACTION: &str = r#"{
+    "sender": "01020304",
+    "sequence_number": "7",
+    "tags": ["a", "b"],
+    "payload": {"Transfer": {"to": "abcd", "amount": "1000"}}
+}"#;
+
+fn transaction() -> Transaction {
+    Transaction {
+        sender: [1, 2, 3, 4],
+        sequence_number: 7,
+        tags: vec!["a".to_string(), "b".to_string()],
+        payload: Payload::Transfer {
+            to: vec![0xab, 0xcd],
+            amount: 1000,
+        },
+    }
+}
+
+#[wasm_bindgen_test]
+fn encode_and_decode() {
+    let schema = schema();
+    let bytes = bcs::to_bytes(&transaction()).unwrap();
+
+    assert_eq!(schema.encode(&js(TRANSACTION)).unwrap(), bytes);
+    assert_eq!(
+        schema.serialized_size(&js(TRANSACTION)).unwrap(),
+        bytes.len()
+    );
+    let decoded = schema.decode(&bytes).unwrap();
+    assert_eq!(
+        js_sys::JSON::stringify(&decoded).unwrap(),
+        js_sys::JSON::stringify(&js(TRANSACTION)).unwrap()
+    );
+}
+
+#[wasm_bindgen_test]
+fn errors() {
+    let schema = schema();
+    let bytes = bcs::to_bytes(&transaction()).unwrap();
+
+    assert_eq!(
+        message(schema.decode(&bytes[..bytes.len() - 1]).unwrap_err()),
+        "unexpected end of input"
+    );
+    assert_eq!(
+        message(schema.decode(&[&bytes[..], &[0]].concat()).unwrap_err()),
+        "remaining input"
+    );
+    assert_eq!(
+        message(schema.encode(&js(r#"{"sender": "01020304"}"#)).unwrap_err()),
+        "missing field `sequence_number`"
+    );
+    assert!(schema.encode(&JsValue::UNDEFINED).is_err());
+    assert_eq!(
+        message(Schema::new("{}", "Transaction").err().unwrap()),
+        "unknown container Transaction"
+    );
+}
+
+#[wasm_bindgen_test]
+fn hex() {
+    assert_eq!(to_hex_string(&[0, 0xab, 0xff]), "00abff");
+    assert_eq!(from_hex_string("00ABff").unwrap(), vec![0, 0xab, 0xff]);
+    assert!(from_hex_string("abc").is_err());
+    assert!(from_hex_string("0x00").is_err());
+    assert!(from_hex_string("+f").is_err());
+    assert!(from_hex_string("\u{e9}0").is_err());
+}